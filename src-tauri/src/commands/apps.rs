@@ -1,19 +1,76 @@
 use crate::storage::DbState;
 use serde::{Deserialize, Serialize};
 use std::fs;
+use std::path::{Path, PathBuf};
 use tauri::Manager;
 
-#[tauri::command]
-pub async fn download_app_repo(
-    app_handle: tauri::AppHandle,
-    repo_url: String,
-    folder_name: String,
-) -> Result<(), String> {
+/// Carpeta donde se clonan las apps (`<AppData>/apps`).
+pub fn apps_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
     let app_data = app_handle
         .path()
         .app_data_dir()
         .map_err(|e| e.to_string())?;
-    let target_dir = app_data.join("apps").join(&folder_name);
+    Ok(app_data.join("apps"))
+}
+
+/// Versión instalada de una app según su repositorio git (tag o commit corto).
+pub fn installed_app_version(app_dir: &Path) -> Option<String> {
+    let output = std::process::Command::new("git")
+        .args(["describe", "--tags", "--always"])
+        .current_dir(app_dir)
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if version.is_empty() {
+        None
+    } else {
+        Some(version)
+    }
+}
+
+/// Ruta de instalación de una app, rechazando nombres que escapen de `apps/`.
+pub fn app_install_dir(
+    app_handle: &tauri::AppHandle,
+    folder_name: &str,
+) -> Result<PathBuf, String> {
+    let invalid = folder_name.is_empty()
+        || folder_name == "."
+        || folder_name == ".."
+        || folder_name.contains(['/', '\\']);
+    if invalid {
+        return Err(format!("Nombre de aplicación inválido: '{}'", folder_name));
+    }
+    Ok(apps_dir(app_handle)?.join(folder_name))
+}
+
+/// Sólo se clonan repositorios `https://` o `ssh://`. La URL puede venir del servidor remoto
+/// (`install_app`), así que también se rechaza lo que git interpretaría como opción
+/// (`--upload-pack=...`) o transportes que ejecutan comandos (`ext::`, `file://`).
+pub fn validate_repo_url(repo_url: &str) -> Result<(), String> {
+    let valid = !repo_url.starts_with('-')
+        && (repo_url.starts_with("https://") || repo_url.starts_with("ssh://"))
+        && !repo_url
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control());
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("URL de repositorio no permitida: '{}'", repo_url))
+    }
+}
+
+/// Clona el repositorio de la app. Bloqueante (git).
+pub fn clone_app_repo(
+    app_handle: &tauri::AppHandle,
+    repo_url: &str,
+    folder_name: &str,
+) -> Result<(), String> {
+    validate_repo_url(repo_url)?;
+    let target_dir = app_install_dir(app_handle, folder_name)?;
 
     if target_dir.exists() {
         return Err("La aplicación ya está instalada. Intenta actualizarla.".into());
@@ -23,7 +80,7 @@ pub async fn download_app_repo(
 
     // Ejecutamos git clone. El repo DEBE tener la carpeta dist ya compilada.
     let status = std::process::Command::new("git")
-        .args(["clone", "--depth", "1", &repo_url, "."])
+        .args(["clone", "--depth", "1", "--", repo_url, "."])
         .current_dir(&target_dir)
         .status()
         .map_err(|e| format!("Error al ejecutar git: {}", e))?;
@@ -31,19 +88,15 @@ pub async fn download_app_repo(
     if status.success() {
         Ok(())
     } else {
+        // No dejamos una carpeta a medias que bloquee el siguiente intento
+        let _ = fs::remove_dir_all(&target_dir);
         Err("Error al clonar el repositorio".into())
     }
 }
-#[tauri::command]
-pub async fn update_app_repo(
-    app_handle: tauri::AppHandle,
-    folder_name: String,
-) -> Result<(), String> {
-    let app_data = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e| e.to_string())?;
-    let target_dir = app_data.join("apps").join(&folder_name);
+
+/// Actualiza la app con `git pull`. Bloqueante (git).
+pub fn pull_app_repo(app_handle: &tauri::AppHandle, folder_name: &str) -> Result<(), String> {
+    let target_dir = app_install_dir(app_handle, folder_name)?;
 
     if !target_dir.exists() {
         return Err("La aplicación no está instalada.".into());
@@ -63,41 +116,32 @@ pub async fn update_app_repo(
     }
 }
 
-#[tauri::command]
-pub async fn delete_app_repo(
-    app_handle: tauri::AppHandle,
-    folder_name: String,
-) -> Result<(), String> {
-    let app_data = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e| e.to_string())?;
-    let target_dir = app_data.join("apps").join(&folder_name);
+pub fn remove_app_repo(app_handle: &tauri::AppHandle, folder_name: &str) -> Result<(), String> {
+    let target_dir = app_install_dir(app_handle, folder_name)?;
 
     if !target_dir.exists() {
         return Err("La aplicación no existe.".into());
     }
 
-    fs::remove_dir_all(&target_dir).map_err(|e| format!("Error al eliminar la carpeta: {}", e))?;
-
-    Ok(())
+    fs::remove_dir_all(&target_dir).map_err(|e| format!("Error al eliminar la carpeta: {}", e))
 }
 
-#[tauri::command]
-pub async fn open_app_window(
-    app_handle: tauri::AppHandle,
-    folder_name: String,
-) -> Result<(), String> {
+pub fn open_app_webview(app_handle: &tauri::AppHandle, folder_name: &str) -> Result<(), String> {
     let window_label = format!("app-{}", folder_name);
 
+    // Si ya está abierta, la traemos al frente en lugar de fallar por etiqueta duplicada
+    if let Some(window) = app_handle.get_webview_window(&window_label) {
+        return window.set_focus().map_err(|e| e.to_string());
+    }
+
     // Ahora solo apuntamos a la carpeta de la app.
     // El protocolo se encarga de entrar a /dist/index.html automáticamente.
     let url = format!("sandra-app://localhost/{}/", folder_name);
 
     tauri::WebviewWindowBuilder::new(
-        &app_handle,
+        app_handle,
         &window_label,
-        tauri::WebviewUrl::App(url.parse().unwrap()),
+        tauri::WebviewUrl::App(url.parse().map_err(|_| format!("URL inválida: {}", url))?),
     )
     .title(format!("Sandra App: {}", folder_name))
     .inner_size(1200.0, 800.0)
@@ -108,6 +152,96 @@ pub async fn open_app_window(
     Ok(())
 }
 
+#[tauri::command]
+pub async fn download_app_repo(
+    app_handle: tauri::AppHandle,
+    repo_url: String,
+    folder_name: String,
+) -> Result<(), String> {
+    clone_app_repo(&app_handle, &repo_url, &folder_name)
+}
+
+#[tauri::command]
+pub async fn update_app_repo(
+    app_handle: tauri::AppHandle,
+    folder_name: String,
+) -> Result<(), String> {
+    pull_app_repo(&app_handle, &folder_name)
+}
+
+#[tauri::command]
+pub async fn delete_app_repo(
+    app_handle: tauri::AppHandle,
+    folder_name: String,
+) -> Result<(), String> {
+    remove_app_repo(&app_handle, &folder_name)
+}
+
+#[tauri::command]
+pub async fn open_app_window(
+    app_handle: tauri::AppHandle,
+    folder_name: String,
+) -> Result<(), String> {
+    open_app_webview(&app_handle, &folder_name)
+}
+
+/// Qué acciones sobre apps puede ordenar el servidor remoto en este equipo.
+/// Se guarda como JSON en `config` (clave `remote_app_policy`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteAppPolicy {
+    pub allow_install: bool,
+    pub allow_update: bool,
+    pub allow_remove: bool,
+    pub allow_open: bool,
+}
+
+impl Default for RemoteAppPolicy {
+    fn default() -> Self {
+        Self {
+            allow_install: true,
+            allow_update: true,
+            allow_remove: true,
+            allow_open: true,
+        }
+    }
+}
+
+pub fn load_remote_app_policy(conn: &rusqlite::Connection) -> RemoteAppPolicy {
+    conn.query_row(
+        "SELECT value FROM config WHERE key = 'remote_app_policy'",
+        [],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|json| serde_json::from_str(&json).ok())
+    .unwrap_or_default()
+}
+
+#[tauri::command]
+pub async fn get_remote_app_policy(
+    state: tauri::State<'_, DbState>,
+) -> Result<RemoteAppPolicy, String> {
+    let conn = state.0.lock().unwrap();
+    Ok(load_remote_app_policy(&conn))
+}
+
+#[tauri::command]
+pub async fn set_remote_app_policy(
+    state: tauri::State<'_, DbState>,
+    policy: RemoteAppPolicy,
+) -> Result<(), String> {
+    let conn = state.0.lock().unwrap();
+    let json = serde_json::to_string(&policy).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO config (key, value) VALUES ('remote_app_policy', ?1)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        [&json],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DesktopApp {
     pub id: Option<i32>,
@@ -122,13 +256,19 @@ pub struct DesktopApp {
     pub username: Option<String>,
     pub password: Option<String>,
     pub token: Option<String>,
+    /// Conexión a la que se enruta su tráfico `/v1/`. `None` = la primera activa.
+    pub connection_id: Option<i64>,
 }
 
 #[tauri::command]
 pub async fn get_all_apps(state: tauri::State<'_, DbState>) -> Result<Vec<DesktopApp>, String> {
     let conn = state.0.lock().unwrap();
+    query_all_apps(&conn)
+}
+
+pub fn query_all_apps(conn: &rusqlite::Connection) -> Result<Vec<DesktopApp>, String> {
     let mut stmt = conn
-        .prepare("SELECT id, app_id, name, icon, repo, external_url, is_installed, is_favorite, description, username, password, token FROM desktop_apps ORDER BY name ASC")
+        .prepare("SELECT id, app_id, name, icon, repo, external_url, is_installed, is_favorite, description, username, password, token, connection_id FROM desktop_apps ORDER BY name ASC")
         .map_err(|e| e.to_string())?;
 
     let rows = stmt
@@ -146,6 +286,7 @@ pub async fn get_all_apps(state: tauri::State<'_, DbState>) -> Result<Vec<Deskto
                 username: row.get(9).unwrap_or(None),
                 password: row.get(10).unwrap_or(None),
                 token: row.get(11).unwrap_or(None),
+                connection_id: row.get(12).unwrap_or(None),
             })
         })
         .map_err(|e| e.to_string())?;
@@ -162,7 +303,7 @@ pub async fn get_all_apps(state: tauri::State<'_, DbState>) -> Result<Vec<Deskto
 pub async fn create_app(state: tauri::State<'_, DbState>, app: DesktopApp) -> Result<i64, String> {
     let conn = state.0.lock().unwrap();
     conn.execute(
-        "INSERT INTO desktop_apps (app_id, name, icon, repo, external_url, is_installed, is_favorite, description, username, password, token) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        "INSERT INTO desktop_apps (app_id, name, icon, repo, external_url, is_installed, is_favorite, description, username, password, token, connection_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
         (
             &app.app_id,
             &app.name,
@@ -175,6 +316,7 @@ pub async fn create_app(state: tauri::State<'_, DbState>, app: DesktopApp) -> Re
             &app.username,
             &app.password,
             &app.token,
+            &app.connection_id,
         ),
     )
     .map_err(|e| e.to_string())?;
@@ -186,7 +328,7 @@ pub async fn create_app(state: tauri::State<'_, DbState>, app: DesktopApp) -> Re
 pub async fn update_app(state: tauri::State<'_, DbState>, app: DesktopApp) -> Result<(), String> {
     let conn = state.0.lock().unwrap();
     conn.execute(
-        "UPDATE desktop_apps SET name = ?1, icon = ?2, repo = ?3, external_url = ?4, is_installed = ?5, is_favorite = ?6, description = ?7, username = ?8, password = ?9, token = ?10 WHERE app_id = ?11",
+        "UPDATE desktop_apps SET name = ?1, icon = ?2, repo = ?3, external_url = ?4, is_installed = ?5, is_favorite = ?6, description = ?7, username = ?8, password = ?9, token = ?10, connection_id = ?11 WHERE app_id = ?12",
         (
             &app.name,
             &app.icon,
@@ -198,6 +340,7 @@ pub async fn update_app(state: tauri::State<'_, DbState>, app: DesktopApp) -> Re
             &app.username,
             &app.password,
             &app.token,
+            &app.connection_id,
             &app.app_id,
         ),
     )
@@ -205,6 +348,27 @@ pub async fn update_app(state: tauri::State<'_, DbState>, app: DesktopApp) -> Re
     Ok(())
 }
 
+/// Fija (o libera con `None`) la conexión por la que se enruta el tráfico `/v1/` de la app.
+#[tauri::command]
+pub async fn set_app_connection(
+    state: tauri::State<'_, DbState>,
+    app_id: String,
+    connection_id: Option<i64>,
+) -> Result<(), String> {
+    let conn = state.0.lock().unwrap();
+    let updated = conn
+        .execute(
+            "UPDATE desktop_apps SET connection_id = ?1 WHERE app_id = ?2",
+            rusqlite::params![connection_id, app_id],
+        )
+        .map_err(|e| e.to_string())?;
+
+    if updated == 0 {
+        return Err(format!("La aplicación '{}' no existe.", app_id));
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn delete_app(state: tauri::State<'_, DbState>, app_id: String) -> Result<(), String> {
     let conn = state.0.lock().unwrap();
@@ -230,3 +394,31 @@ pub async fn verify_app_installed(
 
     Ok(path.exists())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_https_and_ssh_repos_are_cloned() {
+        assert!(validate_repo_url("https://github.com/code-epic/gdoc.git").is_ok());
+        assert!(validate_repo_url("ssh://git@github.com/code-epic/gdoc.git").is_ok());
+
+        for url in [
+            "--upload-pack=touch /tmp/x",
+            "-c core.sshCommand=sh",
+            "ext::sh -c touch% /tmp/x",
+            "file:///etc",
+            "/home/user/repo",
+            "git@github.com:code-epic/gdoc.git",
+            "https://github.com/a b",
+            "",
+        ] {
+            assert!(
+                validate_repo_url(url).is_err(),
+                "{} debería rechazarse",
+                url
+            );
+        }
+    }
+}
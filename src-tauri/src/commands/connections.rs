@@ -1,4 +1,7 @@
-use crate::remote_control;
+use crate::connection_manager::{ConnectionManager, ConnectionSnapshot, AD_HOC_CONNECTION_ID};
+use crate::proxy_handler::{validate_connection, MockConfig, RouteRule};
+use crate::remote_control::outbox::{self, QueuedMessage};
+use crate::remote_control::ReconnectPolicy;
 use crate::storage::DbState;
 use local_ip_address::local_ip;
 use reqwest::Client;
@@ -6,8 +9,7 @@ use rusqlite::OptionalExtension;
 use serde::{Deserialize, Serialize};
 use std::net::{TcpStream, ToSocketAddrs};
 use std::time::Duration;
-use tauri::{AppHandle, Emitter};
-use uuid::Uuid;
+use tauri::AppHandle;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Connection {
@@ -21,33 +23,90 @@ pub struct Connection {
     pub wss_host: Option<String>,
     pub wss_port: Option<u16>,
     pub is_connected: Option<bool>,
+    pub reconnect_policy: Option<ReconnectPolicy>,
+    /// Base del tráfico `/v1/` (p. ej. `http://localhost:8080/gateway`).
+    /// Sin ella se usa `https://{ip_address}:{port}`.
+    pub api_base_url: Option<String>,
+    /// Reglas evaluadas en orden antes de la URL base.
+    pub route_rules: Option<Vec<RouteRule>>,
+    /// Guardar las respuestas `GET /v1/` para servirlas si el servidor cae.
+    pub offline_cache: Option<bool>,
+    /// Encolar las escrituras `/v1/` que fallen por red y reenviarlas al volver.
+    pub offline_writes: Option<bool>,
+    /// Modo simulado: `/v1/` se responde desde fixtures locales sin servidor.
+    pub mock: Option<MockConfig>,
 }
 
-#[tauri::command]
-pub async fn get_or_create_client_id(state: tauri::State<'_, DbState>) -> Result<String, String> {
-    let conn = state.0.lock().unwrap();
-
-    let existing: Option<String> = conn
-        .query_row(
-            "SELECT value FROM config WHERE key = 'client_id'",
-            [],
-            |row| row.get(0),
-        )
-        .optional()
-        .map_err(|e| e.to_string())?;
-
-    if let Some(uuid) = existing {
-        return Ok(uuid);
+/// Lee una columna JSON opcional. Si está corrupta se avisa y se ignora: el perfil sigue
+/// cargando con el valor por defecto en lugar de perderla sin dejar rastro.
+fn json_column<T: serde::de::DeserializeOwned>(
+    row: &rusqlite::Row,
+    index: usize,
+    id: i64,
+    column: &str,
+) -> Option<T> {
+    let json: String = row.get::<_, Option<String>>(index).ok().flatten()?;
+    match serde_json::from_str(&json) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            println!(
+                "⚠️ [Conexiones] '{}' ilegible en la conexión {}, se ignora: {}",
+                column, id, e
+            );
+            None
+        }
     }
+}
 
-    let new_uuid = Uuid::new_v4().to_string();
-    conn.execute(
-        "INSERT INTO config (key, value) VALUES ('client_id', ?1)",
-        [&new_uuid],
-    )
-    .map_err(|e| e.to_string())?;
+/// Columnas en el orden que espera `Connection::from_row`.
+pub const CONNECTION_COLUMNS: &str = "id, name, ip_address, port, username, password, last_connected, wss_host, wss_port, is_connected, reconnect_policy, api_base_url, route_rules, offline_cache, offline_writes, mock_config";
+
+impl Connection {
+    pub fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        let is_connected_val: Option<i32> = row.get(9).ok();
+        let is_connected = matches!(is_connected_val, Some(1));
+
+        // Política, reglas y modo simulado se guardan como JSON
+        let id: i64 = row.get(0)?;
+        let reconnect_policy = json_column::<ReconnectPolicy>(row, 10, id, "reconnect_policy")
+            .filter(|policy| match policy.validate() {
+                Ok(()) => true,
+                Err(e) => {
+                    println!(
+                        "⚠️ [Conexiones] Política de reconexión inválida en la conexión {}, se usa la por defecto: {}",
+                        id, e
+                    );
+                    false
+                }
+            });
+        let route_rules = json_column(row, 12, id, "route_rules");
+        let mock = json_column(row, 15, id, "mock_config");
+
+        Ok(Connection {
+            id: Some(row.get(0)?),
+            name: row.get(1)?,
+            ip_address: row.get(2)?,
+            port: row.get(3)?,
+            username: row.get(4)?,
+            password: row.get(5)?,
+            last_connected: row.get(6)?,
+            wss_host: row.get(7).ok(),
+            wss_port: row.get(8).ok(),
+            is_connected: Some(is_connected),
+            reconnect_policy,
+            api_base_url: row.get(11).unwrap_or(None),
+            route_rules,
+            offline_cache: row.get(13).unwrap_or(None),
+            offline_writes: row.get(14).unwrap_or(None),
+            mock,
+        })
+    }
+}
 
-    Ok(new_uuid)
+#[tauri::command]
+pub async fn get_or_create_client_id(state: tauri::State<'_, DbState>) -> Result<String, String> {
+    let conn = state.0.lock().unwrap();
+    crate::storage::get_or_create_client_id(&conn)
 }
 
 #[tauri::command]
@@ -103,74 +162,111 @@ pub async fn save_connection(
         }
     }
 
-    let connected_int = if conn_data.is_connected.unwrap_or(false) {
-        1
-    } else {
-        0
+    validate_connection(&conn_data)?;
+
+    let policy_json = match &conn_data.reconnect_policy {
+        Some(p) => Some(serde_json::to_string(p).map_err(|e| e.to_string())?),
+        None => None,
+    };
+    let rules_json = match &conn_data.route_rules {
+        Some(r) => Some(serde_json::to_string(r).map_err(|e| e.to_string())?),
+        None => None,
+    };
+    let mock_json = match &conn_data.mock {
+        Some(m) => Some(serde_json::to_string(m).map_err(|e| e.to_string())?),
+        None => None,
     };
 
     if let Some(id) = conn_data.id {
         conn.execute(
-            "UPDATE connections SET name=?1, ip_address=?2, port=?3, username=?4, password=?5, wss_host=?6, wss_port=?7, is_connected=?8 WHERE id=?9",
-            rusqlite::params![conn_data.name, conn_data.ip_address, conn_data.port, conn_data.username, conn_data.password, conn_data.wss_host, conn_data.wss_port, connected_int, id],
+            "UPDATE connections SET name=?1, ip_address=?2, port=?3, username=?4, password=?5, wss_host=?6, wss_port=?7, reconnect_policy=?8, api_base_url=?9, route_rules=?10, offline_cache=?11, offline_writes=?12, mock_config=?13 WHERE id=?14",
+            rusqlite::params![conn_data.name, conn_data.ip_address, conn_data.port, conn_data.username, conn_data.password, conn_data.wss_host, conn_data.wss_port, policy_json, conn_data.api_base_url, rules_json, conn_data.offline_cache.unwrap_or(false), conn_data.offline_writes.unwrap_or(false), mock_json, id],
         ).map_err(|e| e.to_string())?;
     } else {
         conn.execute(
-            "INSERT INTO connections (name, ip_address, port, username, password, wss_host, wss_port, is_connected) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-            rusqlite::params![conn_data.name, conn_data.ip_address, conn_data.port, conn_data.username, conn_data.password, conn_data.wss_host, conn_data.wss_port, connected_int],
+            "INSERT INTO connections (name, ip_address, port, username, password, wss_host, wss_port, reconnect_policy, api_base_url, route_rules, offline_cache, offline_writes, mock_config) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            rusqlite::params![conn_data.name, conn_data.ip_address, conn_data.port, conn_data.username, conn_data.password, conn_data.wss_host, conn_data.wss_port, policy_json, conn_data.api_base_url, rules_json, conn_data.offline_cache.unwrap_or(false), conn_data.offline_writes.unwrap_or(false), mock_json],
         ).map_err(|e| e.to_string())?;
     }
     Ok(())
 }
 
 #[tauri::command]
-pub async fn get_connections(state: tauri::State<'_, DbState>) -> Result<Vec<Connection>, String> {
+pub async fn get_connections(
+    state: tauri::State<'_, DbState>,
+    manager: tauri::State<'_, ConnectionManager>,
+) -> Result<Vec<Connection>, String> {
     let conn = state.0.lock().unwrap();
-    let mut stmt = conn.prepare("SELECT id, name, ip_address, port, username, password, last_connected, wss_host, wss_port, is_connected FROM connections ORDER BY id DESC").map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM connections ORDER BY id DESC",
+            CONNECTION_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
 
     let rows = stmt
-        .query_map([], |row| {
-            let is_connected_val: Option<i32> = row.get(9).ok();
-            let is_connected = matches!(is_connected_val, Some(1));
-
-            Ok(Connection {
-                id: Some(row.get(0)?),
-                name: row.get(1)?,
-                ip_address: row.get(2)?,
-                port: row.get(3)?,
-                username: row.get(4)?,
-                password: row.get(5)?,
-                last_connected: row.get(6)?,
-                wss_host: row.get(7).ok(),
-                wss_port: row.get(8).ok(),
-                is_connected: Some(is_connected),
-            })
-        })
+        .query_map([], Connection::from_row)
         .map_err(|e| e.to_string())?;
 
     let mut list = Vec::new();
     for r in rows {
-        list.push(r.unwrap());
+        let mut item = r.unwrap();
+        // El estado real lo lleva el ConnectionManager, no la columna de la BD
+        item.is_connected = item
+            .id
+            .map(|id| manager.is_connected(id as i64))
+            .or(Some(false));
+        list.push(item);
     }
     Ok(list)
 }
 
 #[tauri::command]
-pub async fn delete_connection(state: tauri::State<'_, DbState>, id: i32) -> Result<(), String> {
+pub async fn delete_connection(
+    state: tauri::State<'_, DbState>,
+    manager: tauri::State<'_, ConnectionManager>,
+    app_handle: AppHandle,
+    id: i32,
+) -> Result<(), String> {
+    // Primero cerramos el listener para que no vuelva a escribir en la fila
+    manager.remove(&app_handle, id as i64);
+
     let conn = state.0.lock().unwrap();
     conn.execute("DELETE FROM connections WHERE id = ?1", [id])
         .map_err(|e| e.to_string())?;
+    // Lo pendiente de enviar a ese servidor ya no tiene destino
+    conn.execute("DELETE FROM outbound_queue WHERE connection_id = ?1", [id])
+        .map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM proxy_cache WHERE connection_id = ?1", [id])
+        .map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM proxy_write_queue WHERE connection_id = ?1",
+        [id],
+    )
+    .map_err(|e| e.to_string())?;
+    // Las apps enrutadas a esta conexión vuelven a la ruta por defecto
+    conn.execute(
+        "UPDATE desktop_apps SET connection_id = NULL WHERE connection_id = ?1",
+        [id],
+    )
+    .map_err(|e| e.to_string())?;
     Ok(())
 }
 
 #[tauri::command]
 pub async fn connect_to_server(
-    state: tauri::State<'_, DbState>,
-    conn_task: tauri::State<'_, crate::ConnectionTask>,
+    manager: tauri::State<'_, ConnectionManager>,
     app_handle: AppHandle,
     conn_data: Connection,
     client_id: String,
 ) -> Result<(), String> {
+    // La política llega del frontend sin pasar por `save_connection`
+    if let Some(policy) = &conn_data.reconnect_policy {
+        policy
+            .validate()
+            .map_err(|e| format!("Política de reconexión: {}", e))?;
+    }
+
     let host = conn_data
         .wss_host
         .clone()
@@ -184,67 +280,72 @@ pub async fn connect_to_server(
 
     println!("🔌 Iniciando conexión bajo demanda a: {}", url);
 
-    // 1. Abort previous task if any
-    {
-        let mut task_guard = conn_task.0.lock().unwrap();
-        if let Some(handle) = task_guard.take() {
-            println!("⚠️ Abortando tarea de conexión anterior...");
-            handle.abort();
-        }
-    }
+    // La conexión sin perfil guardado se gestiona bajo una clave reservada
+    let connection_id = conn_data
+        .id
+        .map(|n| n as i64)
+        .unwrap_or(AD_HOC_CONNECTION_ID);
+    // El manager detiene la conexión anterior y refleja el estado en la BD
+    manager.start(&app_handle, connection_id, url, conn_data.reconnect_policy);
 
-    // Update DB status
-    let conn = state.0.lock().unwrap();
-    // Reset all others
-    let _ = conn.execute("UPDATE connections SET is_connected = 0", []);
-    if let Some(id) = conn_data.id {
-        println!(
-            "🔌 [DB] Marking connection ID={} as ACTIVE (is_connected=1)",
-            id
-        );
-        let res = conn.execute(
-            "UPDATE connections SET is_connected = 1 WHERE id = ?1",
-            [id],
-        );
-        if let Err(e) = res {
-            println!("❌ [DB] Error updating is_connected: {}", e);
-        }
-    } else {
-        println!("❌ [DB] Cannot mark active: conn_data.id is NONE");
-    }
+    Ok(())
+}
 
-    // Capture ID for the background thread
-    let conn_id_i64 = conn_data.id.map(|n| n as i64);
+/// Fuerza un reintento inmediato de la conexión indicada (o de todas).
+#[tauri::command]
+pub async fn retry_now(
+    manager: tauri::State<'_, ConnectionManager>,
+    app_handle: AppHandle,
+    connection_id: Option<i64>,
+) -> Result<(), String> {
+    manager.retry_now(&app_handle, connection_id)
+}
 
-    // 2. Spawn new task and save handle
-    let handle = tauri::async_runtime::spawn(async move {
-        remote_control::start_remote_listener(url, app_handle, conn_id_i64).await;
-    });
+#[tauri::command]
+pub async fn get_connection_state(
+    manager: tauri::State<'_, ConnectionManager>,
+    connection_id: Option<i64>,
+) -> Result<Vec<ConnectionSnapshot>, String> {
+    Ok(match connection_id {
+        Some(id) => vec![manager.snapshot(id)],
+        None => manager.snapshots(),
+    })
+}
 
-    {
-        let mut task_guard = conn_task.0.lock().unwrap();
-        *task_guard = Some(handle);
-    }
+/// Mensajes pendientes de enviar al servidor (de una conexión o de todas).
+#[tauri::command]
+pub async fn get_outbound_queue(
+    state: tauri::State<'_, DbState>,
+    connection_id: Option<i64>,
+) -> Result<Vec<QueuedMessage>, String> {
+    let conn = state.0.lock().unwrap();
+    outbox::list(&conn, connection_id)
+}
 
-    Ok(())
+#[tauri::command]
+pub async fn clear_outbound_queue(
+    state: tauri::State<'_, DbState>,
+    connection_id: Option<i64>,
+) -> Result<usize, String> {
+    let conn = state.0.lock().unwrap();
+    outbox::clear(&conn, connection_id)
 }
 
 #[tauri::command]
 pub async fn disconnect_from_server(
-    state: tauri::State<'_, DbState>,
-    conn_task: tauri::State<'_, crate::ConnectionTask>,
+    manager: tauri::State<'_, ConnectionManager>,
     app_handle: AppHandle,
     conn_data: Connection,
     client_id: String,
 ) -> Result<(), String> {
-    // 1. Abort background task immediately
-    {
-        let mut task_guard = conn_task.0.lock().unwrap();
-        if let Some(handle) = task_guard.take() {
-            println!("⏹️ Deteniendo listener background...");
-            handle.abort();
-        }
-    }
+    let connection_id = conn_data
+        .id
+        .map(|n| n as i64)
+        .unwrap_or(AD_HOC_CONNECTION_ID);
+
+    // 1. Abort background task immediately (Disconnecting -> Idle, BD incluida)
+    println!("⏹️ Deteniendo listener background...");
+    manager.stop(&app_handle, connection_id);
 
     let host = conn_data
         .wss_host
@@ -257,18 +358,7 @@ pub async fn disconnect_from_server(
 
     println!("🔌 Desconectando y notificando servicio logout: {}", url);
 
-    // 2. Update DB immediately
-    {
-        let conn = state.0.lock().unwrap();
-        if let Some(id) = conn_data.id {
-            let _ = conn.execute(
-                "UPDATE connections SET is_connected = 0 WHERE id = ?1",
-                [id],
-            );
-        }
-    }
-
-    // 3. Notify Server
+    // 2. Notify Server
     let url_clone = url.clone();
     tauri::async_runtime::spawn(async move {
         let client = Client::builder()
@@ -281,8 +371,5 @@ pub async fn disconnect_from_server(
         }
     });
 
-    // 4. Emit Disconnected
-    let _ = app_handle.emit("connection-status", "disconnected");
-
     Ok(())
 }
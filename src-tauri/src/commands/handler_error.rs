@@ -129,6 +129,10 @@ pub async fn get_app_logs(
 #[tauri::command]
 pub async fn get_db_stats(state: tauri::State<'_, DbState>) -> Result<DbStats, String> {
     let conn = state.0.lock().unwrap();
+    collect_db_stats(&conn)
+}
+
+pub fn collect_db_stats(conn: &rusqlite::Connection) -> Result<DbStats, String> {
     // Consultamos las tablas del sistema excluyendo las internas de sqlite
     let mut stmt = conn
         .prepare("SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%'")
@@ -3,5 +3,6 @@ pub mod connections;
 pub mod handler_error;
 pub mod monitor;
 pub mod pdf;
+pub mod proxy;
 pub mod system;
 pub mod window;
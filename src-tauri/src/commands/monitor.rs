@@ -2,7 +2,7 @@ use mac_address::get_mac_address;
 use serde::Serialize;
 use sysinfo::{Disks, System};
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct SystemStats {
     pub disk_total: u64,
     pub disk_free: u64,
@@ -12,6 +12,11 @@ pub struct SystemStats {
 
 #[tauri::command]
 pub async fn get_system_telemetry() -> Result<SystemStats, String> {
+    Ok(collect_system_stats())
+}
+
+/// Lectura síncrona (y algo costosa) del hardware; desde código async usar `spawn_blocking`.
+pub fn collect_system_stats() -> SystemStats {
     let mut sys = System::new_all();
     sys.refresh_all();
 
@@ -37,10 +42,10 @@ pub async fn get_system_telemetry() -> Result<SystemStats, String> {
         Err(_) => "Unknown MAC".to_string(),
     };
 
-    Ok(SystemStats {
+    SystemStats {
         disk_total: total_space,
         disk_free: available_space,
         os_info,
         mac_address: mac,
-    })
+    }
 }
@@ -0,0 +1,218 @@
+use crate::proxy_handler::{
+    CacheStats, CaptureEntry, CaptureOptions, DeniedAttempt, Download, ExternalSessionInfo,
+    ExternalSessions, QueuedWrite, SocketBridge, SocketEvent, StoredCookie, TrafficCapture,
+};
+use crate::storage::DbState;
+use tauri::ipc::Channel;
+
+/// Sesiones del proxy externo abiertas, una por (webview, app).
+#[tauri::command]
+pub fn get_external_sessions(
+    sessions: tauri::State<'_, ExternalSessions>,
+) -> Vec<ExternalSessionInfo> {
+    sessions.list()
+}
+
+/// Cierra las sesiones externas de un webview (o todas) para que dejen de capturar peticiones.
+#[tauri::command]
+pub fn clear_external_sessions(
+    sessions: tauri::State<'_, ExternalSessions>,
+    webview: Option<String>,
+) -> usize {
+    sessions.clear(webview.as_deref())
+}
+
+/// Cookies guardadas por el proxy externo, filtradas por app y/o sitio (incluye subdominios).
+#[tauri::command]
+pub fn get_proxy_cookies(
+    state: tauri::State<'_, DbState>,
+    app_id: Option<String>,
+    site: Option<String>,
+) -> Result<Vec<StoredCookie>, String> {
+    let conn = state.0.lock().map_err(|e| e.to_string())?;
+    crate::proxy_handler::list_cookies(&conn, app_id.as_deref(), site.as_deref())
+}
+
+/// Borra las cookies de una app y/o sitio (cierra sus sesiones). Sin filtros, vacía el jar.
+#[tauri::command]
+pub fn clear_proxy_cookies(
+    state: tauri::State<'_, DbState>,
+    app_id: Option<String>,
+    site: Option<String>,
+) -> Result<usize, String> {
+    let conn = state.0.lock().map_err(|e| e.to_string())?;
+    crate::proxy_handler::clear_cookies(&conn, app_id.as_deref(), site.as_deref())
+}
+
+/// Últimos intentos del proxy externo bloqueados por la política de destinos.
+#[tauri::command]
+pub fn get_external_proxy_denials(
+    state: tauri::State<'_, DbState>,
+    limit: Option<i64>,
+) -> Result<Vec<DeniedAttempt>, String> {
+    let conn = state.0.lock().map_err(|e| e.to_string())?;
+    crate::proxy_handler::recent_denials(&conn, limit.unwrap_or(100).clamp(1, 1000))
+}
+
+/// Tráfico capturado por el proxy (de una app o de todas) para el inspector.
+#[tauri::command]
+pub fn get_proxy_traffic(
+    capture: tauri::State<'_, TrafficCapture>,
+    app_id: Option<String>,
+    limit: Option<usize>,
+) -> Vec<CaptureEntry> {
+    capture.list(app_id.as_deref(), limit)
+}
+
+#[tauri::command]
+pub fn clear_proxy_traffic(
+    capture: tauri::State<'_, TrafficCapture>,
+    app_id: Option<String>,
+) -> usize {
+    capture.clear(app_id.as_deref())
+}
+
+#[tauri::command]
+pub fn get_proxy_capture_options(capture: tauri::State<'_, TrafficCapture>) -> CaptureOptions {
+    capture.options()
+}
+
+/// Activa/desactiva la captura y el guardado de cuerpos.
+#[tauri::command]
+pub fn set_proxy_capture_options(
+    capture: tauri::State<'_, TrafficCapture>,
+    options: CaptureOptions,
+) -> CaptureOptions {
+    capture.set_options(options)
+}
+
+/// Exporta el tráfico capturado (de una app o de todas) como fichero HAR 1.2.
+#[tauri::command]
+pub fn export_proxy_har(
+    app_handle: tauri::AppHandle,
+    capture: tauri::State<'_, TrafficCapture>,
+    app_id: Option<String>,
+    target_path: String,
+) -> Result<usize, String> {
+    let entries = capture.list(app_id.as_deref(), None);
+    let version = app_handle.package_info().version.to_string();
+    let har = crate::proxy_handler::to_har(&entries, &version);
+
+    let json = serde_json::to_vec_pretty(&har).map_err(|e| e.to_string())?;
+    std::fs::write(&target_path, json).map_err(|e| e.to_string())?;
+    println!(
+        "📦 HAR exportado ({} peticiones): {}",
+        entries.len(),
+        target_path
+    );
+    Ok(entries.len())
+}
+
+/// Tamaño actual de la caché offline del proxy `/v1/`.
+#[tauri::command]
+pub fn get_proxy_cache_stats(state: tauri::State<'_, DbState>) -> Result<CacheStats, String> {
+    let conn = state.0.lock().map_err(|e| e.to_string())?;
+    crate::proxy_handler::cache_stats(&conn)
+}
+
+/// Vacía la caché offline de una conexión y/o app (sin filtros, toda).
+#[tauri::command]
+pub fn purge_proxy_cache(
+    state: tauri::State<'_, DbState>,
+    connection_id: Option<i64>,
+    app_id: Option<String>,
+) -> Result<usize, String> {
+    let conn = state.0.lock().map_err(|e| e.to_string())?;
+    crate::proxy_handler::purge_cache(&conn, connection_id, app_id.as_deref())
+}
+
+/// Escrituras `/v1/` encoladas sin conexión (de una conexión y/o app).
+#[tauri::command]
+pub fn get_write_queue(
+    state: tauri::State<'_, DbState>,
+    connection_id: Option<i64>,
+    app_id: Option<String>,
+) -> Result<Vec<QueuedWrite>, String> {
+    let conn = state.0.lock().map_err(|e| e.to_string())?;
+    crate::proxy_handler::list_queued_writes(&conn, connection_id, app_id.as_deref())
+}
+
+/// Reintenta ya el reenvío de la cola de una conexión.
+#[tauri::command]
+pub fn replay_write_queue(app_handle: tauri::AppHandle, connection_id: i64) {
+    crate::proxy_handler::spawn_replay(&app_handle, connection_id);
+}
+
+#[tauri::command]
+pub fn discard_queued_write(
+    state: tauri::State<'_, DbState>,
+    queue_id: String,
+) -> Result<usize, String> {
+    let conn = state.0.lock().map_err(|e| e.to_string())?;
+    crate::proxy_handler::discard_queued_write(&conn, &queue_id)
+}
+
+/// Abre un WebSocket `/v1/ws/...` hacia la conexión de la app que lo pide (según
+/// su ventana `app-<id>`; desde la principal se rechaza). Los mensajes llegan por
+/// `on_event`; devuelve el id para enviar y cerrar.
+#[tauri::command]
+pub async fn open_app_socket(
+    app_handle: tauri::AppHandle,
+    webview: tauri::Webview,
+    path: String,
+    on_event: Channel<SocketEvent>,
+) -> Result<String, String> {
+    crate::proxy_handler::open_socket(&app_handle, webview.label(), &path, on_event).await
+}
+
+/// Envía un mensaje de texto (o binario en base64 si `binary`) por un socket abierto
+/// desde la misma ventana.
+#[tauri::command]
+pub fn send_app_socket(
+    bridge: tauri::State<'_, SocketBridge>,
+    webview: tauri::Webview,
+    socket_id: String,
+    data: String,
+    binary: Option<bool>,
+) -> Result<(), String> {
+    use base64::{engine::general_purpose, Engine as _};
+    use tokio_tungstenite::tungstenite::protocol::Message;
+
+    let message = if binary.unwrap_or(false) {
+        let bytes = general_purpose::STANDARD
+            .decode(&data)
+            .map_err(|e| e.to_string())?;
+        Message::Binary(bytes.into())
+    } else {
+        Message::Text(data.into())
+    };
+    bridge.send(webview.label(), &socket_id, message)
+}
+
+/// Cierra un socket abierto desde la misma ventana.
+#[tauri::command]
+pub fn close_app_socket(
+    bridge: tauri::State<'_, SocketBridge>,
+    webview: tauri::Webview,
+    socket_id: String,
+) -> bool {
+    bridge.close(webview.label(), &socket_id)
+}
+
+/// Descarga un fichero `/v1/...` de la conexión de la app a la carpeta de descargas,
+/// sin pasar por el protocolo (que no admite streaming). Devuelve la ruta final.
+#[tauri::command]
+pub async fn download_app_file(
+    app_handle: tauri::AppHandle,
+    webview: tauri::Webview,
+    path: String,
+    file_name: Option<String>,
+) -> Result<Download, String> {
+    crate::proxy_handler::download_to_disk(
+        &app_handle,
+        webview.label(),
+        &path,
+        file_name.as_deref(),
+    )
+    .await
+}
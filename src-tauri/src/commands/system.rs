@@ -1,6 +1,12 @@
+use crate::power::{
+    PowerAction, PowerActionStatus, PowerManager, PowerRequest, ScheduledPowerAction,
+    DEFAULT_POWER_DELAY,
+};
+use crate::runtime_config::{RuntimeConfig, RuntimeConfigStore};
 use local_ip_address::local_ip;
 use reqwest::blocking::get;
-use std::process::Command;
+use std::time::Duration;
+use tauri::AppHandle;
 
 #[tauri::command]
 pub fn get_network_info() -> Result<Vec<String>, String> {
@@ -21,22 +27,73 @@ pub fn get_network_info() -> Result<Vec<String>, String> {
     Ok(info)
 }
 
+/// Reinicio solicitado desde la UI local: se programa con el margen por defecto
+/// y puede cancelarse con `cancel_power_action`.
 #[tauri::command]
-pub fn remote_reboot() -> Result<String, String> {
-    #[cfg(target_os = "windows")]
-    {
-        Command::new("shutdown")
-            .args(["/r", "/t", "0"])
-            .spawn()
-            .map_err(|e| e.to_string())?;
-    }
+pub fn remote_reboot(
+    app_handle: AppHandle,
+    power: tauri::State<'_, PowerManager>,
+) -> Result<String, String> {
+    let scheduled = power.schedule(
+        &app_handle,
+        PowerRequest {
+            action: PowerAction::Reboot,
+            delay: DEFAULT_POWER_DELAY,
+            reason: None,
+            requested_by: "local",
+            dry_run: false,
+        },
+        None,
+    )?;
+
+    Ok(format!(
+        "Reinicio programado en {} segundos",
+        scheduled.delay_secs
+    ))
+}
 
-    #[cfg(any(target_os = "linux", target_os = "macos"))]
-    {
-        Command::new("reboot").spawn().map_err(|e| e.to_string())?;
-    }
+#[tauri::command]
+pub fn schedule_power_action(
+    app_handle: AppHandle,
+    power: tauri::State<'_, PowerManager>,
+    action: PowerAction,
+    delay_secs: Option<u64>,
+    reason: Option<String>,
+    dry_run: Option<bool>,
+) -> Result<ScheduledPowerAction, String> {
+    let delay = delay_secs
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_POWER_DELAY);
+
+    power.schedule(
+        &app_handle,
+        PowerRequest {
+            action,
+            delay,
+            reason,
+            requested_by: "local",
+            dry_run: dry_run.unwrap_or(false),
+        },
+        None,
+    )
+}
 
-    Ok("Señal de reinicio enviada".into())
+#[tauri::command]
+pub fn cancel_power_action(
+    power: tauri::State<'_, PowerManager>,
+) -> Result<ScheduledPowerAction, String> {
+    power.cancel()
+}
+
+#[tauri::command]
+pub fn get_power_action_status(power: tauri::State<'_, PowerManager>) -> PowerActionStatus {
+    power.status()
+}
+
+/// Configuración vigente (la empujada por el servidor o la de fábrica).
+#[tauri::command]
+pub fn get_runtime_config(store: tauri::State<'_, RuntimeConfigStore>) -> RuntimeConfig {
+    store.current()
 }
 
 #[tauri::command]
@@ -74,6 +131,10 @@ pub fn reset_database(state: tauri::State<'_, crate::storage::DbState>) -> Resul
         DROP TABLE IF EXISTS app_logs;
         DROP TABLE IF EXISTS system_events;
         DROP TABLE IF EXISTS config;
+        DROP TABLE IF EXISTS outbound_queue;
+        DROP TABLE IF EXISTS proxy_cookies;
+        DROP TABLE IF EXISTS proxy_cache;
+        DROP TABLE IF EXISTS proxy_write_queue;
         DROP TABLE IF EXISTS desktop_apps;
     ",
     )
@@ -0,0 +1,340 @@
+use crate::remote_control::{self, ReconnectPolicy};
+use crate::storage::DbState;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Notify;
+
+/// Clave usada para conexiones que aún no tienen perfil guardado en la BD.
+pub const AD_HOC_CONNECTION_ID: i64 = 0;
+
+/// Estado explícito de una conexión con un servidor Sandra.
+/// Se serializa como `{ "status": "reconnecting", "attempt": 2, ... }`.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ConnectionState {
+    Idle,
+    Connecting {
+        attempt: u32,
+    },
+    Connected,
+    Reconnecting {
+        attempt: u32,
+        next_retry_at: String,
+        next_retry_in_ms: u64,
+    },
+    Failed {
+        reason: String,
+    },
+    Disconnecting,
+}
+
+impl ConnectionState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ConnectionState::Idle => "idle",
+            ConnectionState::Connecting { .. } => "connecting",
+            ConnectionState::Connected => "connected",
+            ConnectionState::Reconnecting { .. } => "reconnecting",
+            ConnectionState::Failed { .. } => "failed",
+            ConnectionState::Disconnecting => "disconnecting",
+        }
+    }
+
+    pub fn reconnecting(attempt: u32, delay: Duration) -> Self {
+        let next_retry_at = chrono::Duration::from_std(delay)
+            .map(|d| chrono::Utc::now() + d)
+            .unwrap_or_else(|_| chrono::Utc::now())
+            .to_rfc3339();
+
+        ConnectionState::Reconnecting {
+            attempt,
+            next_retry_at,
+            next_retry_in_ms: delay.as_millis() as u64,
+        }
+    }
+}
+
+/// Payload del evento `connection-status` y respuesta de `get_connection_state`.
+#[derive(Serialize, Clone, Debug)]
+pub struct ConnectionSnapshot {
+    pub connection_id: i64,
+    /// Atajo de `state.status` para listeners que sólo necesitan la etiqueta.
+    pub status: &'static str,
+    pub state: ConnectionState,
+    /// Causa del último cambio (error de handshake, cierre del servidor, etc.)
+    pub reason: Option<String>,
+    pub timestamp: String,
+}
+
+/// Listener WSS en segundo plano junto con lo necesario para despertarlo o relanzarlo.
+struct ListenerTask {
+    handle: JoinHandle<()>,
+    wake: Arc<Notify>,
+    ws_url: String,
+    /// `None` = política de la configuración remota vigente.
+    policy: Option<ReconnectPolicy>,
+}
+
+struct ManagedConnection {
+    snapshot: ConnectionSnapshot,
+    listener: Option<ListenerTask>,
+}
+
+/// Fuente única de verdad del estado de conexión.
+/// La columna `connections.is_connected` es sólo un reflejo que se escribe desde aquí.
+#[derive(Default)]
+pub struct ConnectionManager {
+    entries: Mutex<HashMap<i64, ManagedConnection>>,
+}
+
+impl ConnectionManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registra un cambio de estado, lo refleja en la BD y lo emite a la UI.
+    pub fn transition(
+        &self,
+        app_handle: &AppHandle,
+        connection_id: i64,
+        state: ConnectionState,
+        reason: Option<String>,
+    ) {
+        let snapshot = ConnectionSnapshot {
+            connection_id,
+            status: state.label(),
+            state,
+            reason,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+
+        {
+            let mut entries = self.entries.lock().unwrap();
+            match entries.get_mut(&connection_id) {
+                Some(entry) => entry.snapshot = snapshot.clone(),
+                None => {
+                    entries.insert(
+                        connection_id,
+                        ManagedConnection {
+                            snapshot: snapshot.clone(),
+                            listener: None,
+                        },
+                    );
+                }
+            }
+        }
+
+        persist_connected_flag(
+            app_handle,
+            connection_id,
+            snapshot.state == ConnectionState::Connected,
+        );
+
+        let _ = app_handle.emit("connection-status", snapshot);
+    }
+
+    /// Lanza (o relanza) el listener de `connection_id`.
+    /// Las demás conexiones siguen activas: puede haber varios servidores a la vez.
+    pub fn start(
+        &self,
+        app_handle: &AppHandle,
+        connection_id: i64,
+        ws_url: String,
+        policy: Option<ReconnectPolicy>,
+    ) {
+        self.abort_listener(connection_id);
+
+        // El propio listener emite `Connecting` al comenzar cada intento
+        let task = spawn_listener(app_handle.clone(), connection_id, ws_url, policy);
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries
+            .entry(connection_id)
+            .or_insert_with(|| ManagedConnection {
+                snapshot: idle_snapshot(connection_id),
+                listener: None,
+            });
+        entry.listener = Some(task);
+    }
+
+    /// Detiene el listener y deja la conexión en `Idle`.
+    pub fn stop(&self, app_handle: &AppHandle, connection_id: i64) {
+        self.transition(
+            app_handle,
+            connection_id,
+            ConnectionState::Disconnecting,
+            None,
+        );
+        self.abort_listener(connection_id);
+        self.transition(app_handle, connection_id, ConnectionState::Idle, None);
+    }
+
+    /// Fuerza un reintento inmediato (de todas las conexiones si `connection_id` es `None`).
+    /// Si el listener ya abandonó la reconexión (agotó `max_attempts`), se relanza
+    /// con la misma URL y política.
+    pub fn retry_now(
+        &self,
+        app_handle: &AppHandle,
+        connection_id: Option<i64>,
+    ) -> Result<(), String> {
+        let mut entries = self.entries.lock().unwrap();
+        let mut retried = 0;
+
+        for (id, entry) in entries.iter_mut() {
+            if connection_id.is_some_and(|target| target != *id) {
+                continue;
+            }
+            let task = match entry.listener.take() {
+                Some(task) => task,
+                None => continue,
+            };
+
+            if task.handle.inner().is_finished() {
+                println!("🔁 Relanzando listener de la conexión {}...", id);
+                entry.listener = Some(spawn_listener(
+                    app_handle.clone(),
+                    *id,
+                    task.ws_url,
+                    task.policy,
+                ));
+            } else {
+                // Sólo se despierta un listener que está esperando su backoff: un aviso
+                // guardado con la conexión activa se saltaría la espera del próximo corte
+                if matches!(entry.snapshot.state, ConnectionState::Reconnecting { .. }) {
+                    task.wake.notify_one();
+                }
+                entry.listener = Some(task);
+            }
+            retried += 1;
+        }
+
+        if retried == 0 {
+            return Err("No hay ninguna conexión activa para reintentar.".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn snapshot(&self, connection_id: i64) -> ConnectionSnapshot {
+        let entries = self.entries.lock().unwrap();
+        match entries.get(&connection_id) {
+            Some(entry) => entry.snapshot.clone(),
+            None => idle_snapshot(connection_id),
+        }
+    }
+
+    pub fn snapshots(&self) -> Vec<ConnectionSnapshot> {
+        let entries = self.entries.lock().unwrap();
+        entries.values().map(|e| e.snapshot.clone()).collect()
+    }
+
+    /// URL WSS con la que se lanzó el listener de `connection_id`.
+    pub fn ws_url(&self, connection_id: i64) -> Option<String> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(&connection_id)
+            .and_then(|e| e.listener.as_ref())
+            .map(|task| task.ws_url.clone())
+    }
+
+    pub fn is_connected(&self, connection_id: i64) -> bool {
+        let entries = self.entries.lock().unwrap();
+        matches!(
+            entries.get(&connection_id).map(|e| &e.snapshot.state),
+            Some(ConnectionState::Connected)
+        )
+    }
+
+    /// IDs de las conexiones actualmente en estado `Connected`.
+    /// Ordenados por ID para que el enrutado por defecto sea determinista.
+    pub fn connected_ids(&self) -> Vec<i64> {
+        let entries = self.entries.lock().unwrap();
+        let mut ids: Vec<i64> = entries
+            .iter()
+            .filter(|(_, e)| e.snapshot.state == ConnectionState::Connected)
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Detiene y olvida una conexión (p. ej. al borrar su perfil).
+    pub fn remove(&self, app_handle: &AppHandle, connection_id: i64) {
+        self.stop(app_handle, connection_id);
+        self.entries.lock().unwrap().remove(&connection_id);
+    }
+
+    fn abort_listener(&self, connection_id: i64) {
+        let task = {
+            let mut entries = self.entries.lock().unwrap();
+            entries
+                .get_mut(&connection_id)
+                .and_then(|e| e.listener.take())
+        };
+        if let Some(task) = task {
+            task.handle.abort();
+        }
+    }
+}
+
+fn idle_snapshot(connection_id: i64) -> ConnectionSnapshot {
+    ConnectionSnapshot {
+        connection_id,
+        status: ConnectionState::Idle.label(),
+        state: ConnectionState::Idle,
+        reason: None,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    }
+}
+
+fn spawn_listener(
+    app_handle: AppHandle,
+    connection_id: i64,
+    ws_url: String,
+    policy: Option<ReconnectPolicy>,
+) -> ListenerTask {
+    let wake = Arc::new(Notify::new());
+
+    let url = ws_url.clone();
+    let listener_wake = wake.clone();
+    let listener_policy = policy.clone();
+    let handle = tauri::async_runtime::spawn(async move {
+        remote_control::start_remote_listener(
+            url,
+            app_handle,
+            connection_id,
+            listener_policy,
+            listener_wake,
+        )
+        .await;
+    });
+
+    ListenerTask {
+        handle,
+        wake,
+        ws_url,
+        policy,
+    }
+}
+
+fn persist_connected_flag(app_handle: &AppHandle, connection_id: i64, connected: bool) {
+    if connection_id == AD_HOC_CONNECTION_ID {
+        return;
+    }
+
+    let state = app_handle.state::<DbState>();
+    let lock_result = state.0.lock();
+
+    if let Ok(conn) = lock_result {
+        let sql = if connected {
+            "UPDATE connections SET is_connected = 1, last_connected = CURRENT_TIMESTAMP WHERE id = ?1"
+        } else {
+            "UPDATE connections SET is_connected = 0 WHERE id = ?1"
+        };
+        if let Err(e) = conn.execute(sql, [connection_id]) {
+            println!("Failed to update DB connection status: {}", e);
+        }
+    }
+}
@@ -1,34 +1,67 @@
 pub mod commands;
+pub mod connection_manager;
+pub mod power;
 pub mod proxy_handler;
 pub mod remote_control;
+pub mod runtime_config;
 pub mod storage;
 
+use crate::connection_manager::ConnectionManager;
+use crate::power::PowerManager;
+use crate::runtime_config::RuntimeConfigStore;
 use crate::storage::DbState;
 use std::sync::Mutex;
-use tauri::async_runtime::JoinHandle;
 use tauri::Manager;
 
-pub struct ConnectionTask(pub Mutex<Option<JoinHandle<()>>>);
-
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_fs::init())
-        .register_uri_scheme_protocol("sandra-app", |app_handle, request| {
-            proxy_handler::handle_request(app_handle.app_handle(), &request)
+        .register_asynchronous_uri_scheme_protocol("sandra-app", |ctx, request, responder| {
+            let app_handle = ctx.app_handle().clone();
+            let webview = ctx.webview_label().to_string();
+            tauri::async_runtime::spawn(async move {
+                let response = proxy_handler::handle_request(&app_handle, &webview, &request).await;
+                responder.respond(response);
+            });
         })
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
         .setup(move |app| {
             let conn = storage::initialize_db(&app.handle()).expect("Error al inicializar SQLite");
+            app.manage(RuntimeConfigStore::load(&conn));
             app.manage(DbState(Mutex::new(conn)));
-            app.manage(ConnectionTask(Mutex::new(None)));
+            app.manage(ConnectionManager::new());
+            app.manage(PowerManager::from_env());
+            app.manage(proxy_handler::ExternalSessions::default());
+            app.manage(proxy_handler::TrafficCapture::default());
+            app.manage(proxy_handler::WriteQueue::default());
+            app.manage(proxy_handler::MockFixtures::default());
+            app.manage(proxy_handler::SocketBridge::default());
+            app.manage(
+                proxy_handler::ProxyClients::new().expect("Error al crear los clientes HTTP"),
+            );
             Ok(())
         })
+        .on_window_event(|window, event| {
+            // La sesión externa y los WebSockets de la app mueren con su ventana
+            if let tauri::WindowEvent::Destroyed = event {
+                window
+                    .state::<proxy_handler::ExternalSessions>()
+                    .clear(Some(window.label()));
+                window
+                    .state::<proxy_handler::SocketBridge>()
+                    .close_webview(window.label());
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             commands::monitor::get_system_telemetry,
             commands::system::get_network_info,
             commands::system::remote_reboot,
+            commands::system::schedule_power_action,
+            commands::system::cancel_power_action,
+            commands::system::get_power_action_status,
+            commands::system::get_runtime_config,
             commands::system::export_database,
             commands::system::reset_database,
             commands::apps::download_app_repo,
@@ -40,6 +73,9 @@ pub fn run() {
             commands::apps::create_app,
             commands::apps::update_app,
             commands::apps::delete_app,
+            commands::apps::set_app_connection,
+            commands::apps::get_remote_app_policy,
+            commands::apps::set_remote_app_policy,
             commands::handler_error::save_app_log,
             commands::handler_error::get_app_logs,
             commands::handler_error::clear_app_logs,
@@ -53,6 +89,29 @@ pub fn run() {
             commands::connections::delete_connection,
             commands::connections::connect_to_server,
             commands::connections::disconnect_from_server,
+            commands::connections::retry_now,
+            commands::connections::get_connection_state,
+            commands::connections::get_outbound_queue,
+            commands::connections::clear_outbound_queue,
+            commands::proxy::get_external_sessions,
+            commands::proxy::clear_external_sessions,
+            commands::proxy::get_proxy_cookies,
+            commands::proxy::clear_proxy_cookies,
+            commands::proxy::get_external_proxy_denials,
+            commands::proxy::get_proxy_traffic,
+            commands::proxy::clear_proxy_traffic,
+            commands::proxy::get_proxy_capture_options,
+            commands::proxy::set_proxy_capture_options,
+            commands::proxy::export_proxy_har,
+            commands::proxy::get_proxy_cache_stats,
+            commands::proxy::purge_proxy_cache,
+            commands::proxy::get_write_queue,
+            commands::proxy::replay_write_queue,
+            commands::proxy::discard_queued_write,
+            commands::proxy::open_app_socket,
+            commands::proxy::send_app_socket,
+            commands::proxy::close_app_socket,
+            commands::proxy::download_app_file,
             commands::window::close_splash,
             commands::pdf::save_protected_pdf
         ])
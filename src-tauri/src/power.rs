@@ -0,0 +1,585 @@
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_dialog::{
+    DialogExt, MessageDialogButtons, MessageDialogKind, MessageDialogResult,
+};
+use tokio::sync::oneshot;
+
+/// Margen por defecto para que el usuario guarde su trabajo.
+pub const DEFAULT_POWER_DELAY: Duration = Duration::from_secs(60);
+/// Aviso mínimo de una acción pedida por el servidor: nunca sin cuenta atrás visible.
+pub const MIN_REMOTE_POWER_DELAY: Duration = Duration::from_secs(30);
+/// Variable de entorno que fuerza el ejecutor simulado en todo el proceso.
+pub const DRY_RUN_ENV: &str = "SANDRA_POWER_DRY_RUN";
+
+/// Botón del aviso que cancela la acción programada.
+const CANCEL_ACTION_LABEL: &str = "Cancelar acción";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerAction {
+    Reboot,
+    Shutdown,
+    Logoff,
+}
+
+impl PowerAction {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "reboot" => Some(PowerAction::Reboot),
+            "shutdown" => Some(PowerAction::Shutdown),
+            "logoff" => Some(PowerAction::Logoff),
+            _ => None,
+        }
+    }
+
+    fn verb(&self) -> &'static str {
+        match self {
+            PowerAction::Reboot => "reiniciará",
+            PowerAction::Shutdown => "apagará",
+            PowerAction::Logoff => "cerrará la sesión",
+        }
+    }
+}
+
+/// Ejecuta la acción de energía. Existe para poder sustituir el sistema real
+/// por uno simulado en pruebas o en modo dry-run.
+pub trait PowerExecutor: Send + Sync {
+    fn execute(&self, action: PowerAction) -> Result<(), String>;
+}
+
+/// Ejecutor real: llama a las herramientas del SO y comprueba su resultado.
+pub struct SystemPowerExecutor;
+
+impl PowerExecutor for SystemPowerExecutor {
+    fn execute(&self, action: PowerAction) -> Result<(), String> {
+        let mut command = system_command(action);
+        let status = command
+            .status()
+            .map_err(|e| format!("No se pudo ejecutar {:?}: {}", command, e))?;
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!("{:?} terminó con {}", command, status))
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn system_command(action: PowerAction) -> Command {
+    let mut command = Command::new("shutdown");
+    match action {
+        PowerAction::Reboot => command.args(["/r", "/t", "0"]),
+        PowerAction::Shutdown => command.args(["/s", "/t", "0"]),
+        PowerAction::Logoff => command.arg("/l"),
+    };
+    command
+}
+
+#[cfg(target_os = "macos")]
+fn system_command(action: PowerAction) -> Command {
+    let mut command = Command::new("osascript");
+    let script = match action {
+        PowerAction::Reboot => "tell application \"System Events\" to restart",
+        PowerAction::Shutdown => "tell application \"System Events\" to shut down",
+        PowerAction::Logoff => "tell application \"System Events\" to log out",
+    };
+    command.args(["-e", script]);
+    command
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn system_command(action: PowerAction) -> Command {
+    match action {
+        PowerAction::Reboot => Command::new("reboot"),
+        PowerAction::Shutdown => {
+            let mut command = Command::new("shutdown");
+            command.args(["-h", "now"]);
+            command
+        }
+        PowerAction::Logoff => {
+            let mut command = Command::new("loginctl");
+            command.args(["terminate-user", &std::env::var("USER").unwrap_or_default()]);
+            command
+        }
+    }
+}
+
+/// Ejecutor simulado: sólo registra lo que se habría hecho.
+#[derive(Default)]
+pub struct DryRunPowerExecutor {
+    pub executed: Mutex<Vec<PowerAction>>,
+}
+
+impl PowerExecutor for DryRunPowerExecutor {
+    fn execute(&self, action: PowerAction) -> Result<(), String> {
+        println!("🧪 [Power] Dry-run: se habría ejecutado {:?}", action);
+        self.executed.lock().unwrap().push(action);
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ScheduledPowerAction {
+    pub id: String,
+    pub action: PowerAction,
+    /// "local" o "remote"
+    pub requested_by: String,
+    pub reason: Option<String>,
+    pub dry_run: bool,
+    pub scheduled_at: String,
+    pub execute_at: String,
+    pub delay_secs: u64,
+}
+
+/// Payload del evento `power-action` y respuesta de `get_power_action_status`.
+#[derive(Serialize, Clone, Debug)]
+pub struct PowerActionStatus {
+    /// idle, scheduled, cancelled, executing, executed, failed
+    pub status: &'static str,
+    pub action: Option<ScheduledPowerAction>,
+    pub error: Option<String>,
+}
+
+/// Parámetros de una acción a programar.
+pub struct PowerRequest {
+    pub action: PowerAction,
+    pub delay: Duration,
+    pub reason: Option<String>,
+    /// "local" o "remote"
+    pub requested_by: &'static str,
+    /// Usa el ejecutor simulado sólo para esta acción.
+    pub dry_run: bool,
+}
+
+/// Recibe cada cambio de estado de una acción (p. ej. para reportarlo al servidor).
+pub type StatusObserver = Box<dyn Fn(&PowerActionStatus) + Send + Sync>;
+
+struct PendingAction {
+    action: ScheduledPowerAction,
+    cancel: oneshot::Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+/// Programa, cancela y ejecuta acciones de energía con cuenta atrás.
+/// Sólo puede haber una acción pendiente a la vez.
+pub struct PowerManager {
+    executor: Arc<dyn PowerExecutor>,
+    dry_run_executor: Arc<DryRunPowerExecutor>,
+    pending: Mutex<Option<PendingAction>>,
+    /// Compartido con la tarea de cuenta atrás, que publica los estados finales.
+    last: Arc<Mutex<Option<PowerActionStatus>>>,
+}
+
+impl PowerManager {
+    pub fn new(executor: Arc<dyn PowerExecutor>) -> Self {
+        Self {
+            executor,
+            dry_run_executor: Arc::new(DryRunPowerExecutor::default()),
+            pending: Mutex::new(None),
+            last: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Ejecutor del sistema, o el simulado si `SANDRA_POWER_DRY_RUN` está definido.
+    pub fn from_env() -> Self {
+        if std::env::var_os(DRY_RUN_ENV).is_some() {
+            println!("🧪 [Power] {} activo: acciones simuladas", DRY_RUN_ENV);
+            Self::new(Arc::new(DryRunPowerExecutor::default()))
+        } else {
+            Self::new(Arc::new(SystemPowerExecutor))
+        }
+    }
+
+    /// Programa la acción, avisa al usuario local y emite cada cambio de estado
+    /// en `power-action` (y a `observer`, si lo hay).
+    pub fn schedule(
+        &self,
+        app_handle: &AppHandle,
+        request: PowerRequest,
+        observer: Option<StatusObserver>,
+    ) -> Result<ScheduledPowerAction, String> {
+        let emitter = app_handle.clone();
+        let scheduled = self.start(
+            request,
+            Box::new(move |status| {
+                if let Some(observer) = &observer {
+                    observer(status);
+                }
+                let _ = emitter.emit("power-action", status);
+            }),
+        )?;
+        show_notice(app_handle, &scheduled);
+        Ok(scheduled)
+    }
+
+    /// Núcleo de `schedule` sin dependencias de la ventana: lanza la cuenta atrás y
+    /// notifica cada estado a `notify` tras guardarlo como último conocido.
+    fn start(
+        &self,
+        request: PowerRequest,
+        notify: StatusObserver,
+    ) -> Result<ScheduledPowerAction, String> {
+        let PowerRequest {
+            action,
+            delay,
+            reason,
+            requested_by,
+            dry_run,
+        } = request;
+
+        let mut pending = self.pending.lock().unwrap();
+        if let Some(current) = pending.as_ref() {
+            if !current.handle.inner().is_finished() {
+                return Err(format!(
+                    "Ya hay una acción programada ({:?}) para {}.",
+                    current.action.action, current.action.execute_at
+                ));
+            }
+        }
+
+        let now = chrono::Utc::now();
+        let scheduled = ScheduledPowerAction {
+            id: uuid::Uuid::new_v4().to_string(),
+            action,
+            requested_by: requested_by.to_string(),
+            reason,
+            dry_run,
+            scheduled_at: now.to_rfc3339(),
+            execute_at: (now + chrono::Duration::from_std(delay).unwrap_or_default()).to_rfc3339(),
+            delay_secs: delay.as_secs(),
+        };
+
+        let executor: Arc<dyn PowerExecutor> = if dry_run {
+            self.dry_run_executor.clone()
+        } else {
+            self.executor.clone()
+        };
+
+        let last = self.last.clone();
+        let publish: StatusObserver = Box::new(move |status| {
+            *last.lock().unwrap() = Some(status.clone());
+            notify(status);
+        });
+        publish(&PowerActionStatus {
+            status: "scheduled",
+            action: Some(scheduled.clone()),
+            error: None,
+        });
+
+        let (cancel_tx, cancel_rx) = oneshot::channel::<()>();
+        let task_action = scheduled.clone();
+        let handle = tauri::async_runtime::spawn(async move {
+            run_countdown(task_action, delay, executor, cancel_rx, publish).await;
+        });
+
+        *pending = Some(PendingAction {
+            action: scheduled.clone(),
+            cancel: cancel_tx,
+            handle,
+        });
+
+        println!(
+            "⏲️ [Power] {:?} programado para {} ({})",
+            action, scheduled.execute_at, requested_by
+        );
+        Ok(scheduled)
+    }
+
+    pub fn cancel(&self) -> Result<ScheduledPowerAction, String> {
+        let mut guard = self.pending.lock().unwrap();
+        let PendingAction {
+            action,
+            cancel,
+            handle,
+        } = guard
+            .take()
+            .filter(|p| !p.handle.inner().is_finished())
+            .ok_or_else(|| "No hay ninguna acción de energía pendiente.".to_string())?;
+
+        // Si la cuenta atrás ya terminó el envío falla y no hay nada que cancelar. La
+        // acción sigue en curso: se devuelve a `pending` (con un emisor ya cerrado) para
+        // que `start` no admita otra mientras se ejecuta.
+        if cancel.send(()).is_err() {
+            let (closed, _) = oneshot::channel();
+            *guard = Some(PendingAction {
+                action,
+                cancel: closed,
+                handle,
+            });
+            return Err("La acción ya se está ejecutando.".to_string());
+        }
+
+        println!("🛑 [Power] {:?} cancelado", action.action);
+        Ok(action)
+    }
+
+    pub fn status(&self) -> PowerActionStatus {
+        if let Some(pending) = self.pending.lock().unwrap().as_ref() {
+            if !pending.handle.inner().is_finished() {
+                return PowerActionStatus {
+                    status: "scheduled",
+                    action: Some(pending.action.clone()),
+                    error: None,
+                };
+            }
+        }
+
+        self.last
+            .lock()
+            .unwrap()
+            .clone()
+            .unwrap_or(PowerActionStatus {
+                status: "idle",
+                action: None,
+                error: None,
+            })
+    }
+
+    /// Acciones que el ejecutor simulado habría lanzado.
+    pub fn dry_run_history(&self) -> Vec<PowerAction> {
+        self.dry_run_executor.executed.lock().unwrap().clone()
+    }
+}
+
+async fn run_countdown(
+    action: ScheduledPowerAction,
+    delay: Duration,
+    executor: Arc<dyn PowerExecutor>,
+    cancel_rx: oneshot::Receiver<()>,
+    publish: StatusObserver,
+) {
+    let notify = |status: PowerActionStatus| publish(&status);
+
+    tokio::select! {
+        _ = tokio::time::sleep(delay) => {}
+        _ = cancel_rx => {
+            notify(PowerActionStatus { status: "cancelled", action: Some(action), error: None });
+            return;
+        }
+    }
+
+    notify(PowerActionStatus {
+        status: "executing",
+        action: Some(action.clone()),
+        error: None,
+    });
+
+    let kind = action.action;
+    let result = tauri::async_runtime::spawn_blocking(move || executor.execute(kind))
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r);
+
+    match result {
+        Ok(()) => notify(PowerActionStatus {
+            status: "executed",
+            action: Some(action),
+            error: None,
+        }),
+        Err(e) => {
+            eprintln!("❌ [Power] {:?} falló: {}", kind, e);
+            notify(PowerActionStatus {
+                status: "failed",
+                action: Some(action),
+                error: Some(e),
+            })
+        }
+    }
+}
+
+/// Aviso visible al usuario local. Sólo el botón "Cancelar acción" la cancela:
+/// cerrar el diálogo no cuenta como respuesta.
+fn show_notice(app_handle: &AppHandle, action: &ScheduledPowerAction) {
+    let origin = if action.requested_by == "remote" {
+        "El administrador remoto ha programado"
+    } else {
+        "Se ha programado"
+    };
+    let mut message = format!(
+        "{} una acción de energía: el equipo se {} en {} segundos.",
+        origin,
+        action.action.verb(),
+        action.delay_secs
+    );
+    if let Some(reason) = &action.reason {
+        message.push_str(&format!("\n\nMotivo: {}", reason));
+    }
+    message.push_str("\n\nGuarde su trabajo.");
+
+    let handle = app_handle.clone();
+    let action_id = action.id.clone();
+    app_handle
+        .dialog()
+        .message(message)
+        .title("Aviso del sistema")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::YesNoCancelCustom(
+            "Entendido".to_string(),
+            CANCEL_ACTION_LABEL.to_string(),
+            "Cerrar".to_string(),
+        ))
+        .show_with_result(move |result| {
+            let cancel_requested = match result {
+                MessageDialogResult::No => true,
+                MessageDialogResult::Custom(label) => label == CANCEL_ACTION_LABEL,
+                _ => false,
+            };
+            if !cancel_requested {
+                return;
+            }
+            let manager = handle.state::<PowerManager>();
+            // Sólo cancelamos si sigue pendiente la misma acción que mostró el aviso
+            let same = matches!(manager.status().action, Some(a) if a.id == action_id);
+            if same {
+                let _ = manager.cancel();
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(action: PowerAction, delay: Duration) -> PowerRequest {
+        PowerRequest {
+            action,
+            delay,
+            reason: Some("prueba".into()),
+            requested_by: "remote",
+            dry_run: true,
+        }
+    }
+
+    fn recorder() -> (Arc<Mutex<Vec<&'static str>>>, StatusObserver) {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        (
+            seen,
+            Box::new(move |status| sink.lock().unwrap().push(status.status)),
+        )
+    }
+
+    fn wait(duration: Duration) {
+        tauri::async_runtime::block_on(async move { tokio::time::sleep(duration).await });
+    }
+
+    fn manager() -> PowerManager {
+        PowerManager::new(Arc::new(DryRunPowerExecutor::default()))
+    }
+
+    #[test]
+    fn scheduled_action_runs_after_its_delay() {
+        let power = manager();
+        let (seen, notify) = recorder();
+        let scheduled = power
+            .start(
+                request(PowerAction::Reboot, Duration::from_millis(20)),
+                notify,
+            )
+            .unwrap();
+        assert_eq!(power.status().status, "scheduled");
+        assert_eq!(power.status().action.unwrap().id, scheduled.id);
+
+        wait(Duration::from_millis(300));
+
+        assert_eq!(power.dry_run_history(), vec![PowerAction::Reboot]);
+        assert_eq!(power.status().status, "executed");
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec!["scheduled", "executing", "executed"]
+        );
+    }
+
+    #[test]
+    fn cancelled_action_never_runs() {
+        let power = manager();
+        let (seen, notify) = recorder();
+        power
+            .start(
+                request(PowerAction::Shutdown, Duration::from_secs(60)),
+                notify,
+            )
+            .unwrap();
+
+        let cancelled = power.cancel().unwrap();
+        assert_eq!(cancelled.action, PowerAction::Shutdown);
+        wait(Duration::from_millis(100));
+
+        assert!(power.dry_run_history().is_empty());
+        assert_eq!(power.status().status, "cancelled");
+        assert_eq!(*seen.lock().unwrap(), vec!["scheduled", "cancelled"]);
+        assert!(power.cancel().is_err());
+    }
+
+    #[test]
+    fn only_one_action_can_be_pending() {
+        let power = manager();
+        power
+            .start(
+                request(PowerAction::Logoff, Duration::from_secs(60)),
+                recorder().1,
+            )
+            .unwrap();
+        assert!(power
+            .start(
+                request(PowerAction::Reboot, Duration::from_secs(60)),
+                recorder().1
+            )
+            .is_err());
+
+        power.cancel().unwrap();
+        wait(Duration::from_millis(100));
+        assert!(power
+            .start(
+                request(PowerAction::Reboot, Duration::from_secs(60)),
+                recorder().1
+            )
+            .is_ok());
+        power.cancel().unwrap();
+    }
+
+    #[test]
+    fn cancelling_an_executing_action_keeps_it_pending() {
+        let power = manager();
+        let scheduled = power
+            .start(
+                request(PowerAction::Reboot, Duration::from_secs(60)),
+                recorder().1,
+            )
+            .unwrap();
+        // Cuenta atrás vencida (receptor soltado) con el ejecutor aún trabajando
+        let (cancel, _) = oneshot::channel();
+        let handle = tauri::async_runtime::spawn(async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        });
+        *power.pending.lock().unwrap() = Some(PendingAction {
+            action: scheduled,
+            cancel,
+            handle,
+        });
+
+        assert!(power.cancel().unwrap_err().contains("ejecutando"));
+        assert!(power
+            .start(
+                request(PowerAction::Shutdown, Duration::from_secs(60)),
+                recorder().1
+            )
+            .is_err());
+        assert!(power.cancel().is_err());
+    }
+
+    #[test]
+    fn status_is_idle_without_actions() {
+        let power = manager();
+        let status = power.status();
+        assert_eq!(status.status, "idle");
+        assert!(status.action.is_none());
+        assert!(power.cancel().is_err());
+    }
+}
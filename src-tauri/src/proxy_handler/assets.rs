@@ -0,0 +1,369 @@
+use std::path::Path;
+use std::time::SystemTime;
+
+use tauri::http::{header, Method, Request, Response, StatusCode};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use super::{create_error_response, mime};
+
+const LOCAL_CSP: &str = "default-src 'self' 'unsafe-inline' sandra-app: asset: tauri: data: blob: http: https: ws: wss:;";
+/// Bytes leídos para identificar ficheros sin extensión conocida.
+const SNIFF_LEN: u64 = 512;
+const CACHE_IMMUTABLE: &str = "public, max-age=31536000, immutable";
+/// Se revalida siempre con ETag/Last-Modified (respuesta 304 si no cambió).
+const CACHE_REVALIDATE: &str = "no-cache";
+
+/// Sirve un fichero de una app con soporte de `Range` (206), peticiones
+/// condicionales (`If-None-Match` / `If-Modified-Since` → 304) y `Cache-Control`
+/// según el tipo de fichero. Sólo se lee del disco el tramo pedido.
+pub async fn serve_file(request: &Request<Vec<u8>>, file_path: &Path) -> Response<Vec<u8>> {
+    let metadata = match tokio::fs::metadata(file_path).await {
+        Ok(metadata) if metadata.is_file() => metadata,
+        _ => return create_error_response(404, "File not found locally"),
+    };
+
+    let len = metadata.len();
+    let modified = metadata.modified().ok();
+    let etag = entity_tag(len, modified);
+    let last_modified = modified.map(http_date);
+    let cache_control = cache_policy(file_path);
+    let mime_type = content_type(file_path).await;
+
+    let builder = || {
+        let mut builder = Response::builder()
+            .header(header::CONTENT_TYPE, mime_type)
+            // El tipo es definitivo: el webview no debe reinterpretarlo
+            .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+            .header(header::ACCEPT_RANGES, "bytes")
+            .header(header::ETAG, &etag)
+            .header(header::CACHE_CONTROL, cache_control)
+            .header("Access-Control-Allow-Origin", "*")
+            .header("Content-Security-Policy", LOCAL_CSP);
+        if let Some(last_modified) = &last_modified {
+            builder = builder.header(header::LAST_MODIFIED, last_modified);
+        }
+        builder
+    };
+
+    if not_modified(request, &etag, modified) {
+        return builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Vec::new())
+            .unwrap_or_else(|_| create_error_response(500, "Error building response"));
+    }
+
+    let range = requested_range(request, &etag, len);
+    let head_only = request.method() == Method::HEAD;
+
+    match range {
+        Some(Err(())) => builder()
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+            .body(Vec::new())
+            .unwrap_or_else(|_| create_error_response(500, "Error building response")),
+        Some(Ok((start, end))) => {
+            let body = if head_only {
+                Vec::new()
+            } else {
+                match read_range(file_path, start, end - start + 1).await {
+                    Ok(body) => body,
+                    Err(_) => return create_error_response(500, "Error reading file"),
+                }
+            };
+            builder()
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(
+                    header::CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", start, end, len),
+                )
+                .header(header::CONTENT_LENGTH, end - start + 1)
+                .body(body)
+                .unwrap_or_else(|_| create_error_response(500, "Error building response"))
+        }
+        None => {
+            let body = if head_only {
+                Vec::new()
+            } else {
+                match tokio::fs::read(file_path).await {
+                    Ok(body) => body,
+                    Err(_) => return create_error_response(404, "File not found locally"),
+                }
+            };
+            builder()
+                .header(header::CONTENT_LENGTH, len)
+                .body(body)
+                .unwrap_or_else(|_| create_error_response(500, "Error building response"))
+        }
+    }
+}
+
+/// Por extensión y, si no se reconoce, por el contenido de las primeras bytes.
+async fn content_type(file_path: &Path) -> &'static str {
+    if let Some(mime) = mime::from_extension(file_path) {
+        return mime;
+    }
+
+    let mut head = Vec::with_capacity(SNIFF_LEN as usize);
+    if let Ok(file) = tokio::fs::File::open(file_path).await {
+        let _ = file.take(SNIFF_LEN).read_to_end(&mut head).await;
+    }
+    mime::sniff(&head).unwrap_or(mime::FALLBACK)
+}
+
+/// ETag débil derivado de tamaño y fecha: barato y suficiente para ficheros locales.
+fn entity_tag(len: u64, modified: Option<SystemTime>) -> String {
+    let stamp = modified
+        .and_then(|m| m.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    format!("W/\"{:x}-{:x}\"", len, stamp)
+}
+
+fn http_date(time: SystemTime) -> String {
+    chrono::DateTime::<chrono::Utc>::from(time)
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string()
+}
+
+/// Extensiones de los bundles que genera el build de Angular con hash en el nombre.
+const HASHED_BUNDLE_EXTENSIONS: &[&str] =
+    &["js", "mjs", "css", "woff", "woff2", "ttf", "otf", "eot"];
+
+/// Los bundles de Angular llevan el hash en el nombre: `main-ABCD1234.js` (esbuild,
+/// 8 caracteres `A-Z0-9`) o `main.3f2a…e1.js` (webpack, 16 o 20 hex). Su contenido
+/// nunca cambia. Lo demás (index.html, documentos como `report-20240101.pdf`...)
+/// se revalida en cada carga para que una actualización de la app se vea al momento.
+fn cache_policy(file_path: &Path) -> &'static str {
+    let extension = file_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    let stem = file_path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+
+    let esbuild_hash = stem.rsplit_once('-').is_some_and(|(name, hash)| {
+        !name.is_empty()
+            && hash.len() == 8
+            && hash
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+    });
+    let webpack_hash = stem.rsplit_once('.').is_some_and(|(name, hash)| {
+        !name.is_empty()
+            && matches!(hash.len(), 16 | 20)
+            && hash
+                .chars()
+                .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+    });
+
+    if HASHED_BUNDLE_EXTENSIONS.contains(&extension.as_str()) && (esbuild_hash || webpack_hash) {
+        CACHE_IMMUTABLE
+    } else {
+        CACHE_REVALIDATE
+    }
+}
+
+fn header_str(request: &Request<Vec<u8>>, name: header::HeaderName) -> Option<&str> {
+    request.headers().get(name).and_then(|v| v.to_str().ok())
+}
+
+/// `If-None-Match` tiene prioridad; `If-Modified-Since` sólo se mira si no viene.
+fn not_modified(request: &Request<Vec<u8>>, etag: &str, modified: Option<SystemTime>) -> bool {
+    if let Some(if_none_match) = header_str(request, header::IF_NONE_MATCH) {
+        return if_none_match
+            .split(',')
+            .map(str::trim)
+            .any(|candidate| candidate == "*" || weak_eq(candidate, etag));
+    }
+
+    match (header_str(request, header::IF_MODIFIED_SINCE), modified) {
+        (Some(since), Some(modified)) => match chrono::DateTime::parse_from_rfc2822(since) {
+            Ok(since) => {
+                let modified = chrono::DateTime::<chrono::Utc>::from(modified);
+                // HTTP-date no tiene subsegundos
+                modified.timestamp() <= since.timestamp()
+            }
+            Err(_) => false,
+        },
+        _ => false,
+    }
+}
+
+fn weak_eq(a: &str, b: &str) -> bool {
+    a.trim_start_matches("W/") == b.trim_start_matches("W/")
+}
+
+/// Rango pedido: `None` = fichero completo, `Some(Err)` = 416.
+/// Sólo se admite un rango; varios rangos se responden con el fichero entero.
+fn requested_range(
+    request: &Request<Vec<u8>>,
+    etag: &str,
+    len: u64,
+) -> Option<Result<(u64, u64), ()>> {
+    let range = header_str(request, header::RANGE)?;
+
+    // If-Range con un validador distinto: el fichero cambió, se envía entero
+    if let Some(if_range) = header_str(request, header::IF_RANGE) {
+        if !weak_eq(if_range.trim(), etag) {
+            return None;
+        }
+    }
+
+    let spec = range.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+
+    let bounds = match (start.trim(), end.trim()) {
+        // bytes=-500: los últimos 500 bytes
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            if suffix == 0 || len == 0 {
+                return Some(Err(()));
+            }
+            (len.saturating_sub(suffix), len - 1)
+        }
+        (start, "") => (start.parse().ok()?, len.saturating_sub(1)),
+        (start, end) => {
+            let start: u64 = start.parse().ok()?;
+            let end: u64 = end.parse().ok()?;
+            if end < start {
+                return None;
+            }
+            (start, end.min(len.saturating_sub(1)))
+        }
+    };
+
+    if bounds.0 >= len {
+        return Some(Err(()));
+    }
+    Some(Ok(bounds))
+}
+
+async fn read_range(file_path: &Path, start: u64, length: u64) -> std::io::Result<Vec<u8>> {
+    let mut file = tokio::fs::File::open(file_path).await?;
+    file.seek(std::io::SeekFrom::Start(start)).await?;
+    let mut body = Vec::with_capacity(length as usize);
+    file.take(length).read_to_end(&mut body).await?;
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn request(headers: &[(&str, &str)]) -> Request<Vec<u8>> {
+        let mut builder = Request::builder().uri("sandra-app://localhost/app/file.bin");
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(Vec::new()).unwrap()
+    }
+
+    #[test]
+    fn only_hashed_angular_bundles_are_immutable() {
+        for hashed in [
+            "main-ABCD1234.js",
+            "chunk-7QXZ2K4M.mjs",
+            "styles-5INURTSO.css",
+            "media/roboto-KFOMCNQE.woff2",
+            "main.3f2a1b9c8d7e6f5a.js",
+            "runtime.0123456789abcdef0123.js",
+        ] {
+            assert_eq!(
+                cache_policy(Path::new(hashed)),
+                CACHE_IMMUTABLE,
+                "{}",
+                hashed
+            );
+        }
+        for plain in [
+            "index.html",
+            "main.js",
+            "report-20240101.pdf",
+            "logo-ABCD1234.png",
+            "data-20240101.json",
+            "vendor-abcd1234.js",
+            "main.3f2a1b9c.js",
+            "-ABCD1234.js",
+        ] {
+            assert_eq!(
+                cache_policy(Path::new(plain)),
+                CACHE_REVALIDATE,
+                "{}",
+                plain
+            );
+        }
+    }
+
+    #[test]
+    fn ranges_are_parsed_and_clamped() {
+        let etag = "W/\"10-1\"";
+        let range = |value: &str| requested_range(&request(&[("range", value)]), etag, 100);
+
+        assert_eq!(requested_range(&request(&[]), etag, 100), None);
+        assert_eq!(range("bytes=0-9"), Some(Ok((0, 9))));
+        assert_eq!(range("bytes=90-"), Some(Ok((90, 99))));
+        assert_eq!(range("bytes=-10"), Some(Ok((90, 99))));
+        assert_eq!(range("bytes=-500"), Some(Ok((0, 99))));
+        assert_eq!(range("bytes=50-500"), Some(Ok((50, 99))));
+        assert_eq!(range("bytes=100-"), Some(Err(())));
+        assert_eq!(range("bytes=-0"), Some(Err(())));
+        // Varios rangos, rangos invertidos o unidades desconocidas: fichero completo
+        assert_eq!(range("bytes=0-1,5-6"), None);
+        assert_eq!(range("bytes=9-0"), None);
+        assert_eq!(range("items=0-9"), None);
+    }
+
+    #[test]
+    fn if_range_with_another_validator_sends_the_whole_file() {
+        let etag = "W/\"10-1\"";
+        let same = request(&[("range", "bytes=0-9"), ("if-range", "\"10-1\"")]);
+        let changed = request(&[("range", "bytes=0-9"), ("if-range", "W/\"10-2\"")]);
+        assert_eq!(requested_range(&same, etag, 100), Some(Ok((0, 9))));
+        assert_eq!(requested_range(&changed, etag, 100), None);
+    }
+
+    #[test]
+    fn conditional_requests() {
+        let etag = "W/\"10-1\"";
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let date = http_date(modified);
+        let earlier = http_date(modified - Duration::from_secs(60));
+
+        assert!(!not_modified(&request(&[]), etag, Some(modified)));
+        assert!(not_modified(
+            &request(&[("if-none-match", "\"a\", W/\"10-1\"")]),
+            etag,
+            None
+        ));
+        assert!(not_modified(
+            &request(&[("if-none-match", "*")]),
+            etag,
+            None
+        ));
+        assert!(!not_modified(
+            &request(&[("if-none-match", "\"other\"")]),
+            etag,
+            Some(modified)
+        ));
+        assert!(not_modified(
+            &request(&[("if-modified-since", &date)]),
+            etag,
+            Some(modified)
+        ));
+        assert!(!not_modified(
+            &request(&[("if-modified-since", &earlier)]),
+            etag,
+            Some(modified)
+        ));
+        // If-None-Match manda aunque la fecha coincida
+        assert!(!not_modified(
+            &request(&[("if-none-match", "\"other\""), ("if-modified-since", &date)]),
+            etag,
+            Some(modified)
+        ));
+    }
+}
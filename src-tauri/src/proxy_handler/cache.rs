@@ -0,0 +1,264 @@
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+use tauri::http::{header, HeaderMap, Response};
+use url::Url;
+
+/// Tamaño máximo de la caché completa; al superarlo se descartan las entradas
+/// menos usadas recientemente.
+const MAX_CACHE_BYTES: i64 = 100 * 1024 * 1024;
+/// Respuestas más grandes no se guardan.
+const MAX_ENTRY_BYTES: usize = 5 * 1024 * 1024;
+/// Una entrada más antigua que esto ya no se sirve ni siquiera sin conexión.
+const MAX_STALE_SECS: i64 = 7 * 24 * 3600;
+
+/// Cabecera con la que se marca una respuesta servida desde la caché.
+pub const CACHE_HEADER: &str = "X-Sandra-Cache";
+
+#[derive(Serialize, Debug)]
+pub struct CacheStats {
+    pub entries: i64,
+    pub size_bytes: i64,
+}
+
+/// `Cache-Control` de la respuesta: `no-store` prohíbe guardarla; `no-cache` y
+/// `must-revalidate` prohíben servirla sin validar, que es justo lo que haría
+/// la caché sin conexión.
+fn cacheable(headers: &HeaderMap) -> bool {
+    !headers
+        .get_all(header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|d| d.trim().to_ascii_lowercase())
+        .any(|d| d == "no-store" || d == "no-cache" || d == "must-revalidate")
+}
+
+/// La clave de la caché es sólo la URL: una respuesta con `Vary` depende además de
+/// cabeceras de la petición (idioma, `Origin`...) y podría servirse a quien no toca.
+fn varies(headers: &HeaderMap) -> bool {
+    headers.contains_key(header::VARY)
+}
+
+/// Guarda una respuesta `GET` correcta de `app_id` a través de `connection_id`.
+pub fn store(
+    conn: &Connection,
+    connection_id: i64,
+    app_id: &str,
+    url: &Url,
+    response: &Response<Vec<u8>>,
+) -> Result<(), String> {
+    let now = chrono::Utc::now().timestamp();
+    if response.status().as_u16() != 200
+        || response.body().len() > MAX_ENTRY_BYTES
+        || !cacheable(response.headers())
+        || varies(response.headers())
+    {
+        return Ok(());
+    }
+
+    let headers: Vec<(String, String)> = response
+        .headers()
+        .iter()
+        .filter(|(name, _)| *name != header::SET_COOKIE)
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+    let headers = serde_json::to_string(&headers).map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "INSERT INTO proxy_cache (connection_id, app_id, url, status, headers, body, size_bytes, stored_at, last_used_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8)
+         ON CONFLICT(connection_id, app_id, url) DO UPDATE SET
+            status = excluded.status, headers = excluded.headers, body = excluded.body,
+            size_bytes = excluded.size_bytes, stored_at = excluded.stored_at,
+            last_used_at = excluded.last_used_at",
+        rusqlite::params![
+            connection_id,
+            app_id,
+            url.as_str(),
+            response.status().as_u16(),
+            headers,
+            response.body(),
+            response.body().len() as i64,
+            now
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    evict(conn, now)
+}
+
+/// Descarta lo demasiado antiguo y, si aún se supera el límite, lo menos usado.
+fn evict(conn: &Connection, now: i64) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM proxy_cache WHERE stored_at < ?1",
+        [now - MAX_STALE_SECS],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let total: i64 = conn
+        .query_row(
+            "SELECT COALESCE(SUM(size_bytes), 0) FROM proxy_cache",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if total <= MAX_CACHE_BYTES {
+        return Ok(());
+    }
+
+    // Se borran las entradas más antiguas en uso hasta bajar del límite
+    conn.execute(
+        "DELETE FROM proxy_cache WHERE id IN (
+            SELECT id FROM (
+                SELECT id, SUM(size_bytes) OVER (ORDER BY last_used_at ASC, id ASC) AS running
+                FROM proxy_cache
+            ) WHERE running - size_bytes < ?1
+        )",
+        [total - MAX_CACHE_BYTES],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Respuesta guardada para servirla mientras el servidor no responde,
+/// marcada con `X-Sandra-Cache: stale` y su antigüedad en `Age`.
+pub fn lookup(
+    conn: &Connection,
+    connection_id: i64,
+    app_id: &str,
+    url: &Url,
+) -> Result<Option<Response<Vec<u8>>>, String> {
+    let now = chrono::Utc::now().timestamp();
+    let row = conn
+        .query_row(
+            "SELECT id, status, headers, body, stored_at FROM proxy_cache
+             WHERE connection_id = ?1 AND app_id = ?2 AND url = ?3 AND stored_at >= ?4",
+            rusqlite::params![connection_id, app_id, url.as_str(), now - MAX_STALE_SECS],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, u16>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Vec<u8>>(3)?,
+                    row.get::<_, i64>(4)?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    let (id, status, headers, body, stored_at) = match row {
+        Some(row) => row,
+        None => return Ok(None),
+    };
+    conn.execute(
+        "UPDATE proxy_cache SET last_used_at = ?1 WHERE id = ?2",
+        [now, id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let headers: Vec<(String, String)> = serde_json::from_str(&headers).unwrap_or_default();
+    let mut builder = Response::builder().status(status);
+    for (name, value) in &headers {
+        if !name.eq_ignore_ascii_case("age") {
+            builder = builder.header(name, value);
+        }
+    }
+    builder
+        .header(CACHE_HEADER, "stale")
+        .header(header::AGE, (now - stored_at).max(0).to_string())
+        .body(body)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+pub fn stats(conn: &Connection) -> Result<CacheStats, String> {
+    conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(size_bytes), 0) FROM proxy_cache",
+        [],
+        |row| {
+            Ok(CacheStats {
+                entries: row.get(0)?,
+                size_bytes: row.get(1)?,
+            })
+        },
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Vacía la caché de una conexión y/o app (sin filtros, toda).
+pub fn purge(
+    conn: &Connection,
+    connection_id: Option<i64>,
+    app_id: Option<&str>,
+) -> Result<usize, String> {
+    conn.execute(
+        "DELETE FROM proxy_cache WHERE (?1 IS NULL OR connection_id = ?1) AND (?2 IS NULL OR app_id = ?2)",
+        rusqlite::params![connection_id, app_id],
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::storage::init_tables(&conn).unwrap();
+        conn
+    }
+
+    fn response(headers: &[(&str, &str)]) -> Response<Vec<u8>> {
+        let mut builder = Response::builder().status(200);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(b"{}".to_vec()).unwrap()
+    }
+
+    #[test]
+    fn stores_and_serves_stale_copy() {
+        let conn = db();
+        let url = Url::parse("https://srv.local/v1/items").unwrap();
+        store(
+            &conn,
+            1,
+            "crm",
+            &url,
+            &response(&[("content-type", "application/json")]),
+        )
+        .unwrap();
+
+        let cached = lookup(&conn, 1, "crm", &url).unwrap().unwrap();
+        assert_eq!(cached.body(), b"{}");
+        assert_eq!(cached.headers()[CACHE_HEADER], "stale");
+        assert_eq!(cached.headers()["content-type"], "application/json");
+
+        assert!(lookup(&conn, 1, "otra", &url).unwrap().is_none());
+        assert!(lookup(&conn, 2, "crm", &url).unwrap().is_none());
+    }
+
+    #[test]
+    fn skips_uncacheable_responses() {
+        let conn = db();
+        let url = Url::parse("https://srv.local/v1/items").unwrap();
+        store(
+            &conn,
+            1,
+            "",
+            &url,
+            &response(&[("cache-control", "private, no-store")]),
+        )
+        .unwrap();
+        store(
+            &conn,
+            1,
+            "",
+            &url,
+            &response(&[("vary", "Accept-Language")]),
+        )
+        .unwrap();
+        assert_eq!(stats(&conn).unwrap().entries, 0);
+    }
+}
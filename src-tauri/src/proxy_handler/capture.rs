@@ -0,0 +1,436 @@
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::http::{HeaderMap, Request, Response};
+use tauri::{AppHandle, Emitter, Manager};
+use url::Url;
+
+/// Peticiones que se conservan en memoria (todas las apps juntas); al llenarse
+/// se descartan las más antiguas.
+const MAX_ENTRIES: usize = 2000;
+const MAX_BODY_LIMIT: usize = 1024 * 1024;
+
+/// Cabeceras cuyo valor nunca se guarda.
+const REDACTED_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct CaptureOptions {
+    pub enabled: bool,
+    /// Guardar también los cuerpos (hasta `max_body_bytes`; los mayores se omiten).
+    pub bodies: bool,
+    pub max_body_bytes: usize,
+}
+
+impl Default for CaptureOptions {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            bodies: false,
+            max_body_bytes: 64 * 1024,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct CapturedBody {
+    pub mime_type: Option<String>,
+    pub text: String,
+    /// `Some("base64")` si el cuerpo no era texto UTF-8.
+    pub encoding: Option<&'static str>,
+}
+
+/// Una petición que atravesó el proxy (`/v1/` o externo).
+#[derive(Serialize, Clone, Debug)]
+pub struct CaptureEntry {
+    pub id: u64,
+    /// App que la originó; `""` para el contenedor.
+    pub app_id: String,
+    /// `remote` (`/v1/`), `mock` (modo simulado) o `external`.
+    pub kind: &'static str,
+    pub started_at: String,
+    pub duration_ms: u64,
+    pub method: String,
+    pub url: String,
+    /// Path y query que pidió la app (`/v1/items?page=2`), sin el prefijo de la URL
+    /// base del servidor. `None` en el tráfico externo.
+    pub local_path: Option<String>,
+    /// 0 si no hubo respuesta del servidor.
+    pub status: u16,
+    pub request_size: usize,
+    pub response_size: usize,
+    pub request_headers: Vec<(String, String)>,
+    pub response_headers: Vec<(String, String)>,
+    pub request_body: Option<CapturedBody>,
+    pub response_body: Option<CapturedBody>,
+    pub error: Option<String>,
+}
+
+/// Petición en curso: se crea antes de reenviarla y se cierra con su resultado.
+pub struct Pending {
+    started: Instant,
+    started_at: chrono::DateTime<chrono::Utc>,
+}
+
+pub fn start() -> Pending {
+    Pending {
+        started: Instant::now(),
+        started_at: chrono::Utc::now(),
+    }
+}
+
+/// Buffer circular del tráfico del proxy para el inspector.
+#[derive(Default)]
+pub struct TrafficCapture {
+    entries: Mutex<VecDeque<CaptureEntry>>,
+    options: Mutex<CaptureOptions>,
+    next_id: Mutex<u64>,
+}
+
+impl TrafficCapture {
+    pub fn options(&self) -> CaptureOptions {
+        self.options.lock().unwrap().clone()
+    }
+
+    pub fn set_options(&self, mut options: CaptureOptions) -> CaptureOptions {
+        options.max_body_bytes = options.max_body_bytes.min(MAX_BODY_LIMIT);
+        *self.options.lock().unwrap() = options.clone();
+        options
+    }
+
+    /// Entradas de una app (o de todas), de la más antigua a la más reciente.
+    pub fn list(&self, app_id: Option<&str>, limit: Option<usize>) -> Vec<CaptureEntry> {
+        let entries = self.entries.lock().unwrap();
+        let matching: Vec<CaptureEntry> = entries
+            .iter()
+            .filter(|e| app_id.is_none_or(|app_id| e.app_id == app_id))
+            .cloned()
+            .collect();
+        let skip = limit.map_or(0, |limit| matching.len().saturating_sub(limit));
+        matching.into_iter().skip(skip).collect()
+    }
+
+    pub fn clear(&self, app_id: Option<&str>) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|e| app_id.is_some_and(|app_id| e.app_id != app_id));
+        before - entries.len()
+    }
+
+    fn push(&self, mut entry: CaptureEntry) -> CaptureEntry {
+        {
+            let mut next_id = self.next_id.lock().unwrap();
+            *next_id += 1;
+            entry.id = *next_id;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= MAX_ENTRIES {
+            entries.pop_front();
+        }
+        entries.push_back(entry.clone());
+        entry
+    }
+}
+
+fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if REDACTED_HEADERS.contains(&name.as_str()) {
+                "[redacted]".to_string()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+fn capture_body(
+    body: &[u8],
+    headers: &HeaderMap,
+    options: &CaptureOptions,
+) -> Option<CapturedBody> {
+    if !options.bodies || body.is_empty() || body.len() > options.max_body_bytes {
+        return None;
+    }
+    let mime_type = headers
+        .get(tauri::http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    Some(match std::str::from_utf8(body) {
+        Ok(text) => CapturedBody {
+            mime_type,
+            text: text.to_string(),
+            encoding: None,
+        },
+        Err(_) => CapturedBody {
+            mime_type,
+            text: general_purpose::STANDARD.encode(body),
+            encoding: Some("base64"),
+        },
+    })
+}
+
+/// Registra el resultado de una petición proxificada y lo emite en `proxy-traffic`.
+pub fn record<E: std::fmt::Display>(
+    app_handle: &AppHandle,
+    pending: Pending,
+    kind: &'static str,
+    app_id: &str,
+    url: &Url,
+    request: &Request<Vec<u8>>,
+    result: &Result<Response<Vec<u8>>, E>,
+) {
+    let capture = app_handle.state::<TrafficCapture>();
+    let options = capture.options();
+    if !options.enabled {
+        return;
+    }
+
+    let (status, response_size, response_headers, response_body, error) = match result {
+        Ok(response) => (
+            response.status().as_u16(),
+            response.body().len(),
+            header_pairs(response.headers()),
+            capture_body(response.body(), response.headers(), &options),
+            None,
+        ),
+        Err(e) => (0, 0, Vec::new(), None, Some(e.to_string())),
+    };
+
+    let entry = capture.push(CaptureEntry {
+        id: 0,
+        app_id: app_id.to_string(),
+        kind,
+        started_at: pending.started_at.to_rfc3339(),
+        duration_ms: pending.started.elapsed().as_millis() as u64,
+        method: request.method().to_string(),
+        url: url.to_string(),
+        local_path: (kind != "external")
+            .then(|| request.uri().path_and_query().map(|pq| pq.to_string()))
+            .flatten(),
+        status,
+        request_size: request.body().len(),
+        response_size,
+        request_headers: header_pairs(request.headers()),
+        response_headers,
+        request_body: capture_body(request.body(), request.headers(), &options),
+        response_body,
+        error,
+    });
+    let _ = app_handle.emit("proxy-traffic", &entry);
+}
+
+fn har_headers(headers: &[(String, String)]) -> Value {
+    headers
+        .iter()
+        .map(|(name, value)| json!({ "name": name, "value": value }))
+        .collect()
+}
+
+fn har_mime(headers: &[(String, String)]) -> String {
+    headers
+        .iter()
+        .find(|(name, _)| name == "content-type")
+        .map(|(_, value)| value.clone())
+        .unwrap_or_default()
+}
+
+/// Documento HAR 1.2 con las entradas dadas.
+pub fn to_har(entries: &[CaptureEntry], creator_version: &str) -> Value {
+    let entries: Vec<Value> = entries
+        .iter()
+        .map(|entry| {
+            let query: Vec<Value> = Url::parse(&entry.url)
+                .map(|url| {
+                    url.query_pairs()
+                        .map(|(name, value)| json!({ "name": name, "value": value }))
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            let mut request = json!({
+                "method": entry.method,
+                "url": entry.url,
+                "httpVersion": "HTTP/1.1",
+                "cookies": [],
+                "headers": har_headers(&entry.request_headers),
+                "queryString": query,
+                "headersSize": -1,
+                "bodySize": entry.request_size,
+            });
+            if let Some(body) = &entry.request_body {
+                request["postData"] = json!({
+                    "mimeType": body.mime_type.clone().unwrap_or_default(),
+                    "text": body.text,
+                });
+            }
+
+            let mut content = json!({
+                "size": entry.response_size,
+                "mimeType": har_mime(&entry.response_headers),
+            });
+            if let Some(body) = &entry.response_body {
+                content["text"] = json!(body.text);
+                if let Some(encoding) = body.encoding {
+                    content["encoding"] = json!(encoding);
+                }
+            }
+            let redirect_url = entry
+                .response_headers
+                .iter()
+                .find(|(name, _)| name == "location")
+                .map(|(_, value)| value.clone())
+                .unwrap_or_default();
+
+            let mut har_entry = json!({
+                "startedDateTime": entry.started_at,
+                "time": entry.duration_ms,
+                "request": request,
+                "response": {
+                    "status": entry.status,
+                    "statusText": tauri::http::StatusCode::from_u16(entry.status)
+                        .ok()
+                        .and_then(|s| s.canonical_reason())
+                        .unwrap_or(""),
+                    "httpVersion": "HTTP/1.1",
+                    "cookies": [],
+                    "headers": har_headers(&entry.response_headers),
+                    "content": content,
+                    "redirectURL": redirect_url,
+                    "headersSize": -1,
+                    "bodySize": entry.response_size,
+                },
+                "cache": {},
+                "timings": { "send": 0, "wait": entry.duration_ms, "receive": 0 },
+                "_appId": entry.app_id,
+                "_kind": entry.kind,
+            });
+            // El modo simulado encaja por este path, no por el de la URL del servidor
+            if let Some(local_path) = &entry.local_path {
+                har_entry["_localPath"] = json!(local_path);
+            }
+            if let Some(error) = &entry.error {
+                har_entry["comment"] = json!(error);
+            }
+            har_entry
+        })
+        .collect();
+
+    json!({
+        "log": {
+            "version": "1.2",
+            "creator": { "name": "Sandra Desktop Container", "version": creator_version },
+            "entries": entries,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry() -> CaptureEntry {
+        CaptureEntry {
+            id: 1,
+            app_id: "crm".into(),
+            kind: "remote",
+            started_at: "2026-01-01T00:00:00+00:00".into(),
+            duration_ms: 42,
+            method: "POST".into(),
+            url: "https://srv.local/v1/items?page=2&q=a%20b".into(),
+            local_path: Some("/v1/items?page=2&q=a%20b".into()),
+            status: 302,
+            request_size: 7,
+            response_size: 3,
+            request_headers: vec![("content-type".into(), "application/json".into())],
+            response_headers: vec![
+                ("content-type".into(), "image/png".into()),
+                ("location".into(), "/v1/items/9".into()),
+            ],
+            request_body: Some(CapturedBody {
+                mime_type: Some("application/json".into()),
+                text: "{\"a\":1}".into(),
+                encoding: None,
+            }),
+            response_body: Some(CapturedBody {
+                mime_type: Some("image/png".into()),
+                text: "AAEC".into(),
+                encoding: Some("base64"),
+            }),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn har_document_structure() {
+        let har = to_har(&[entry()], "1.2.3");
+        let log = &har["log"];
+        assert_eq!(log["version"], "1.2");
+        assert_eq!(log["creator"]["version"], "1.2.3");
+
+        let e = &log["entries"][0];
+        assert_eq!(e["time"], 42);
+        assert_eq!(e["_appId"], "crm");
+        assert_eq!(e["_kind"], "remote");
+        assert_eq!(e["_localPath"], "/v1/items?page=2&q=a%20b");
+        assert!(e.get("comment").is_none());
+
+        let request = &e["request"];
+        assert_eq!(request["method"], "POST");
+        assert_eq!(request["bodySize"], 7);
+        assert_eq!(
+            request["queryString"],
+            json!([{ "name": "page", "value": "2" }, { "name": "q", "value": "a b" }])
+        );
+        assert_eq!(request["postData"]["mimeType"], "application/json");
+        assert_eq!(request["postData"]["text"], "{\"a\":1}");
+
+        let response = &e["response"];
+        assert_eq!(response["status"], 302);
+        assert_eq!(response["statusText"], "Found");
+        assert_eq!(response["redirectURL"], "/v1/items/9");
+        assert_eq!(response["content"]["mimeType"], "image/png");
+        assert_eq!(response["content"]["text"], "AAEC");
+        assert_eq!(response["content"]["encoding"], "base64");
+    }
+
+    #[test]
+    fn har_entry_without_response() {
+        let mut failed = entry();
+        failed.status = 0;
+        failed.request_body = None;
+        failed.response_body = None;
+        failed.response_headers.clear();
+        failed.error = Some("connection refused".into());
+
+        let har = to_har(&[failed], "1.0.0");
+        let e = &har["log"]["entries"][0];
+        assert_eq!(e["comment"], "connection refused");
+        assert!(e["request"].get("postData").is_none());
+        assert_eq!(e["response"]["statusText"], "");
+        assert_eq!(e["response"]["redirectURL"], "");
+        assert!(e["response"]["content"].get("text").is_none());
+    }
+
+    #[test]
+    fn credentials_are_redacted() {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer secret".parse().unwrap());
+        headers.insert("cookie", "sid=1".parse().unwrap());
+        headers.insert("accept", "*/*".parse().unwrap());
+        let pairs = header_pairs(&headers);
+        assert!(pairs.contains(&("authorization".into(), "[redacted]".into())));
+        assert!(pairs.contains(&("cookie".into(), "[redacted]".into())));
+        assert!(pairs.contains(&("accept".into(), "*/*".into())));
+    }
+}
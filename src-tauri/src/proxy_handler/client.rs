@@ -0,0 +1,94 @@
+use super::policy::PublicResolver;
+use super::ProxyError;
+use reqwest::Client;
+
+/// User-Agent de navegador para que los sitios externos no sirvan versiones degradadas.
+const EXTERNAL_USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+/// Clientes HTTP compartidos por todas las peticiones del protocolo.
+/// Se crean una sola vez para reutilizar el pool de conexiones (keep-alive, TLS);
+/// los timeouts se fijan por petición para respetar la configuración vigente.
+pub struct ProxyClients {
+    /// Tráfico `/v1/` hacia los servidores propios (certificados internos sin validar).
+    pub remote: Client,
+    /// Proxy de sitios externos; nunca conecta a direcciones privadas.
+    pub external: Client,
+    /// Proxy externo hacia destinos cubiertos por una regla `allow` con `private: true`.
+    pub external_private: Client,
+    /// TLS de los WebSocket `/v1/ws/`, con la misma política que `remote`.
+    pub remote_tls: native_tls::TlsConnector,
+}
+
+impl ProxyClients {
+    pub fn new() -> Result<Self, String> {
+        let remote = Client::builder()
+            .danger_accept_invalid_certs(true)
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        // Sin redirecciones automáticas: `proxy_arbitrary_url` las sigue para gestionar cookies
+        let external_builder = || {
+            Client::builder()
+                .user_agent(EXTERNAL_USER_AGENT)
+                .redirect(reqwest::redirect::Policy::none())
+                .danger_accept_invalid_certs(true)
+        };
+        let external = external_builder()
+            .dns_resolver(PublicResolver)
+            .build()
+            .map_err(|e| e.to_string())?;
+        let external_private = external_builder().build().map_err(|e| e.to_string())?;
+
+        let remote_tls = native_tls::TlsConnector::builder()
+            .danger_accept_invalid_certs(true)
+            .min_protocol_version(Some(native_tls::Protocol::Tlsv12))
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            remote,
+            external,
+            external_private,
+            remote_tls,
+        })
+    }
+}
+
+/// La respuesta supera `proxy.max_body_bytes`; el protocolo contesta 502.
+#[derive(Debug)]
+pub struct BodyTooLarge {
+    pub limit: usize,
+}
+
+impl std::fmt::Display for BodyTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "La respuesta supera el máximo de {} bytes; use download_app_file para ficheros grandes",
+            self.limit
+        )
+    }
+}
+
+impl std::error::Error for BodyTooLarge {}
+
+/// Lee el cuerpo por trozos a medida que llega, sin bloquear ningún hilo.
+/// No es streaming: `UriSchemeResponder::respond` sólo acepta el cuerpo completo, así
+/// que se acumula en memoria, como mucho `limit` bytes (se corta antes si el servidor
+/// anuncia un `Content-Length` mayor). Las descargas grandes no pasan por aquí: van a
+/// disco con el comando `download_app_file` (ver `download.rs`).
+pub async fn read_body(mut resp: reqwest::Response, limit: usize) -> Result<Vec<u8>, ProxyError> {
+    let announced = resp.content_length().unwrap_or(0);
+    if announced > limit as u64 {
+        return Err(BodyTooLarge { limit }.into());
+    }
+
+    let mut body = Vec::with_capacity(announced as usize);
+    while let Some(chunk) = resp.chunk().await? {
+        if body.len() + chunk.len() > limit {
+            return Err(BodyTooLarge { limit }.into());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
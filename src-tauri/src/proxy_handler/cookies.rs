@@ -0,0 +1,649 @@
+use crate::storage::DbState;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::sync::atomic::{AtomicI64, Ordering};
+use tauri::http::header::{HeaderMap, SET_COOKIE};
+use tauri::{AppHandle, Manager};
+use url::Url;
+
+/// Cada cuánto se purgan del jar las cookies caducadas (segundos).
+const SWEEP_INTERVAL_SECS: i64 = 300;
+static LAST_SWEEP: AtomicI64 = AtomicI64::new(0);
+
+/// Sufijos públicos de varios niveles más habituales. Los de un solo nivel (`com`, `es`...)
+/// se detectan por no tener punto. No es la Public Suffix List completa, pero cubre los casos
+/// que permitirían a un sitio fijar cookies para todo un registro.
+const MULTI_LABEL_PUBLIC_SUFFIXES: &[&str] = &[
+    "co.uk",
+    "org.uk",
+    "gov.uk",
+    "ac.uk",
+    "me.uk",
+    "ltd.uk",
+    "plc.uk",
+    "net.uk",
+    "sch.uk",
+    "com.au",
+    "net.au",
+    "org.au",
+    "edu.au",
+    "gov.au",
+    "co.nz",
+    "org.nz",
+    "govt.nz",
+    "co.jp",
+    "ne.jp",
+    "or.jp",
+    "ac.jp",
+    "go.jp",
+    "com.br",
+    "net.br",
+    "org.br",
+    "gov.br",
+    "com.mx",
+    "org.mx",
+    "gob.mx",
+    "com.ar",
+    "gob.ar",
+    "com.es",
+    "org.es",
+    "gob.es",
+    "nom.es",
+    "edu.es",
+    "com.co",
+    "gov.co",
+    "com.pe",
+    "gob.pe",
+    "cl.cl",
+    "gob.cl",
+    "com.uy",
+    "com.ve",
+    "com.ec",
+    "gob.ec",
+    "co.in",
+    "gov.in",
+    "com.cn",
+    "gov.cn",
+    "com.hk",
+    "com.sg",
+    "com.tw",
+    "co.kr",
+    "co.za",
+    "com.tr",
+    "com.ru",
+    "co.il",
+    "github.io",
+    "gitlab.io",
+    "herokuapp.com",
+    "azurewebsites.net",
+    "cloudfront.net",
+    "appspot.com",
+    "vercel.app",
+    "netlify.app",
+    "pages.dev",
+    "workers.dev",
+    "web.app",
+    "firebaseapp.com",
+];
+
+/// Cookie tal como se guarda en `proxy_cookies`.
+#[derive(Serialize, Debug, Clone)]
+pub struct StoredCookie {
+    /// App (o ventana) propietaria; `""` para el contenedor principal.
+    pub app_id: String,
+    pub domain: String,
+    /// Sin atributo `Domain`: sólo se envía al host exacto que la fijó.
+    pub host_only: bool,
+    pub path: String,
+    pub name: String,
+    pub value: String,
+    /// Segundos UNIX; `None` = cookie de sesión (se conserva hasta que se borre).
+    pub expires_at: Option<i64>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: Option<String>,
+}
+
+impl StoredCookie {
+    fn is_expired(&self, now: i64) -> bool {
+        matches!(self.expires_at, Some(expires) if expires <= now)
+    }
+
+    fn matches(&self, url: &Url, now: i64) -> bool {
+        let host = match url.host_str() {
+            Some(host) => host.to_ascii_lowercase(),
+            None => return false,
+        };
+        let domain_ok = if self.host_only {
+            host == self.domain
+        } else {
+            domain_match(&host, &self.domain)
+        };
+
+        domain_ok
+            && path_match(url.path(), &self.path)
+            && (!self.secure || url.scheme() == "https")
+            && !self.is_expired(now)
+    }
+}
+
+/// RFC 6265 §5.1.3: una IP sólo coincide consigo misma (`2.3.4` no es dominio padre de `1.2.3.4`).
+fn domain_match(host: &str, domain: &str) -> bool {
+    host == domain
+        || (!is_ip_literal(host)
+            && host.ends_with(domain)
+            && host[..host.len() - domain.len()].ends_with('.'))
+}
+
+/// `host_str()` devuelve las IPv6 entre corchetes.
+fn is_ip_literal(host: &str) -> bool {
+    host.starts_with('[') || host.parse::<std::net::IpAddr>().is_ok()
+}
+
+/// RFC 6265 §5.3 paso 5: un `Domain` que sea sufijo público no puede abarcar otros sitios.
+fn is_public_suffix(domain: &str) -> bool {
+    !domain.contains('.') || MULTI_LABEL_PUBLIC_SUFFIXES.contains(&domain)
+}
+
+/// RFC 6265 §5.1.4
+fn path_match(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+/// RFC 6265 §5.1.4: directorio de la URL que fijó la cookie.
+fn default_path(url: &Url) -> String {
+    let path = url.path();
+    match path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(i) => path[..i].to_string(),
+    }
+}
+
+/// Fechas de `Expires`: RFC 1123 y la variante con guiones que aún usan muchos sitios.
+fn parse_http_date(value: &str) -> Option<i64> {
+    let value = value.trim();
+    if let Ok(date) = chrono::DateTime::parse_from_rfc2822(value) {
+        return Some(date.timestamp());
+    }
+    for format in ["%a, %d-%b-%Y %H:%M:%S GMT", "%A, %d-%b-%y %H:%M:%S GMT"] {
+        if let Ok(date) = chrono::NaiveDateTime::parse_from_str(value, format) {
+            return Some(date.and_utc().timestamp());
+        }
+    }
+    None
+}
+
+/// Interpreta una cabecera `Set-Cookie` recibida desde `url`.
+/// Devuelve `None` si es inválida o pretende fijarse para otro dominio.
+pub fn parse_set_cookie(header: &str, url: &Url, app_id: &str) -> Option<StoredCookie> {
+    let host = url.host_str()?.to_ascii_lowercase();
+    let mut parts = header.split(';');
+    let (name, value) = parts.next()?.split_once('=')?;
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let mut cookie = StoredCookie {
+        app_id: app_id.to_string(),
+        domain: host.clone(),
+        host_only: true,
+        path: default_path(url),
+        name: name.to_string(),
+        value: value.trim().to_string(),
+        expires_at: None,
+        secure: false,
+        http_only: false,
+        same_site: None,
+    };
+    let mut max_age: Option<i64> = None;
+
+    for attribute in parts {
+        let (key, val) = match attribute.split_once('=') {
+            Some((key, val)) => (key.trim().to_ascii_lowercase(), val.trim()),
+            None => (attribute.trim().to_ascii_lowercase(), ""),
+        };
+        match key.as_str() {
+            "domain" if !val.is_empty() => {
+                let domain = val.trim_start_matches('.').to_ascii_lowercase();
+                if !domain_match(&host, &domain) {
+                    return None;
+                }
+                if is_public_suffix(&domain) {
+                    // Sólo se admite como cookie del host exacto (p. ej. un servidor en `localhost`)
+                    if domain != host {
+                        return None;
+                    }
+                    continue;
+                }
+                cookie.domain = domain;
+                cookie.host_only = false;
+            }
+            "path" if val.starts_with('/') => cookie.path = val.to_string(),
+            "expires" => cookie.expires_at = parse_http_date(val).or(cookie.expires_at),
+            "max-age" => max_age = val.parse().ok(),
+            "secure" => cookie.secure = true,
+            "httponly" => cookie.http_only = true,
+            "samesite" => cookie.same_site = Some(val.to_string()),
+            _ => {}
+        }
+    }
+
+    // Max-Age tiene prioridad sobre Expires
+    if let Some(seconds) = max_age {
+        cookie.expires_at = Some(now + seconds.max(0));
+    }
+    // Un sitio en http no puede fijar cookies Secure
+    if cookie.secure && url.scheme() != "https" {
+        return None;
+    }
+    Some(cookie)
+}
+
+/// Guarda (o borra, si ya caducó) una cookie. Una misma (app, dominio, host_only, path, nombre)
+/// se reemplaza.
+pub fn store(conn: &Connection, cookie: &StoredCookie) -> Result<(), String> {
+    let now = chrono::Utc::now().timestamp();
+    if cookie.is_expired(now) {
+        conn.execute(
+            "DELETE FROM proxy_cookies
+             WHERE app_id = ?1 AND domain = ?2 AND host_only = ?3 AND path = ?4 AND name = ?5",
+            rusqlite::params![
+                cookie.app_id,
+                cookie.domain,
+                cookie.host_only,
+                cookie.path,
+                cookie.name
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    conn.execute(
+        "INSERT INTO proxy_cookies (app_id, domain, host_only, path, name, value, expires_at, secure, http_only, same_site)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+         ON CONFLICT(app_id, domain, host_only, path, name) DO UPDATE SET
+            value = excluded.value, expires_at = excluded.expires_at,
+            secure = excluded.secure, http_only = excluded.http_only, same_site = excluded.same_site",
+        rusqlite::params![
+            cookie.app_id,
+            cookie.domain,
+            cookie.host_only,
+            cookie.path,
+            cookie.name,
+            cookie.value,
+            cookie.expires_at,
+            cookie.secure,
+            cookie.http_only,
+            cookie.same_site
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Valor de la cabecera `Cookie` para una petición de `app_id` a `url`.
+/// Las cookies con path más específico van primero (RFC 6265 §5.4).
+pub fn cookie_header(conn: &Connection, app_id: &str, url: &Url) -> Result<Option<String>, String> {
+    let host = match url.host_str() {
+        Some(host) => host.to_ascii_lowercase(),
+        None => return Ok(None),
+    };
+    let now = chrono::Utc::now().timestamp();
+    sweep_expired(conn, now)?;
+
+    // Sólo las filas del host y de sus dominios padre pueden coincidir
+    let candidates: Vec<&str> = std::iter::once(host.as_str())
+        .chain(host.match_indices('.').map(|(i, _)| &host[i + 1..]))
+        .collect();
+    let placeholders = (0..candidates.len())
+        .map(|i| format!("?{}", i + 3))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let sql = format!(
+        "{} WHERE app_id = ?1 AND (expires_at IS NULL OR expires_at > ?2) AND domain IN ({}) ORDER BY id",
+        SELECT_COOKIES, placeholders
+    );
+
+    let mut params: Vec<&dyn rusqlite::ToSql> = vec![&app_id, &now];
+    params.extend(candidates.iter().map(|c| c as &dyn rusqlite::ToSql));
+
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let mut cookies: Vec<StoredCookie> = stmt
+        .query_map(params.as_slice(), read_cookie)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|cookie| cookie.matches(url, now))
+        .collect();
+
+    if cookies.is_empty() {
+        return Ok(None);
+    }
+    cookies.sort_by_key(|c| std::cmp::Reverse(c.path.len()));
+    Ok(Some(
+        cookies
+            .iter()
+            .map(|c| format!("{}={}", c.name, c.value))
+            .collect::<Vec<_>>()
+            .join("; "),
+    ))
+}
+
+const SELECT_COOKIES: &str =
+    "SELECT app_id, domain, host_only, path, name, value, expires_at, secure, http_only, same_site
+     FROM proxy_cookies";
+
+fn read_cookie(row: &rusqlite::Row) -> rusqlite::Result<StoredCookie> {
+    Ok(StoredCookie {
+        app_id: row.get(0)?,
+        domain: row.get(1)?,
+        host_only: row.get(2)?,
+        path: row.get(3)?,
+        name: row.get(4)?,
+        value: row.get(5)?,
+        expires_at: row.get(6)?,
+        secure: row.get(7)?,
+        http_only: row.get(8)?,
+        same_site: row.get(9)?,
+    })
+}
+
+/// Purga las cookies caducadas, como mucho una vez cada `SWEEP_INTERVAL_SECS`.
+/// Las lecturas ya ignoran las caducadas, así que no hace falta hacerlo en cada petición.
+fn sweep_expired(conn: &Connection, now: i64) -> Result<(), String> {
+    let last = LAST_SWEEP.load(Ordering::Relaxed);
+    if now - last < SWEEP_INTERVAL_SECS
+        || LAST_SWEEP
+            .compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed)
+            .is_err()
+    {
+        return Ok(());
+    }
+    conn.execute(
+        "DELETE FROM proxy_cookies WHERE expires_at IS NOT NULL AND expires_at <= ?1",
+        [now],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Cookies vigentes, filtradas por app y/o sitio (dominio o cualquier subdominio).
+pub fn list(
+    conn: &Connection,
+    app_id: Option<&str>,
+    site: Option<&str>,
+) -> Result<Vec<StoredCookie>, String> {
+    let now = chrono::Utc::now().timestamp();
+    sweep_expired(conn, now)?;
+
+    let sql = format!(
+        "{} WHERE (?1 IS NULL OR app_id = ?1) AND (expires_at IS NULL OR expires_at > ?2)
+         ORDER BY domain, path, name",
+        SELECT_COOKIES
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let cookies = stmt
+        .query_map(rusqlite::params![app_id, now], read_cookie)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(match site {
+        Some(site) => {
+            let site = site.trim_start_matches('.').to_ascii_lowercase();
+            cookies
+                .into_iter()
+                .filter(|c| domain_match(&c.domain, &site))
+                .collect()
+        }
+        None => cookies,
+    })
+}
+
+/// Borra las cookies de una app y/o sitio. Sin filtros, vacía el jar completo.
+pub fn clear(conn: &Connection, app_id: Option<&str>, site: Option<&str>) -> Result<usize, String> {
+    let site = site.map(|s| s.trim_start_matches('.').to_ascii_lowercase());
+    conn.execute(
+        "DELETE FROM proxy_cookies
+         WHERE (?1 IS NULL OR app_id = ?1)
+           AND (?2 IS NULL OR domain = ?2
+                OR (length(domain) > length(?2) AND substr(domain, -length(?2) - 1) = '.' || ?2))",
+        rusqlite::params![app_id, site],
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Cabecera `Cookie` del jar para una petición saliente del proxy externo.
+pub fn request_header(app_handle: &AppHandle, app_id: &str, url: &Url) -> Option<String> {
+    let state = app_handle.state::<DbState>();
+    let conn = state.0.lock().ok()?;
+    cookie_header(&conn, app_id, url).unwrap_or_else(|e| {
+        println!("⚠️ [Cookies] No se pudo leer el jar: {}", e);
+        None
+    })
+}
+
+/// Guarda en el jar todas las `Set-Cookie` de una respuesta recibida desde `url`.
+pub fn store_response(app_handle: &AppHandle, app_id: &str, url: &Url, headers: &HeaderMap) {
+    let cookies: Vec<StoredCookie> = headers
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .filter_map(|header| parse_set_cookie(header, url, app_id))
+        .collect();
+    if cookies.is_empty() {
+        return;
+    }
+
+    let state = app_handle.state::<DbState>();
+    let conn = match state.0.lock() {
+        Ok(conn) => conn,
+        Err(_) => return,
+    };
+    for cookie in &cookies {
+        if let Err(e) = store(&conn, cookie) {
+            println!("⚠️ [Cookies] No se pudo guardar '{}': {}", cookie.name, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    fn jar() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::storage::init_tables(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn parses_attributes_and_defaults() {
+        let cookie = parse_set_cookie(
+            "sid=abc; Path=/app; Max-Age=60; Secure; HttpOnly; SameSite=Lax",
+            &url("https://Example.com/login/form"),
+            "crm",
+        )
+        .unwrap();
+        assert_eq!(cookie.app_id, "crm");
+        assert_eq!(cookie.domain, "example.com");
+        assert!(cookie.host_only);
+        assert_eq!(cookie.path, "/app");
+        assert!(cookie.secure && cookie.http_only);
+        assert_eq!(cookie.same_site.as_deref(), Some("Lax"));
+        assert!(cookie.expires_at.unwrap() > chrono::Utc::now().timestamp());
+
+        let cookie = parse_set_cookie("a=1", &url("https://example.com/login/form"), "").unwrap();
+        assert_eq!(cookie.path, "/login");
+    }
+
+    #[test]
+    fn domain_attribute_must_cover_the_host() {
+        let from = url("https://www.example.com/");
+        let cookie = parse_set_cookie("a=1; Domain=.Example.com", &from, "").unwrap();
+        assert_eq!(cookie.domain, "example.com");
+        assert!(!cookie.host_only);
+
+        assert!(parse_set_cookie("a=1; Domain=other.com", &from, "").is_none());
+        assert!(parse_set_cookie("a=1; Domain=ww.example.com", &from, "").is_none());
+    }
+
+    #[test]
+    fn rejects_public_suffix_domains() {
+        assert!(parse_set_cookie("a=1; Domain=com", &url("https://example.com/"), "").is_none());
+        assert!(parse_set_cookie("a=1; Domain=co.uk", &url("https://shop.co.uk/"), "").is_none());
+        assert!(
+            parse_set_cookie("a=1; Domain=github.io", &url("https://me.github.io/"), "").is_none()
+        );
+
+        // Un host que es en sí un sufijo público conserva la cookie sólo para él
+        let cookie =
+            parse_set_cookie("a=1; Domain=localhost", &url("http://localhost/"), "").unwrap();
+        assert_eq!(cookie.domain, "localhost");
+        assert!(cookie.host_only);
+    }
+
+    #[test]
+    fn ip_hosts_only_accept_their_own_domain() {
+        let from = url("http://10.1.2.3/");
+        assert!(parse_set_cookie("a=1; Domain=1.2.3", &from, "").is_none());
+        let cookie = parse_set_cookie("a=1; Domain=10.1.2.3", &from, "").unwrap();
+        assert_eq!(cookie.domain, "10.1.2.3");
+    }
+
+    #[test]
+    fn host_only_and_domain_cookies_coexist() {
+        let conn = jar();
+        let from = url("https://example.com/");
+        store(&conn, &parse_set_cookie("a=host", &from, "").unwrap()).unwrap();
+        store(
+            &conn,
+            &parse_set_cookie("a=domain; Domain=example.com", &from, "").unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            cookie_header(&conn, "", &from).unwrap().as_deref(),
+            Some("a=host; a=domain")
+        );
+        assert_eq!(
+            cookie_header(&conn, "", &url("https://www.example.com/"))
+                .unwrap()
+                .as_deref(),
+            Some("a=domain")
+        );
+    }
+
+    #[test]
+    fn migrates_the_old_unique_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE proxy_cookies (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                app_id TEXT NOT NULL DEFAULT '',
+                domain TEXT NOT NULL,
+                host_only BOOLEAN NOT NULL DEFAULT 1,
+                path TEXT NOT NULL DEFAULT '/',
+                name TEXT NOT NULL,
+                value TEXT NOT NULL,
+                expires_at INTEGER,
+                secure BOOLEAN NOT NULL DEFAULT 0,
+                http_only BOOLEAN NOT NULL DEFAULT 0,
+                same_site TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                UNIQUE(app_id, domain, path, name)
+            );
+            INSERT INTO proxy_cookies (domain, name, value) VALUES ('example.com', 'a', 'host');",
+        )
+        .unwrap();
+        crate::storage::init_tables(&conn).unwrap();
+
+        let from = url("https://example.com/");
+        store(
+            &conn,
+            &parse_set_cookie("a=domain; Domain=example.com", &from, "").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(list(&conn, None, None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn rejects_invalid_cookies() {
+        let from = url("http://example.com/");
+        assert!(parse_set_cookie("novalue", &from, "").is_none());
+        assert!(parse_set_cookie("=1", &from, "").is_none());
+        assert!(parse_set_cookie("a=1; Secure", &from, "").is_none());
+    }
+
+    #[test]
+    fn domain_and_path_matching() {
+        assert!(domain_match("example.com", "example.com"));
+        assert!(domain_match("a.b.example.com", "example.com"));
+        assert!(!domain_match("badexample.com", "example.com"));
+        assert!(domain_match("1.2.3.4", "1.2.3.4"));
+        assert!(!domain_match("1.2.3.4", "2.3.4"));
+        assert!(!domain_match("[::1:2]", "1:2]"));
+
+        assert!(path_match("/", "/"));
+        assert!(path_match("/docs/a", "/docs"));
+        assert!(path_match("/docs/a", "/docs/"));
+        assert!(!path_match("/docsx", "/docs"));
+        assert!(!path_match("/doc", "/docs"));
+    }
+
+    #[test]
+    fn cookie_header_only_sends_matching_cookies() {
+        let conn = jar();
+        let from = url("https://www.example.com/");
+        for header in ["a=1; Domain=example.com", "b=2; Path=/app", "c=3"] {
+            store(&conn, &parse_set_cookie(header, &from, "crm").unwrap()).unwrap();
+        }
+        store(
+            &conn,
+            &parse_set_cookie("x=9", &url("https://other.com/"), "crm").unwrap(),
+        )
+        .unwrap();
+        store(&conn, &parse_set_cookie("y=8", &from, "otra").unwrap()).unwrap();
+
+        let header = cookie_header(&conn, "crm", &url("https://www.example.com/app/x")).unwrap();
+        assert_eq!(header.as_deref(), Some("b=2; a=1; c=3"));
+
+        let header = cookie_header(&conn, "crm", &url("https://api.example.com/")).unwrap();
+        assert_eq!(header.as_deref(), Some("a=1"));
+
+        assert_eq!(
+            cookie_header(&conn, "crm", &url("https://example.org/")).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn clear_treats_site_literally() {
+        let conn = jar();
+        store(
+            &conn,
+            &parse_set_cookie("a=1", &url("https://www.example.com/"), "").unwrap(),
+        )
+        .unwrap();
+        store(
+            &conn,
+            &parse_set_cookie("b=1", &url("https://wwwxexample.com/"), "").unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(clear(&conn, None, Some("_example.com")).unwrap(), 0);
+        assert_eq!(clear(&conn, None, Some("%")).unwrap(), 0);
+        assert_eq!(clear(&conn, None, Some("example.com")).unwrap(), 1);
+        assert_eq!(list(&conn, None, None).unwrap().len(), 1);
+    }
+}
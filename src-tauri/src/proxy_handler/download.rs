@@ -0,0 +1,189 @@
+use super::{forward, routing, ProxyClients};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use tokio::io::AsyncWriteExt;
+
+/// Descarga terminada en la carpeta de descargas del usuario.
+#[derive(Serialize, Debug)]
+pub struct Download {
+    pub path: String,
+    pub bytes: u64,
+}
+
+/// Descarga `path` (`/v1/...`, con query opcional) de la conexión de la app directamente
+/// a disco, trozo a trozo.
+///
+/// El protocolo `sandra-app://` no puede transmitir en streaming: el responder de Tauri
+/// sólo acepta el cuerpo completo, así que allí todo se acumula en memoria y se corta en
+/// `proxy.max_body_bytes`. Los ficheros grandes se piden por aquí y nunca pasan por el
+/// webview ni por ese límite. Igual que los WebSocket, la app sale de su ventana propia
+/// (`app-<id>`) y se rechazan las llamadas desde la principal.
+pub async fn download(
+    app_handle: &AppHandle,
+    webview: &str,
+    path: &str,
+    file_name: Option<&str>,
+) -> Result<Download, String> {
+    let (path, query) = match path.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (path, None),
+    };
+    if !path.starts_with("/v1/") {
+        return Err("Sólo se admiten rutas /v1/".into());
+    }
+
+    let app_id = super::webview_app_id(webview).ok_or(
+        "Las descargas sólo se piden desde la ventana propia de una app: \
+         desde la ventana principal no se sabe qué app lo pide",
+    )?;
+    let app_id = Some(app_id.as_str());
+    let conn =
+        super::resolve_connection(app_handle, app_id)?.ok_or("No hay ninguna conexión activa")?;
+    if conn.mock.as_ref().is_some_and(|mock| mock.enabled) {
+        return Err("El modo simulado no admite descargas".into());
+    }
+    let url = routing::upstream_url(&conn, path, query)?;
+
+    let mut headers = tauri::http::HeaderMap::new();
+    forward::add_identity(&mut headers, &super::forward_context(app_handle, app_id));
+
+    // El timeout es de inactividad (cabeceras y cada trozo), no de la descarga completa
+    let timeout = crate::runtime_config::current(app_handle)
+        .proxy
+        .remote_timeout();
+    let clients = app_handle.state::<ProxyClients>();
+    let mut resp = tokio::time::timeout(
+        timeout,
+        clients.remote.get(url.clone()).headers(headers).send(),
+    )
+    .await
+    .map_err(|_| format!("Tiempo de espera agotado pidiendo {}", url))?
+    .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("El servidor respondió {} a {}", resp.status(), url));
+    }
+
+    let dir = app_handle
+        .path()
+        .download_dir()
+        .map_err(|e| e.to_string())?;
+    let name =
+        sanitize_file_name(file_name.unwrap_or_else(|| path.rsplit('/').next().unwrap_or("")));
+    let target = unique_path(&dir, &name);
+    // Se escribe en `.part` y se renombra al terminar: nunca queda un fichero a medias
+    // con el nombre final
+    let partial = target.with_file_name(format!("{}.part", name_of(&target)));
+
+    let mut file = tokio::fs::File::create(&partial)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut bytes: u64 = 0;
+    let result: Result<(), String> = async {
+        loop {
+            let chunk = tokio::time::timeout(timeout, resp.chunk())
+                .await
+                .map_err(|_| format!("Tiempo de espera agotado descargando {}", url))?
+                .map_err(|e| e.to_string())?;
+            let Some(chunk) = chunk else { break };
+            file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+            bytes += chunk.len() as u64;
+        }
+        file.flush().await.map_err(|e| e.to_string())
+    }
+    .await;
+    drop(file);
+
+    if let Err(e) = result {
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(e);
+    }
+    tokio::fs::rename(&partial, &target)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    println!(
+        "⬇️ [Descarga] {} -> {} ({} bytes)",
+        url,
+        target.display(),
+        bytes
+    );
+    Ok(Download {
+        path: target.to_string_lossy().into_owned(),
+        bytes,
+    })
+}
+
+/// Nombre seguro dentro de la carpeta de descargas: sin separadores, sin `..` ni
+/// caracteres de control.
+fn sanitize_file_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_control() || matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    let name = name.trim().trim_start_matches('.');
+    if name.is_empty() {
+        "descarga".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+fn name_of(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// `informe.pdf`, `informe (1).pdf`, `informe (2).pdf`... sin sobrescribir nada.
+fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    let candidate = dir.join(name);
+    if !candidate.exists() {
+        return candidate;
+    }
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{}", ext)),
+        _ => (name, String::new()),
+    };
+    (1..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, ext)))
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_names_stay_inside_the_download_dir() {
+        assert_eq!(sanitize_file_name("informe.pdf"), "informe.pdf");
+        assert_eq!(sanitize_file_name("../../etc/passwd"), "_.._etc_passwd");
+        assert_eq!(sanitize_file_name("..\\win.ini"), "_win.ini");
+        assert_eq!(sanitize_file_name(".bashrc"), "bashrc");
+        assert_eq!(sanitize_file_name(""), "descarga");
+        assert_eq!(sanitize_file_name(".."), "descarga");
+    }
+
+    #[test]
+    fn existing_files_are_not_overwritten() {
+        let dir = std::env::temp_dir().join(format!("sandra-dl-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        assert_eq!(unique_path(&dir, "a.pdf"), dir.join("a.pdf"));
+        std::fs::write(dir.join("a.pdf"), b"x").unwrap();
+        assert_eq!(unique_path(&dir, "a.pdf"), dir.join("a (1).pdf"));
+        std::fs::write(dir.join("a (1).pdf"), b"x").unwrap();
+        assert_eq!(unique_path(&dir, "a.pdf"), dir.join("a (2).pdf"));
+        std::fs::write(dir.join("datos"), b"x").unwrap();
+        assert_eq!(unique_path(&dir, "datos"), dir.join("datos (1)"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
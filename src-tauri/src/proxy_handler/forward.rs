@@ -0,0 +1,303 @@
+use tauri::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use tauri::http::{response, Request};
+use url::Url;
+
+/// Cabeceras de la petición que se reenvían al backend si la configuración no dice otra cosa.
+/// Un `*` final indica prefijo (`x-*` = todas las cabeceras propias).
+pub const DEFAULT_FORWARD_HEADERS: &[&str] = &[
+    "accept",
+    "accept-language",
+    "authorization",
+    "cache-control",
+    "content-type",
+    "cookie",
+    "if-match",
+    "if-modified-since",
+    "if-none-match",
+    "if-range",
+    "if-unmodified-since",
+    "pragma",
+    "range",
+    "x-*",
+];
+
+/// Cabeceras del navegador que se reenvían a los sitios externos. Nunca las cookies
+/// (las pone el jar del proxy) ni credenciales o cabeceras del contenedor.
+const EXTERNAL_SAFE_HEADERS: &[&str] = &[
+    "accept",
+    "accept-language",
+    "cache-control",
+    "content-type",
+    "if-match",
+    "if-modified-since",
+    "if-none-match",
+    "if-range",
+    "if-unmodified-since",
+    "pragma",
+    "range",
+    "x-requested-with",
+];
+
+/// Métodos que el proxy externo reenvía (y anuncia en las respuestas CORS).
+pub const EXTERNAL_ALLOW_METHODS: &str = "GET, HEAD, POST, PUT, PATCH, DELETE, OPTIONS";
+
+/// Cabeceras propias de cada salto (RFC 9110 §7.6.1): nunca se reenvían en ningún sentido.
+const HOP_BY_HOP: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Cabeceras que sólo pone el contenedor: las que traiga la petición se descartan
+/// para que una página no pueda hacerse pasar por otra app u otro cliente.
+const CONTAINER_HEADERS: &[&str] = &[
+    "x-sandra-app",
+    "x-sandra-client",
+    "x-sandra-queue-id",
+    "x-forwarded-for",
+    "x-forwarded-host",
+    "x-forwarded-proto",
+];
+
+/// Cabeceras de respuesta que impedirían cargar la app en el iframe; se sustituyen por las nuestras.
+const IFRAME_BLOCKING: &[&str] = &[
+    "x-frame-options",
+    "content-security-policy",
+    "access-control-allow-origin",
+];
+
+pub fn default_forward_headers() -> Vec<String> {
+    DEFAULT_FORWARD_HEADERS
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn is_hop_by_hop(name: &str) -> bool {
+    HOP_BY_HOP.contains(&name)
+}
+
+fn is_allowed(name: &str, allowlist: &[String]) -> bool {
+    allowlist.iter().any(|entry| {
+        let entry = entry.to_ascii_lowercase();
+        match entry.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == entry,
+        }
+    })
+}
+
+/// Datos de la petición que no vienen del webview sino del contenedor.
+pub struct ForwardContext<'a> {
+    pub app_id: Option<&'a str>,
+    pub client_id: Option<String>,
+    /// Token guardado de la app (`desktop_apps.token`), usado si la app no envía credenciales.
+    pub app_token: Option<String>,
+    pub client_ip: Option<String>,
+}
+
+/// Construye las cabeceras hacia el backend: las permitidas de la petición original,
+/// `X-Forwarded-*`, la identidad del cliente y, si falta, el bearer de la app.
+pub fn request_headers(
+    request: &Request<Vec<u8>>,
+    allowlist: &[String],
+    context: &ForwardContext,
+) -> HeaderMap {
+    let mut headers = app_headers(request, allowlist);
+    add_identity(&mut headers, context);
+    headers
+}
+
+/// Las cabeceras propias de la petición (permitidas y `X-Forwarded-Host/Proto`), sin
+/// la identidad ni las credenciales que añade el contenedor. Es lo que guarda la cola
+/// de escrituras; `add_identity` se vuelve a aplicar al reenviarlas.
+pub fn app_headers(request: &Request<Vec<u8>>, allowlist: &[String]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+
+    for (name, value) in request.headers() {
+        let name_str = name.as_str();
+        if !is_hop_by_hop(name_str)
+            && !CONTAINER_HEADERS.contains(&name_str)
+            && is_allowed(name_str, allowlist)
+        {
+            headers.append(name.clone(), value.clone());
+        }
+    }
+
+    let forwarded_host = request
+        .uri()
+        .host()
+        .or_else(|| {
+            request
+                .headers()
+                .get(header::HOST)
+                .and_then(|v| v.to_str().ok())
+        })
+        .unwrap_or("localhost");
+    insert(&mut headers, "x-forwarded-host", forwarded_host);
+    insert(&mut headers, "x-forwarded-proto", "sandra-app");
+    headers
+}
+
+/// `X-Forwarded-For`, la identidad del cliente y, si falta, el bearer de la app.
+/// También la usa el puente WebSocket, que no parte de una petición del webview.
+pub fn add_identity(headers: &mut HeaderMap, context: &ForwardContext) {
+    if let Some(ip) = &context.client_ip {
+        insert(headers, "x-forwarded-for", ip);
+    }
+    if let Some(client_id) = &context.client_id {
+        insert(headers, "x-sandra-client", client_id);
+    }
+    if let Some(app_id) = context.app_id {
+        insert(headers, "x-sandra-app", app_id);
+    }
+
+    if !headers.contains_key(header::AUTHORIZATION) {
+        if let Some(token) = context.app_token.as_deref().filter(|t| !t.is_empty()) {
+            insert(
+                headers,
+                header::AUTHORIZATION.as_str(),
+                &format!("Bearer {}", token),
+            );
+        }
+    }
+}
+
+/// Cabeceras hacia un sitio externo: las seguras de la petición original, con
+/// `Origin` y `Referer` traducidos al sitio real (los formularios con protección
+/// CSRF los comprueban) en lugar del origen `sandra-app`.
+pub fn external_request_headers(request: &Request<Vec<u8>>, target: &Url) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+
+    for (name, value) in request.headers() {
+        if EXTERNAL_SAFE_HEADERS.contains(&name.as_str()) {
+            headers.append(name.clone(), value.clone());
+        }
+    }
+
+    if request.headers().contains_key(header::ORIGIN) {
+        insert(
+            &mut headers,
+            header::ORIGIN.as_str(),
+            &target.origin().ascii_serialization(),
+        );
+    }
+    if let Some(referer) = request
+        .headers()
+        .get(header::REFERER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(proxied_target)
+    {
+        insert(&mut headers, header::REFERER.as_str(), &referer);
+    }
+
+    headers
+}
+
+/// URL real de una URL del proxy externo (`.../external-proxy?target=<url>`).
+pub fn proxied_target(proxy_url: &str) -> Option<String> {
+    let start = proxy_url.find("target=")? + "target=".len();
+    let encoded = &proxy_url[start..];
+    let encoded = &encoded[..encoded.find('&').unwrap_or(encoded.len())];
+    urlencoding::decode(encoded).ok().map(|s| s.into_owned())
+}
+
+/// Token de sesión de una URL del proxy externo (`...?session=<token>&target=...`).
+pub fn proxied_session(proxy_url: &str) -> Option<String> {
+    let (_, query) = proxy_url.split_once('?')?;
+    url::form_urlencoded::parse(query.as_bytes())
+        .find(|(name, _)| name == "session")
+        .map(|(_, value)| value.into_owned())
+}
+
+fn insert(headers: &mut HeaderMap, name: &str, value: &str) {
+    if let (Ok(name), Ok(value)) = (
+        HeaderName::from_bytes(name.as_bytes()),
+        HeaderValue::from_str(value),
+    ) {
+        headers.insert(name, value);
+    }
+}
+
+/// Copia las cabeceras de la respuesta remota conservando las de varios valores
+/// (`Set-Cookie`), salvo las de salto y las que bloquearían el iframe.
+pub fn copy_response_headers(
+    mut builder: response::Builder,
+    headers: &HeaderMap,
+) -> response::Builder {
+    for (name, value) in headers {
+        let name_str = name.as_str();
+        if !is_hop_by_hop(name_str) && !IFRAME_BLOCKING.contains(&name_str) {
+            builder = builder.header(name, value);
+        }
+    }
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(app_id: Option<&str>) -> ForwardContext<'_> {
+        ForwardContext {
+            app_id,
+            client_id: Some("client-1".into()),
+            app_token: Some("secreto".into()),
+            client_ip: None,
+        }
+    }
+
+    #[test]
+    fn spoofed_container_headers_are_dropped() {
+        let request = Request::builder()
+            .uri("sandra-app://localhost/v1/items")
+            .header("x-sandra-app", "otra-app")
+            .header("x-sandra-client", "otro-cliente")
+            .header("x-forwarded-for", "10.0.0.1")
+            .header("X-Sandra-Queue-Id", "escritura-ajena")
+            .header("x-trace-id", "abc")
+            .body(Vec::new())
+            .unwrap();
+
+        let headers = request_headers(&request, &default_forward_headers(), &context(None));
+        assert!(headers.get("x-sandra-app").is_none());
+        assert!(headers.get("x-forwarded-for").is_none());
+        assert!(headers.get("x-sandra-queue-id").is_none());
+        assert_eq!(headers["x-sandra-client"], "client-1");
+        assert_eq!(headers["x-trace-id"], "abc");
+        assert_eq!(headers["x-forwarded-proto"], "sandra-app");
+    }
+
+    #[test]
+    fn app_token_only_fills_a_missing_authorization() {
+        let anonymous = Request::builder()
+            .uri("sandra-app://localhost/v1/items")
+            .body(Vec::new())
+            .unwrap();
+        let headers = request_headers(
+            &anonymous,
+            &default_forward_headers(),
+            &context(Some("gdoc")),
+        );
+        assert_eq!(headers[header::AUTHORIZATION], "Bearer secreto");
+        assert_eq!(headers["x-sandra-app"], "gdoc");
+
+        let authenticated = Request::builder()
+            .uri("sandra-app://localhost/v1/items")
+            .header(header::AUTHORIZATION, "Bearer propio")
+            .body(Vec::new())
+            .unwrap();
+        let headers = request_headers(
+            &authenticated,
+            &default_forward_headers(),
+            &context(Some("gdoc")),
+        );
+        assert_eq!(headers[header::AUTHORIZATION], "Bearer propio");
+    }
+}
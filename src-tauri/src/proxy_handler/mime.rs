@@ -0,0 +1,150 @@
+use std::path::Path;
+
+/// Tipo por defecto cuando ni la extensión ni el contenido lo identifican.
+pub const FALLBACK: &str = "application/octet-stream";
+
+/// Tipo MIME según la extensión (sin distinguir mayúsculas). Los tipos de texto
+/// llevan `charset=utf-8`, que es lo que generan los builds de Angular.
+pub fn from_extension(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    let mime = match extension.as_str() {
+        // Documentos y código
+        "html" | "htm" => "text/html; charset=utf-8",
+        "js" | "mjs" | "cjs" => "text/javascript; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "json" | "map" => "application/json; charset=utf-8",
+        "webmanifest" => "application/manifest+json; charset=utf-8",
+        "xml" => "application/xml; charset=utf-8",
+        "txt" | "log" => "text/plain; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        // Imágenes
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "bmp" => "image/bmp",
+        // Fuentes
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "eot" => "application/vnd.ms-fontobject",
+        // Audio y vídeo
+        "mp4" | "m4v" => "video/mp4",
+        "webm" => "video/webm",
+        "ogv" => "video/ogg",
+        "mov" => "video/quicktime",
+        "mp3" => "audio/mpeg",
+        "m4a" => "audio/mp4",
+        "wav" => "audio/wav",
+        "ogg" | "oga" => "audio/ogg",
+        "flac" => "audio/flac",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+/// Identifica el tipo por las primeras bytes del fichero (firmas conocidas).
+/// Sólo se usa cuando la extensión no es reconocida.
+pub fn sniff(head: &[u8]) -> Option<&'static str> {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"\0asm", "application/wasm"),
+        (b"wOFF", "font/woff"),
+        (b"wOF2", "font/woff2"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"ID3", "audio/mpeg"),
+        (b"OggS", "audio/ogg"),
+        (b"fLaC", "audio/flac"),
+        (b"\x1a\x45\xdf\xa3", "video/webm"),
+    ];
+
+    if let Some((_, mime)) = SIGNATURES.iter().find(|(sig, _)| head.starts_with(sig)) {
+        return Some(mime);
+    }
+
+    // Contenedores RIFF (WEBP/WAV) e ISO BMFF (MP4) llevan la marca desplazada
+    if head.len() >= 12 && head.starts_with(b"RIFF") {
+        match &head[8..12] {
+            b"WEBP" => return Some("image/webp"),
+            b"WAVE" => return Some("audio/wav"),
+            _ => {}
+        }
+    }
+    if head.len() >= 8 && &head[4..8] == b"ftyp" {
+        return Some("video/mp4");
+    }
+
+    // Texto UTF-8 sin caracteres de control: se sirve como texto plano
+    let text = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        // La muestra puede cortar un carácter multibyte al final
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&head[..e.valid_up_to()]).ok()?,
+        Err(_) => return None,
+    };
+    let trimmed = text.trim_start();
+    if trimmed.starts_with("<!DOCTYPE html") || trimmed.starts_with("<html") {
+        return Some("text/html; charset=utf-8");
+    }
+    if text
+        .chars()
+        .all(|c| !c.is_control() || c.is_ascii_whitespace())
+    {
+        return Some("text/plain; charset=utf-8");
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extension_lookup_ignores_case() {
+        assert_eq!(
+            from_extension(Path::new("app/MAIN.JS")),
+            Some("text/javascript; charset=utf-8")
+        );
+        assert_eq!(from_extension(Path::new("font.woff2")), Some("font/woff2"));
+        assert_eq!(from_extension(Path::new("README")), None);
+        assert_eq!(from_extension(Path::new("data.unknown")), None);
+    }
+
+    #[test]
+    fn sniffs_binary_signatures() {
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n\0\0"), Some("image/png"));
+        assert_eq!(sniff(b"%PDF-1.7\n"), Some("application/pdf"));
+        assert_eq!(sniff(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(sniff(b"RIFF\0\0\0\0WAVEfmt "), Some("audio/wav"));
+        assert_eq!(sniff(b"\0\0\0\x20ftypisom"), Some("video/mp4"));
+    }
+
+    #[test]
+    fn sniffs_text() {
+        assert_eq!(
+            sniff(b"  <!DOCTYPE html><html>"),
+            Some("text/html; charset=utf-8")
+        );
+        assert_eq!(sniff(b"hola\nmundo\t!"), Some("text/plain; charset=utf-8"));
+        // Un carácter multibyte cortado al final de la muestra sigue siendo texto
+        assert_eq!(
+            sniff(&"año".as_bytes()[..2]),
+            Some("text/plain; charset=utf-8")
+        );
+        assert_eq!(sniff(b"\0\x01\x02binario"), None);
+        assert_eq!(sniff(b"\xff\xfe\xfd"), None);
+    }
+}
@@ -0,0 +1,687 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tauri::http::{Request, Response};
+use tauri::{AppHandle, Manager};
+
+/// Cabecera que indica si la respuesta salió de un fixture (`hit`), no encontró
+/// ninguno (`miss`) o es un error inyectado (`error`).
+const MOCK_HEADER: &str = "X-Sandra-Mock";
+
+/// Modo simulado de una conexión: el tráfico `/v1/` se responde en local sin servidor.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct MockConfig {
+    pub enabled: bool,
+    /// Carpeta de fixtures `*.json` (relativa a la carpeta de datos de la app si no es absoluta).
+    pub fixtures_dir: Option<String>,
+    /// Sesión grabada en HAR (p. ej. la exportada desde el inspector).
+    pub har_file: Option<String>,
+    /// Retardo añadido a cada respuesta, más un aleatorio de hasta `jitter_ms`.
+    pub latency_ms: u64,
+    pub jitter_ms: u64,
+    /// Probabilidad (0-1) de responder con `error_status` en lugar del fixture.
+    pub error_rate: f64,
+    /// Estado de los errores inyectados (503 por defecto).
+    pub error_status: Option<u16>,
+}
+
+impl MockConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.error_rate) {
+            return Err("mock.error_rate debe estar entre 0 y 1".into());
+        }
+        if let Some(status) = self.error_status {
+            if !(100..=599).contains(&status) {
+                return Err(format!("mock.error_status inválido: {}", status));
+            }
+        }
+        if self.enabled && self.fixtures_dir.is_none() && self.har_file.is_none() {
+            return Err("El modo simulado necesita fixtures_dir o har_file".into());
+        }
+        Ok(())
+    }
+}
+
+/// Fichero de fixture:
+///
+/// ```json
+/// { "request":  { "method": "GET", "path": "/v1/users", "query": "page=1", "body": {...} },
+///   "response": { "status": 200, "headers": { "Content-Type": "application/json" }, "body": {...} } }
+/// ```
+///
+/// `path` admite un `*` final como prefijo. `query` y `body` son opcionales: si
+/// faltan, encajan con cualquiera. Un fichero puede contener una lista de fixtures.
+#[derive(Deserialize, Debug)]
+struct FixtureFile {
+    request: FixtureRequest,
+    #[serde(default)]
+    response: FixtureResponse,
+}
+
+#[derive(Deserialize, Debug)]
+struct FixtureRequest {
+    #[serde(default = "default_method")]
+    method: String,
+    path: String,
+    query: Option<String>,
+    body: Option<Value>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+struct FixtureResponse {
+    status: u16,
+    headers: serde_json::Map<String, Value>,
+    body: Value,
+}
+
+impl Default for FixtureResponse {
+    fn default() -> Self {
+        Self {
+            status: 200,
+            headers: serde_json::Map::new(),
+            body: Value::Null,
+        }
+    }
+}
+
+fn default_method() -> String {
+    "GET".into()
+}
+
+/// Fixture ya normalizado, venga de un fichero JSON o de una entrada HAR.
+struct Fixture {
+    method: String,
+    path: String,
+    query: Option<String>,
+    body: Option<String>,
+    status: u16,
+    headers: Vec<(String, String)>,
+    response_body: Vec<u8>,
+    source: String,
+}
+
+impl Fixture {
+    /// Puntuación del encaje (más alta = más específico) o `None` si no encaja.
+    fn score(&self, method: &str, path: &str, query: &str, body: &[u8]) -> Option<u32> {
+        if !self.method.eq_ignore_ascii_case(method) {
+            return None;
+        }
+        // Un path exacto pesa más que cualquier comodín aunque este encaje query y cuerpo
+        let mut score = match self.path.strip_suffix('*') {
+            Some(prefix) if path.starts_with(prefix) => 0,
+            None if self.path == path => 4,
+            _ => return None,
+        };
+        if let Some(expected) = &self.query {
+            if normalize_query(expected) != normalize_query(query) {
+                return None;
+            }
+            score += 1;
+        }
+        if let Some(expected) = &self.body {
+            if !same_body(expected.as_bytes(), body) {
+                return None;
+            }
+            score += 1;
+        }
+        Some(score)
+    }
+}
+
+fn normalize_query(query: &str) -> Vec<(String, String)> {
+    let mut pairs: Vec<(String, String)> = url::form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect();
+    pairs.sort();
+    pairs
+}
+
+/// Cuerpos iguales: como JSON si ambos lo son (sin importar orden ni espacios),
+/// si no, byte a byte.
+fn same_body(expected: &[u8], actual: &[u8]) -> bool {
+    match (
+        serde_json::from_slice::<Value>(expected),
+        serde_json::from_slice::<Value>(actual),
+    ) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => expected == actual,
+    }
+}
+
+fn resolve_path(app_handle: &AppHandle, path: &str) -> PathBuf {
+    let path = PathBuf::from(path);
+    if path.is_absolute() {
+        return path;
+    }
+    match app_handle.path().app_data_dir() {
+        Ok(dir) => dir.join(path),
+        Err(_) => path,
+    }
+}
+
+fn body_bytes(body: Value) -> (Vec<u8>, bool) {
+    match body {
+        Value::Null => (Vec::new(), false),
+        Value::String(text) => (text.into_bytes(), false),
+        json => (json.to_string().into_bytes(), true),
+    }
+}
+
+/// Ficheros `*.json` de la carpeta de fixtures (recursivo), en orden alfabético.
+async fn fixture_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) => {
+                println!("⚠️ [Mock] No se pudo leer {:?}: {}", dir, e);
+                continue;
+            }
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().and_then(|e| e.to_str()) == Some("json") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+async fn load_fixture_file(path: &Path, fixtures: &mut Vec<Fixture>) {
+    let parsed = tokio::fs::read(path)
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| serde_json::from_slice::<Value>(&bytes).map_err(|e| e.to_string()));
+    let files: Vec<FixtureFile> = match parsed.and_then(|value| {
+        let list = match value {
+            Value::Array(list) => list,
+            single => vec![single],
+        };
+        list.into_iter()
+            .map(|v| serde_json::from_value(v).map_err(|e| e.to_string()))
+            .collect()
+    }) {
+        Ok(files) => files,
+        Err(e) => {
+            println!("⚠️ [Mock] Fixture inválido {:?}: {}", path, e);
+            return;
+        }
+    };
+
+    for file in files {
+        let (response_body, is_json) = body_bytes(file.response.body);
+        let mut headers: Vec<(String, String)> = file
+            .response
+            .headers
+            .into_iter()
+            .map(|(name, value)| match value {
+                Value::String(s) => (name, s),
+                other => (name, other.to_string()),
+            })
+            .collect();
+        if is_json
+            && !headers
+                .iter()
+                .any(|(n, _)| n.eq_ignore_ascii_case("content-type"))
+        {
+            headers.push(("Content-Type".into(), "application/json".into()));
+        }
+        fixtures.push(Fixture {
+            method: file.request.method,
+            path: file.request.path,
+            query: file.request.query,
+            body: file.request.body.map(|b| match b {
+                Value::String(s) => s,
+                other => other.to_string(),
+            }),
+            status: file.response.status,
+            headers,
+            response_body,
+            source: path.display().to_string(),
+        });
+    }
+}
+
+/// Entradas de una sesión HAR 1.2 (`log.entries[]`).
+async fn load_har(file: &Path, fixtures: &mut Vec<Fixture>) {
+    use base64::{engine::general_purpose, Engine as _};
+
+    let har: Value = match tokio::fs::read(file)
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| serde_json::from_slice(&bytes).map_err(|e| e.to_string()))
+    {
+        Ok(har) => har,
+        Err(e) => {
+            println!("⚠️ [Mock] HAR inválido {:?}: {}", file, e);
+            return;
+        }
+    };
+
+    for (i, entry) in har["log"]["entries"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .enumerate()
+    {
+        // El tráfico del proxy externo no es de la API `/v1/`
+        if entry["_kind"].as_str() == Some("external") {
+            continue;
+        }
+        let request = &entry["request"];
+        let response = &entry["response"];
+        // Las capturas propias guardan el path local; la URL del servidor lleva el
+        // prefijo de `api_base_url`. HAR de otras herramientas: el path de la URL.
+        let url = match entry["_localPath"]
+            .as_str()
+            .map(|local| {
+                url::Url::parse("sandra-app://localhost").and_then(|base| base.join(local))
+            })
+            .or_else(|| request["url"].as_str().map(url::Url::parse))
+        {
+            Some(Ok(url)) => url,
+            _ => continue,
+        };
+        let status = response["status"].as_u64().unwrap_or(0) as u16;
+        // Entradas sin respuesta (errores de red) no sirven como fixture
+        if status == 0 {
+            continue;
+        }
+
+        let content = &response["content"];
+        let text = content["text"].as_str().unwrap_or_default();
+        let response_body = if content["encoding"].as_str() == Some("base64") {
+            general_purpose::STANDARD.decode(text).unwrap_or_default()
+        } else {
+            text.as_bytes().to_vec()
+        };
+        let headers = response["headers"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|h| {
+                Some((
+                    h["name"].as_str()?.to_string(),
+                    h["value"].as_str()?.to_string(),
+                ))
+            })
+            // El cuerpo se sirve ya descomprimido y con su propio tamaño
+            .filter(|(name, _)| {
+                !["content-encoding", "content-length", "transfer-encoding"]
+                    .contains(&name.to_ascii_lowercase().as_str())
+            })
+            .collect();
+
+        fixtures.push(Fixture {
+            method: request["method"].as_str().unwrap_or("GET").to_string(),
+            path: url.path().to_string(),
+            query: Some(url.query().unwrap_or_default().to_string()),
+            body: request["postData"]["text"].as_str().map(str::to_string),
+            status,
+            headers,
+            response_body,
+            source: format!("{}#{}", file.display(), i),
+        });
+    }
+}
+
+/// Fixtures cargados de cada perfil. Se reutilizan mientras no cambien la carpeta o
+/// el HAR configurados ni la fecha de modificación de sus ficheros.
+#[derive(Default)]
+pub struct MockFixtures {
+    loaded: Mutex<HashMap<i64, LoadedFixtures>>,
+}
+
+struct LoadedFixtures {
+    sources: (Option<PathBuf>, Option<PathBuf>),
+    modified: Vec<(PathBuf, Option<SystemTime>)>,
+    fixtures: Arc<Vec<Fixture>>,
+}
+
+async fn modification_times(files: &[PathBuf]) -> Vec<(PathBuf, Option<SystemTime>)> {
+    let mut modified = Vec::with_capacity(files.len());
+    for file in files {
+        let time = tokio::fs::metadata(file)
+            .await
+            .and_then(|m| m.modified())
+            .ok();
+        modified.push((file.clone(), time));
+    }
+    modified
+}
+
+/// Fixtures del perfil `connection_id`; sólo se leen y parsean de nuevo si algo cambió.
+async fn fixtures_for(
+    app_handle: &AppHandle,
+    connection_id: i64,
+    config: &MockConfig,
+) -> Arc<Vec<Fixture>> {
+    let sources = (
+        config
+            .fixtures_dir
+            .as_deref()
+            .map(|dir| resolve_path(app_handle, dir)),
+        config
+            .har_file
+            .as_deref()
+            .map(|har| resolve_path(app_handle, har)),
+    );
+    let json_files = match &sources.0 {
+        Some(dir) => fixture_files(dir).await,
+        None => Vec::new(),
+    };
+    let mut files = json_files.clone();
+    files.extend(sources.1.clone());
+    let modified = modification_times(&files).await;
+
+    let cache = app_handle.state::<MockFixtures>();
+    if let Some(loaded) = cache.loaded.lock().unwrap().get(&connection_id) {
+        if loaded.sources == sources && loaded.modified == modified {
+            return loaded.fixtures.clone();
+        }
+    }
+
+    let mut fixtures = Vec::new();
+    for file in &json_files {
+        load_fixture_file(file, &mut fixtures).await;
+    }
+    if let Some(har) = &sources.1 {
+        load_har(har, &mut fixtures).await;
+    }
+    println!(
+        "🎭 [Mock] {} fixtures cargados para la conexión {}",
+        fixtures.len(),
+        connection_id
+    );
+
+    let fixtures = Arc::new(fixtures);
+    cache.loaded.lock().unwrap().insert(
+        connection_id,
+        LoadedFixtures {
+            sources,
+            modified,
+            fixtures: fixtures.clone(),
+        },
+    );
+    fixtures
+}
+
+/// Responde una petición `/v1/` con el fixture que mejor encaje (método, path,
+/// query y cuerpo), aplicando la latencia y los errores configurados.
+pub async fn respond(
+    app_handle: &AppHandle,
+    connection_id: i64,
+    config: &MockConfig,
+    request: &Request<Vec<u8>>,
+) -> Response<Vec<u8>> {
+    let delay = config.latency_ms
+        + if config.jitter_ms > 0 {
+            rand::thread_rng().gen_range(0..=config.jitter_ms)
+        } else {
+            0
+        };
+    if delay > 0 {
+        tokio::time::sleep(Duration::from_millis(delay)).await;
+    }
+
+    if config.error_rate > 0.0 && rand::thread_rng().gen_bool(config.error_rate) {
+        let status = config.error_status.unwrap_or(503);
+        return json_response(
+            status,
+            "error",
+            serde_json::json!({ "error": "mock: error inyectado" }),
+        );
+    }
+
+    let fixtures = fixtures_for(app_handle, connection_id, config).await;
+
+    let method = request.method().as_str();
+    let path = request.uri().path();
+    let query = request.uri().query().unwrap_or_default();
+    let body = request.body();
+
+    // El más específico gana; a igualdad, el primero cargado
+    let best = fixtures
+        .iter()
+        .enumerate()
+        .filter_map(|(i, f)| f.score(method, path, query, body).map(|score| (score, i)))
+        .max_by_key(|(score, i)| (*score, std::cmp::Reverse(*i)))
+        .map(|(_, i)| &fixtures[i]);
+
+    match best {
+        Some(fixture) => {
+            println!(
+                "🎭 [Mock] {} {} -> {} ({})",
+                method, path, fixture.status, fixture.source
+            );
+            let mut builder = Response::builder()
+                .status(fixture.status)
+                .header("Access-Control-Allow-Origin", "*")
+                .header(MOCK_HEADER, "hit");
+            for (name, value) in &fixture.headers {
+                builder = builder.header(name, value);
+            }
+            builder
+                .body(fixture.response_body.clone())
+                .unwrap_or_else(|e| {
+                    json_response(500, "error", serde_json::json!({ "error": e.to_string() }))
+                })
+        }
+        None => {
+            println!("🎭 [Mock] Sin fixture para {} {}", method, path);
+            json_response(
+                404,
+                "miss",
+                serde_json::json!({ "error": format!("mock: no hay fixture para {} {}", method, path) }),
+            )
+        }
+    }
+}
+
+fn json_response(status: u16, mock: &str, body: Value) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .header(MOCK_HEADER, mock)
+        .body(body.to_string().into_bytes())
+        .unwrap()
+}
+
+/// Perfiles con el modo simulado activo, en orden de id. El filtro va en SQL para
+/// no deserializar la configuración de cada conexión.
+pub fn mock_connection_ids(conn: &rusqlite::Connection) -> Vec<i64> {
+    conn.prepare(
+        "SELECT id FROM connections
+         WHERE CASE WHEN json_valid(mock_config) THEN json_extract(mock_config, '$.enabled') END = 1
+         ORDER BY id ASC",
+    )
+    .and_then(|mut stmt| {
+        stmt.query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<i64>, _>>()
+    })
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy_handler::capture::{to_har, CaptureEntry, CapturedBody};
+
+    fn fixture(method: &str, path: &str, query: Option<&str>, body: Option<&str>) -> Fixture {
+        Fixture {
+            method: method.into(),
+            path: path.into(),
+            query: query.map(str::to_string),
+            body: body.map(str::to_string),
+            status: 200,
+            headers: Vec::new(),
+            response_body: Vec::new(),
+            source: "test".into(),
+        }
+    }
+
+    #[test]
+    fn score_prefers_the_most_specific_fixture() {
+        let exact = fixture("GET", "/v1/users", None, None);
+        let prefix = fixture("GET", "/v1/*", None, None);
+        let with_query = fixture("get", "/v1/users", Some("b=2&a=1"), None);
+
+        assert_eq!(prefix.score("GET", "/v1/users", "", b""), Some(0));
+        assert_eq!(exact.score("GET", "/v1/users", "a=1", b""), Some(4));
+        // El orden de los parámetros no importa; el método no distingue mayúsculas
+        assert_eq!(
+            with_query.score("GET", "/v1/users", "a=1&b=2", b""),
+            Some(5)
+        );
+
+        assert_eq!(exact.score("POST", "/v1/users", "", b""), None);
+        assert_eq!(exact.score("GET", "/v1/users/1", "", b""), None);
+        assert_eq!(prefix.score("GET", "/v2/users", "", b""), None);
+        assert_eq!(with_query.score("GET", "/v1/users", "a=1", b""), None);
+    }
+
+    #[test]
+    fn exact_path_beats_a_wildcard_matching_query_and_body() {
+        let exact = fixture("POST", "/v1/users", None, None);
+        let wildcard = fixture("POST", "/v1/*", Some("a=1"), Some("{}"));
+        assert!(
+            exact.score("POST", "/v1/users", "a=1", b"{}")
+                > wildcard.score("POST", "/v1/users", "a=1", b"{}")
+        );
+    }
+
+    #[test]
+    fn score_compares_json_bodies_structurally() {
+        let post = fixture(
+            "POST",
+            "/v1/login",
+            None,
+            Some(r#"{"user":"ana","pass":"x"}"#),
+        );
+        assert_eq!(
+            post.score(
+                "POST",
+                "/v1/login",
+                "",
+                br#"{ "pass": "x", "user": "ana" }"#
+            ),
+            Some(5)
+        );
+        assert_eq!(
+            post.score("POST", "/v1/login", "", br#"{"user":"eva"}"#),
+            None
+        );
+
+        let raw = fixture("POST", "/v1/raw", None, Some("a=1"));
+        assert_eq!(raw.score("POST", "/v1/raw", "", b"a=1"), Some(5));
+        assert_eq!(raw.score("POST", "/v1/raw", "", b"a=2"), None);
+    }
+
+    #[test]
+    fn loads_fixture_files_and_exported_har() {
+        let dir = std::env::temp_dir().join(format!("sandra-mock-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("users")).unwrap();
+        std::fs::write(
+            dir.join("users").join("list.json"),
+            r#"[{ "request": { "path": "/v1/users" }, "response": { "body": [1, 2] } },
+                { "request": { "method": "DELETE", "path": "/v1/users/*" }, "response": { "status": 204 } }]"#,
+        )
+        .unwrap();
+        std::fs::write(dir.join("notes.txt"), "no es un fixture").unwrap();
+
+        let entry = CaptureEntry {
+            id: 1,
+            app_id: "crm".into(),
+            kind: "remote",
+            started_at: "2026-01-01T00:00:00+00:00".into(),
+            duration_ms: 5,
+            method: "GET".into(),
+            // Conexión con `api_base_url` = https://srv.local/api
+            url: "https://srv.local/api/v1/items?page=2".into(),
+            local_path: Some("/v1/items?page=2".into()),
+            status: 200,
+            request_size: 0,
+            response_size: 3,
+            request_headers: Vec::new(),
+            response_headers: vec![
+                ("content-type".into(), "image/png".into()),
+                ("content-length".into(), "3".into()),
+            ],
+            request_body: None,
+            response_body: Some(CapturedBody {
+                mime_type: Some("image/png".into()),
+                text: "AAEC".into(),
+                encoding: Some("base64"),
+            }),
+            error: None,
+        };
+        let external = CaptureEntry {
+            kind: "external",
+            url: "https://example.com/v1/items?page=2".into(),
+            local_path: None,
+            ..entry.clone()
+        };
+        let har = dir.join("session.har");
+        std::fs::write(&har, to_har(&[entry, external], "1.0.0").to_string()).unwrap();
+
+        let fixtures = tauri::async_runtime::block_on(async {
+            let mut fixtures = Vec::new();
+            let files = fixture_files(&dir).await;
+            assert_eq!(files.len(), 1);
+            for file in &files {
+                load_fixture_file(file, &mut fixtures).await;
+            }
+            load_har(&har, &mut fixtures).await;
+            fixtures
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(fixtures.len(), 3);
+        assert_eq!(fixtures[0].response_body, b"[1,2]");
+        assert!(fixtures[0]
+            .headers
+            .contains(&("Content-Type".into(), "application/json".into())));
+        assert_eq!(fixtures[1].status, 204);
+        assert_eq!(fixtures[1].score("DELETE", "/v1/users/7", "", b""), Some(0));
+
+        let recorded = &fixtures[2];
+        assert_eq!(recorded.score("GET", "/v1/items", "page=2", b""), Some(5));
+        assert_eq!(recorded.score("GET", "/api/v1/items", "page=2", b""), None);
+        assert_eq!(recorded.response_body, vec![0, 1, 2]);
+        assert_eq!(
+            recorded.headers,
+            vec![("content-type".into(), "image/png".into())]
+        );
+    }
+
+    #[test]
+    fn mock_profiles_are_detected_in_sql() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::storage::init_tables(&conn).unwrap();
+        for (name, mock) in [
+            ("on", Some(r#"{"enabled":true,"fixtures_dir":"f"}"#)),
+            ("off", Some(r#"{"enabled":false}"#)),
+            ("broken", Some("{no es json")),
+            ("none", None),
+        ] {
+            conn.execute(
+                "INSERT INTO connections (name, ip_address, port, mock_config) VALUES (?1, '127.0.0.1', 80, ?2)",
+                rusqlite::params![name, mock],
+            )
+            .unwrap();
+        }
+        assert_eq!(mock_connection_ids(&conn), vec![1]);
+    }
+}
@@ -0,0 +1,1066 @@
+mod assets;
+mod cache;
+mod capture;
+mod client;
+mod cookies;
+mod download;
+mod forward;
+mod mime;
+mod mock;
+mod policy;
+mod rewrite;
+mod routing;
+mod sessions;
+mod websocket;
+mod write_queue;
+
+pub use cache::{purge as purge_cache, stats as cache_stats, CacheStats};
+pub use capture::{to_har, CaptureEntry, CaptureOptions, TrafficCapture};
+pub use client::ProxyClients;
+pub use cookies::{clear as clear_cookies, list as list_cookies, StoredCookie};
+pub use download::{download as download_to_disk, Download};
+pub use forward::default_forward_headers;
+pub use mock::{MockConfig, MockFixtures};
+pub use policy::{recent_denials, DeniedAttempt, ExternalPolicy};
+pub use routing::{validate_connection, RouteRule};
+pub use sessions::{ExternalSessionInfo, ExternalSessions};
+pub use websocket::{open as open_socket, SocketBridge, SocketEvent};
+pub use write_queue::{
+    discard as discard_queued_write, list as list_queued_writes, QueuedWrite, WriteQueue,
+};
+
+use crate::commands::connections::{Connection, CONNECTION_COLUMNS};
+use crate::connection_manager::ConnectionManager;
+use crate::runtime_config::ProxyConfig;
+use crate::storage::DbState;
+use forward::ForwardContext;
+use rusqlite::OptionalExtension;
+use sessions::SessionRef;
+
+use tauri::http::header::{
+    ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, CONTENT_TYPE, ORIGIN,
+};
+use tauri::http::{Method, Request, Response};
+use tauri::{AppHandle, Manager};
+use url::Url;
+
+/// Error de las funciones de proxy; `Send` para poder cruzar los `await` del protocolo asíncrono.
+type ProxyError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Punto de entrada del protocolo `sandra-app://`. Se ejecuta en el runtime async
+/// (ver `register_asynchronous_uri_scheme_protocol` en `lib.rs`), así que una
+/// descarga lenta ya no bloquea el hilo del webview.
+/// `webview` es la etiqueta del webview que origina la petición: acota la sesión externa.
+pub async fn handle_request(
+    app_handle: &AppHandle,
+    webview: &str,
+    request: &Request<Vec<u8>>,
+) -> Response<Vec<u8>> {
+    let uri = request.uri();
+    let path = uri.path();
+    // Se lee en cada petición para que los cambios remotos apliquen sin reiniciar
+    let proxy_config = crate::runtime_config::current(app_handle).proxy;
+
+    // DEBUG: Ver qué llega realmente
+    // println!(
+    //     "🔍 Req: {} | Referer: {:?}",
+    //     path,
+    //     request.headers().get("referer")
+    // );
+
+    // Tráfico del proxy externo (explícito o desde una página proxificada), siempre
+    // en nombre de la app que abrió la página: su política y su jar de cookies
+    let external = external_request(&app_handle.state::<ExternalSessions>(), request, webview);
+
+    // Preflight CORS en contexto externo (proxy explícito, Referer proxificado o sesión
+    // de la app): se contesta localmente
+    if is_preflight(request) {
+        let app_id = request_app_id(request, webview).unwrap_or_default();
+        if external.is_some()
+            || app_handle
+                .state::<ExternalSessions>()
+                .get(webview, &app_id)
+                .is_some()
+        {
+            return preflight_response(request);
+        }
+    }
+
+    // 0. Proxy para URLs externas (Bypass X-Frame-Options)
+    // Uso: sandra-app://localhost/external-proxy?target=https://google.com; las páginas
+    // reescritas añaden `session=<token>`. 1. Con Referer de una página proxificada,
+    // cualquier petición (imágenes, XHR, búsquedas locales como /search) pertenece a
+    // ese sitio externo y se redirige allá, IGNORANDO la conexión local de BD.
+    match external {
+        Some(Ok(external)) => {
+            match proxy_arbitrary_url(
+                app_handle,
+                &external.session,
+                request,
+                &external.url,
+                &proxy_config,
+            )
+            .await
+            {
+                Ok(resp) => return resp,
+                Err(e) => {
+                    // Respuesta demasiado grande: el fallo es del sitio remoto
+                    let status = if e.is::<client::BodyTooLarge>() {
+                        502
+                    } else {
+                        500
+                    };
+                    return create_error_response(
+                        status,
+                        format!("External Proxy Error: {}", e).as_str(),
+                    );
+                }
+            }
+        }
+        Some(Err(e)) => {
+            println!("🛑 [External Proxy] {}: {}", path, e);
+            return create_error_response(403, &format!("External Proxy: {}", e));
+        }
+        None => {}
+    }
+
+    // 2. API PROXY (Only /v1/)
+    // Todo lo que empiece por /v1/ es tráfico de Backend -> Proxy Remoto (si hay conexión)
+    // Cada app puede estar fijada a una conexión concreta (producción, staging...)
+    if path.starts_with("/v1/") {
+        let app_id = request_app_id(request, webview);
+        let route = match resolve_connection(app_handle, app_id.as_deref()) {
+            Ok(route) => route,
+            Err(e) => {
+                println!("❌ [Proxy] {}", e);
+                return create_error_response(503, &e);
+            }
+        };
+
+        // Modo simulado del perfil: fixtures locales en lugar del servidor
+        if let Some((connection_id, mock)) = route
+            .as_ref()
+            .and_then(|conn| Some((conn.id? as i64, conn.mock.clone()?)))
+            .filter(|(_, mock)| mock.enabled)
+        {
+            let pending = capture::start();
+            let result: Result<_, ProxyError> =
+                Ok(mock::respond(app_handle, connection_id, &mock, request).await);
+            if let Ok(url) = Url::parse(&uri.to_string()) {
+                capture::record(
+                    app_handle,
+                    pending,
+                    "mock",
+                    app_id.as_deref().unwrap_or_default(),
+                    &url,
+                    request,
+                    &result,
+                );
+            }
+            if let Ok(response) = result {
+                return response;
+            }
+        }
+
+        if let Some(active_conn) = route {
+            match proxy_to_remote(
+                app_handle,
+                active_conn,
+                request,
+                app_id.as_deref(),
+                &proxy_config,
+            )
+            .await
+            {
+                Ok(response) => return response,
+                Err(e) => {
+                    println!("❌ Error en Proxy Remoto: {}", e);
+                    return create_error_response(
+                        502,
+                        format!("Proxy Error (Remote Unreachable): {}", e).as_str(),
+                    );
+                }
+            }
+        }
+    }
+
+    // 3. TODO LO DEMÁS -> LOCAL (UI, Assets, Scripts)
+    // Cualquier cosa que no sea /v1/ se asume parte del Frontend Local.
+    serve_local_file(app_handle, webview, request, path).await
+}
+
+/// Identifica la app que origina la petición: la ventana de app (`app-<id>`) o, en
+/// el contenedor, el primer segmento del Referer (`sandra-app://localhost/<app_id>/...`).
+/// Nunca una cabecera de la petición: con el id se elige el token y la conexión de
+/// la app, y cualquier página podría suplantar a otra app.
+pub fn request_app_id(request: &Request<Vec<u8>>, webview: &str) -> Option<String> {
+    if let Some(app_id) = webview_app_id(webview) {
+        return Some(app_id);
+    }
+
+    let referer = request
+        .headers()
+        .get("referer")
+        .and_then(|v| v.to_str().ok())?;
+    let referer_url = Url::parse(referer).ok()?;
+    let first = referer_url.path_segments()?.next()?;
+
+    match first {
+        "" | "v1" | "external-proxy" => None,
+        app_id => Some(app_id.to_string()),
+    }
+}
+
+/// App de una ventana propia (`app-<id>`); `None` para el contenedor principal.
+pub fn webview_app_id(webview: &str) -> Option<String> {
+    webview
+        .strip_prefix("app-")
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+}
+
+const UNKNOWN_SESSION: &str = "sesión externa desconocida o caducada; vuelva a abrir la página";
+
+/// Petición del proxy externo ya resuelta: URL real y sesión (app) a la que pertenece.
+struct ExternalRequest {
+    url: String,
+    session: SessionRef,
+}
+
+/// Decide si la petición es tráfico del proxy externo y en nombre de qué app:
+/// - `/external-proxy?session=<token>&target=...` (URLs reescritas): la app del token.
+/// - `/external-proxy?target=...` sin token: navegación inicial desde una app; se
+///   deduce como en `/v1/` (ventana o Referer) y se abre su sesión.
+/// - Referer de una página proxificada: la app del token de ese Referer (rutas que el
+///   reescritor no ve, como las que construyen los scripts de la página).
+///
+/// `Some(Err)` si es tráfico externo sin una sesión válida: nunca se atribuye al
+/// contenedor (`""`), que tiene su propia política y su propio jar.
+fn external_request(
+    sessions: &ExternalSessions,
+    request: &Request<Vec<u8>>,
+    webview: &str,
+) -> Option<Result<ExternalRequest, String>> {
+    let uri = request.uri();
+    let referer = request
+        .headers()
+        .get("referer")
+        .and_then(|v| v.to_str().ok())
+        .filter(|referer| is_external_proxy_url(referer));
+
+    if uri.path() == "/external-proxy" {
+        let uri = uri.to_string();
+        let target = forward::proxied_target(&uri)?;
+        let app_id = match forward::proxied_session(&uri) {
+            Some(token) => sessions.by_token(webview, &token).map(|s| s.app_id),
+            None => match referer {
+                // Una página proxificada que construye la URL sin token sigue siendo de su app
+                Some(referer) => forward::proxied_session(referer)
+                    .and_then(|token| sessions.by_token(webview, &token))
+                    .map(|s| s.app_id),
+                None => Some(request_app_id(request, webview).unwrap_or_default()),
+            },
+        };
+        let Some(app_id) = app_id else {
+            return Some(Err(UNKNOWN_SESSION.into()));
+        };
+        // 🧠 SAVE CONTEXT: último sitio externo visitado por esta app en ESTE webview
+        let token = sessions.open(webview, &app_id, target.clone());
+        return Some(Ok(ExternalRequest {
+            url: target.clone(),
+            session: SessionRef {
+                token,
+                app_id,
+                target,
+            },
+        }));
+    }
+
+    let referer = referer?;
+    let Some(session) =
+        forward::proxied_session(referer).and_then(|token| sessions.by_token(webview, &token))
+    else {
+        return Some(Err(UNKNOWN_SESSION.into()));
+    };
+    // Unir la página externa + path (y query) de la petición
+    let base = forward::proxied_target(referer).and_then(|target| Url::parse(&target).ok())?;
+    let relative = uri
+        .path_and_query()
+        .map(|pq| pq.as_str())
+        .unwrap_or_else(|| uri.path());
+    let url = base.join(relative.trim_start_matches('/')).ok()?;
+    println!(
+        "🌍 [Auto-Proxy Context] Redirecting: {} -> {}",
+        uri.path(),
+        url
+    );
+    Some(Ok(ExternalRequest {
+        url: url.to_string(),
+        session,
+    }))
+}
+
+/// URL de una página servida por el proxy externo (`.../external-proxy?...`).
+fn is_external_proxy_url(url: &str) -> bool {
+    Url::parse(url).is_ok_and(|url| url.path() == "/external-proxy")
+}
+
+/// Elige la conexión para el tráfico `/v1/` de una app.
+/// - App fijada a una conexión: esa, y si no está conectada es un error (no se desvía
+///   el tráfico de producción a otro servidor en silencio), salvo que el perfil pueda
+///   responder sin servidor (modo simulado, caché o cola de escrituras offline).
+/// - App sin fijar: la primera conexión activa y, sin ninguna, el perfil simulado o
+///   con modo offline usado más recientemente.
+fn resolve_connection(
+    app_handle: &AppHandle,
+    app_id: Option<&str>,
+) -> Result<Option<Connection>, String> {
+    // El estado real lo decide el ConnectionManager; la BD sólo aporta el perfil
+    let manager = app_handle.state::<ConnectionManager>();
+    let state = app_handle.state::<DbState>();
+
+    let pinned: Option<i64> = match app_id {
+        Some(app_id) => {
+            let conn_guard = state.0.lock().map_err(|e| e.to_string())?;
+            conn_guard
+                .query_row(
+                    "SELECT connection_id FROM desktop_apps WHERE app_id = ?1",
+                    [app_id],
+                    |row| row.get(0),
+                )
+                .optional()
+                .unwrap_or(None)
+                .flatten()
+        }
+        None => None,
+    };
+
+    // Sólo se consulta cuando el servidor no está conectado
+    let standby_ids = || -> Result<Vec<i64>, String> {
+        let conn_guard = state.0.lock().map_err(|e| e.to_string())?;
+        Ok(standby_connection_ids(&conn_guard))
+    };
+
+    let target = match pinned {
+        Some(id) if manager.is_connected(id) || standby_ids()?.contains(&id) => Some(id),
+        Some(id) => {
+            return Err(format!(
+                "La conexión {} asignada a '{}' no está activa.",
+                id,
+                app_id.unwrap_or_default()
+            ))
+        }
+        None => match manager.connected_ids().into_iter().next() {
+            Some(id) => Some(id),
+            None => standby_ids()?.first().copied(),
+        },
+    };
+
+    let result = match target {
+        Some(id) => {
+            let conn_guard = state.0.lock().map_err(|e| e.to_string())?;
+            conn_guard
+                .query_row(
+                    &format!(
+                        "SELECT {} FROM connections WHERE id = ?1",
+                        CONNECTION_COLUMNS
+                    ),
+                    [id],
+                    Connection::from_row,
+                )
+                .optional()
+                .unwrap_or(None)
+        }
+        None => None,
+    };
+
+    if let Some(ref conn) = result {
+        println!(
+            "✅ [Proxy] Route {} -> {} ({}:{})",
+            app_id.unwrap_or("*"),
+            conn.name,
+            conn.ip_address,
+            conn.port
+        );
+    } else {
+        println!("🚫 [Proxy] No Active Connection found.");
+    }
+
+    Ok(result)
+}
+
+/// Perfiles que responden sin servidor: primero los simulados (por id) y después los
+/// de caché o cola de escrituras offline, del usado más recientemente al más antiguo.
+fn standby_connection_ids(conn: &rusqlite::Connection) -> Vec<i64> {
+    let mut ids = mock::mock_connection_ids(conn);
+    let offline: Vec<i64> = conn
+        .prepare(
+            "SELECT id FROM connections WHERE offline_cache = 1 OR offline_writes = 1
+             ORDER BY last_connected IS NULL, last_connected DESC, id ASC",
+        )
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| row.get(0))?
+                .collect::<Result<Vec<i64>, _>>()
+        })
+        .unwrap_or_default();
+    for id in offline {
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    ids
+}
+
+async fn serve_local_file(
+    app_handle: &AppHandle,
+    webview: &str,
+    request: &Request<Vec<u8>>,
+    path: &str,
+) -> Response<Vec<u8>> {
+    let app_dir = app_handle
+        .path()
+        .app_data_dir()
+        .expect("Error al obtener AppData");
+
+    let clean_path = path.trim_start_matches('/');
+    let proxy_config = crate::runtime_config::current(app_handle).proxy;
+
+    // CASO 1: Raíz de una APP (ej: "gdoc/")
+    // Si el path termina en slash, asumimos que es el índice de la App.
+    // La estructura esperada es apps/<app_id>/dist/index.html
+    let file_path = if path.ends_with('/') {
+        let app_id = clean_path.trim_end_matches('/'); // "gdoc"
+        app_dir
+            .join("apps")
+            .join(app_id)
+            .join("dist")
+            .join("index.html")
+    }
+    // CASO 2: Recurso específico (ej: "gdoc/styles.css" o "gdoc/assets/logo.png")
+    else {
+        let parts: Vec<&str> = clean_path.splitn(2, '/').collect();
+        // Si tenemos [app_id, asset_path]
+        if parts.len() == 2 {
+            let app_id = parts[0];
+            let asset_path = parts[1]; // puede ser "styles.css" o "assets/logo.png" o "index.html" si alguien lo pide explícito
+            app_dir
+                .join("apps")
+                .join(app_id)
+                .join("dist")
+                .join(asset_path)
+        } else {
+            // Fallback genérico si no encaja en estructura app/asset
+            app_dir.join("apps").join(clean_path)
+        }
+    };
+
+    // Debugging path resolution
+    // println!("📂 [Local] Resolving: {} -> {:?}", path, file_path);
+
+    if !file_path.exists() {
+        // println!("⚠️ [Local] File NOT found: {:?}", file_path);
+
+        // FALLBACK SPA: Si el archivo no existe, pero parece ser una ruta de navegación (sin extensión),
+        // intentamos servir el index.html de la aplicación correspondiente.
+        if std::path::Path::new(path).extension().is_none() {
+            let clean_path = path.trim_start_matches('/');
+            let parts: Vec<&str> = clean_path.splitn(2, '/').collect();
+            if !parts.is_empty() {
+                let app_id = parts[0];
+                // Intentamos buscar apps/<app_id>/dist/index.html
+                let index_path = app_dir
+                    .join("apps")
+                    .join(app_id)
+                    .join("dist")
+                    .join("index.html");
+
+                if index_path.exists() {
+                    // println!("🔄 [SPA Fallback] Serving index.html for route: {}", path);
+                    return assets::serve_file(request, &index_path).await;
+                }
+            }
+        }
+
+        // Sesión externa de esta misma app en este webview (ej: /search tras abrir
+        // Google): sólo cuando no hay fichero local, y nunca para el tráfico de API
+        let app_id = request_app_id(request, webview).unwrap_or_default();
+        let session = app_handle
+            .state::<ExternalSessions>()
+            .get(webview, &app_id)
+            .filter(|_| !path.starts_with("/v1/"));
+        {
+            if let Some(session) = &session {
+                if let Ok(base_url) = Url::parse(&session.target) {
+                    if let Ok(full_url) = base_url.join(path.trim_start_matches('/')) {
+                        let full_url_str = full_url.to_string();
+                        if let Ok(resp) = proxy_arbitrary_url(
+                            app_handle,
+                            session,
+                            request,
+                            &full_url_str,
+                            &proxy_config,
+                        )
+                        .await
+                        {
+                            return resp;
+                        }
+                    }
+                }
+            }
+        }
+
+        return create_error_response(
+            404,
+            format!("Local file not found: {:?}", file_path).as_str(),
+        );
+    }
+
+    assets::serve_file(request, &file_path).await
+}
+
+async fn proxy_to_remote(
+    app_handle: &AppHandle,
+    conn: Connection,
+    request: &Request<Vec<u8>>,
+    app_id: Option<&str>,
+    proxy_config: &ProxyConfig,
+) -> Result<Response<Vec<u8>>, ProxyError> {
+    // Construir URL remota (reglas de la conexión o URL base) preservando query params
+    let remote_url = routing::upstream_url(&conn, request.uri().path(), request.uri().query())?;
+    println!("🚀 [Proxy] Forwarding to: {}", remote_url);
+
+    // Inspector: cada petición queda registrada con su resultado
+    let pending = capture::start();
+
+    // Cola de escrituras sin conexión (opt-in por conexión). Mientras haya escrituras
+    // pendientes, las nuevas se encolan detrás para no adelantarlas.
+    let write_queue_connection = conn
+        .id
+        .filter(|_| conn.offline_writes == Some(true))
+        .map(|id| id as i64);
+    let queue_write = write_queue_connection.filter(|_| write_queue::is_mutation(request.method()));
+    let queue_busy = queue_write.is_some_and(|connection_id| {
+        let state = app_handle.state::<DbState>();
+        let db = state.0.lock().unwrap();
+        write_queue::has_pending(&db, connection_id)
+    });
+
+    // El id viaja ya en el primer intento: si vence el timeout y se encola, el
+    // servidor puede reconocer el reenvío aunque hubiera aplicado el original
+    let queue_id = queue_write.map(|_| write_queue::new_queue_id());
+
+    let mut result = if queue_busy {
+        Err("Hay escrituras pendientes por delante".into())
+    } else {
+        send_to_remote(
+            app_handle,
+            &remote_url,
+            request,
+            app_id,
+            queue_id.as_deref(),
+            proxy_config,
+        )
+        .await
+    };
+
+    if let (Some(connection_id), Some(queue_id)) = (queue_write, &queue_id) {
+        let network_failure = result
+            .as_ref()
+            .err()
+            .is_some_and(write_queue::is_network_error);
+        if queue_busy || network_failure {
+            result = enqueue_write(
+                app_handle,
+                queue_id,
+                connection_id,
+                &remote_url,
+                request,
+                app_id,
+                proxy_config,
+            );
+            spawn_replay(app_handle, connection_id);
+        }
+    } else if let (Some(connection_id), Ok(_)) = (write_queue_connection, &result) {
+        // El servidor vuelve a responder: se reenvía lo que quedara pendiente
+        let pending_writes = {
+            let state = app_handle.state::<DbState>();
+            let db = state.0.lock().unwrap();
+            write_queue::has_pending(&db, connection_id)
+        };
+        if pending_writes {
+            spawn_replay(app_handle, connection_id);
+        }
+    }
+
+    // Caché offline (opt-in por conexión): se guardan los GET correctos y, si el
+    // servidor no responde, se sirve la última copia marcada como `stale`
+    if let (Some(connection_id), Some(true), true) =
+        (conn.id, conn.offline_cache, request.method() == Method::GET)
+    {
+        let state = app_handle.state::<DbState>();
+        let db = state.0.lock().unwrap();
+        let scope = app_id.unwrap_or_default();
+        match &result {
+            Ok(response) => {
+                if let Err(e) =
+                    cache::store(&db, connection_id as i64, scope, &remote_url, response)
+                {
+                    println!("⚠️ [Cache] No se pudo guardar {}: {}", remote_url, e);
+                }
+            }
+            // Sólo si el servidor no es alcanzable: un error propio (cuerpo demasiado
+            // grande, cola ocupada...) no debe enmascararse con una copia antigua
+            Err(e) if write_queue::is_network_error(e) => {
+                if let Ok(Some(cached)) =
+                    cache::lookup(&db, connection_id as i64, scope, &remote_url)
+                {
+                    println!(
+                        "📦 [Cache] Servidor no disponible ({}), sirviendo copia: {}",
+                        e, remote_url
+                    );
+                    result = Ok(cached);
+                }
+            }
+            Err(_) => {}
+        }
+    }
+
+    capture::record(
+        app_handle,
+        pending,
+        "remote",
+        app_id.unwrap_or_default(),
+        &remote_url,
+        request,
+        &result,
+    );
+    result
+}
+
+/// Encola una escritura fallida y contesta `202 Accepted` con su id.
+fn enqueue_write(
+    app_handle: &AppHandle,
+    queue_id: &str,
+    connection_id: i64,
+    remote_url: &Url,
+    request: &Request<Vec<u8>>,
+    app_id: Option<&str>,
+    proxy_config: &ProxyConfig,
+) -> Result<Response<Vec<u8>>, ProxyError> {
+    let state = app_handle.state::<DbState>();
+    let db = state.0.lock().unwrap();
+    let position = write_queue::enqueue(
+        &db,
+        queue_id,
+        connection_id,
+        app_id.unwrap_or_default(),
+        remote_url.as_str(),
+        request,
+        &proxy_config.forward_headers,
+    )?;
+    println!(
+        "📥 [Write Queue] {} {} encolada ({}, posición {})",
+        request.method(),
+        remote_url,
+        queue_id,
+        position
+    );
+    Ok(write_queue::accepted_response(queue_id, position))
+}
+
+/// Lanza el reenvío de la cola en segundo plano (no hace nada si ya está en curso).
+pub fn spawn_replay(app_handle: &AppHandle, connection_id: i64) {
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        write_queue::replay(&app_handle, connection_id).await;
+    });
+}
+
+async fn send_to_remote(
+    app_handle: &AppHandle,
+    remote_url: &Url,
+    request: &Request<Vec<u8>>,
+    app_id: Option<&str>,
+    queue_id: Option<&str>,
+    proxy_config: &ProxyConfig,
+) -> Result<Response<Vec<u8>>, ProxyError> {
+    let method = request.method().clone();
+
+    // Cliente compartido (pool de conexiones); el timeout va por petición
+    let clients = app_handle.state::<ProxyClients>();
+    let context = forward_context(app_handle, app_id);
+    let mut headers = forward::request_headers(request, &proxy_config.forward_headers, &context);
+    if let Some(queue_id) = queue_id {
+        write_queue::set_queue_id(&mut headers, queue_id);
+    }
+    let mut req_builder = clients
+        .remote
+        .request(method, remote_url.clone())
+        .timeout(proxy_config.remote_timeout())
+        .headers(headers);
+
+    // Forward Body
+    let body_bytes = request.body().clone();
+    if !body_bytes.is_empty() {
+        req_builder = req_builder.body(body_bytes);
+    }
+
+    // Ejecutar petición
+    let resp = req_builder.send().await?;
+
+    // Procesar respuesta
+    let status = resp.status();
+    let headers = resp.headers().clone();
+    let body = client::read_body(resp, proxy_config.max_body_bytes()).await?;
+
+    // 1. Iniciar el builder con el status original
+    // 2. Copiar headers REMOTOS (multi-valor incluido) pero FILTRAR los problemáticos para iframes
+    let response_builder =
+        forward::copy_response_headers(Response::builder().status(status.as_u16()), &headers);
+
+    // 3. Inyectar nuestros headers permisivos ("Engaño" al navegador)
+    Ok(response_builder
+        .header("Access-Control-Allow-Origin", "*")
+        .header("X-Frame-Options", "ALLOWALL")
+        .header("Referrer-Policy", "unsafe-url") // 🚀 IMPORTANTE: Forzar al navegador a enviar el Referer completo siempre
+        .header(
+            "Content-Security-Policy",
+            "default-src * 'unsafe-inline' 'unsafe-eval' data: blob:;",
+        ) // Muy permisivo para sandbox
+        .body(body)?)
+}
+
+/// Identidad del cliente y credenciales guardadas de la app para la petición saliente.
+fn forward_context<'a>(app_handle: &AppHandle, app_id: Option<&'a str>) -> ForwardContext<'a> {
+    let state = app_handle.state::<DbState>();
+    let conn = state.0.lock().unwrap();
+
+    let app_token = app_id.and_then(|app_id| {
+        conn.query_row(
+            "SELECT token FROM desktop_apps WHERE app_id = ?1",
+            [app_id],
+            |row| row.get::<_, Option<String>>(0),
+        )
+        .optional()
+        .unwrap_or(None)
+        .flatten()
+    });
+
+    ForwardContext {
+        app_id,
+        client_id: crate::storage::get_or_create_client_id(&conn).ok(),
+        app_token,
+        client_ip: local_ip_address::local_ip().ok().map(|ip| ip.to_string()),
+    }
+}
+
+fn create_response(status: u16, content_type: &str, body: Vec<u8>) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, content_type)
+        .header("Access-Control-Allow-Origin", "*")
+        .body(body)
+        .unwrap()
+}
+
+fn create_error_response(status: u16, msg: &str) -> Response<Vec<u8>> {
+    create_response(status, "text/plain", msg.to_string().into_bytes())
+}
+
+/// Cabeceras CORS del proxy externo. Con credenciales el navegador no acepta `*`,
+/// así que se refleja el `Origin` de la petición.
+fn cors_headers(
+    builder: tauri::http::response::Builder,
+    request: &Request<Vec<u8>>,
+) -> tauri::http::response::Builder {
+    let headers = request.headers();
+    let origin = headers
+        .get(ORIGIN)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("*");
+    let allow_headers = headers
+        .get(ACCESS_CONTROL_REQUEST_HEADERS)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("Content-Type, Authorization, X-Requested-With");
+
+    builder
+        .header("Access-Control-Allow-Origin", origin)
+        .header("Access-Control-Allow-Credentials", "true")
+        .header(
+            "Access-Control-Allow-Methods",
+            forward::EXTERNAL_ALLOW_METHODS,
+        )
+        .header("Access-Control-Allow-Headers", allow_headers)
+        .header("Vary", "Origin")
+}
+
+/// Preflight CORS de una página externa: se responde aquí, sin llegar al sitio remoto
+/// (que no conoce el origen `sandra-app` y lo rechazaría).
+fn is_preflight(request: &Request<Vec<u8>>) -> bool {
+    request.method() == Method::OPTIONS
+        && request
+            .headers()
+            .contains_key(ACCESS_CONTROL_REQUEST_METHOD)
+}
+
+fn preflight_response(request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    cors_headers(Response::builder().status(204), request)
+        .header("Access-Control-Max-Age", "600")
+        .body(Vec::new())
+        .unwrap()
+}
+
+/// Saltos de redirección que sigue el proxy externo antes de rendirse.
+const MAX_EXTERNAL_REDIRECTS: usize = 10;
+
+async fn proxy_arbitrary_url(
+    app_handle: &AppHandle,
+    session: &SessionRef,
+    request: &Request<Vec<u8>>,
+    remote_url: &str,
+    proxy_config: &ProxyConfig,
+) -> Result<Response<Vec<u8>>, ProxyError> {
+    // println!("🌍 [External Proxy] Fetching: {}", remote_url);
+    let url = Url::parse(remote_url)?;
+
+    // Inspector: cada petición queda registrada con su resultado
+    let pending = capture::start();
+    let result = fetch_external(app_handle, session, request, url.clone(), proxy_config).await;
+    capture::record(
+        app_handle,
+        pending,
+        "external",
+        &session.app_id,
+        &url,
+        request,
+        &result,
+    );
+    result
+}
+
+/// Descarga `url` en nombre de la app de `session`: su política de destinos y su
+/// jar de cookies; los enlaces de la respuesta quedan dentro de la misma sesión.
+async fn fetch_external(
+    app_handle: &AppHandle,
+    session: &SessionRef,
+    request: &Request<Vec<u8>>,
+    mut url: Url,
+    proxy_config: &ProxyConfig,
+) -> Result<Response<Vec<u8>>, ProxyError> {
+    // Cliente compartido (pool de conexiones); el timeout va por petición
+    let clients = app_handle.state::<ProxyClients>();
+    let cookie_scope = session.app_id.as_str();
+
+    // Método, cuerpo y cabeceras seguras de la petición original (formularios, XHR)
+    let mut method = request.method().clone();
+    let mut body = Some(request.body().clone()).filter(|b| !b.is_empty());
+
+    // Las redirecciones se siguen aquí y no en reqwest para enviar y guardar
+    // las cookies de cada salto (los logins suelen fijarlas en un 302)
+    let mut redirects = 0;
+    let resp = loop {
+        // La política se comprueba en cada salto: una redirección no puede saltársela
+        let client = match policy::check(&proxy_config.external_policy, cookie_scope, &url).await {
+            Ok(policy::Reach::Public) => &clients.external,
+            Ok(policy::Reach::Private) => &clients.external_private,
+            Err(reason) => {
+                policy::record_denial(app_handle, cookie_scope, &url, &reason);
+                return Ok(create_error_response(
+                    403,
+                    &format!("External Proxy: {} ({})", reason, url),
+                ));
+            }
+        };
+
+        let mut req_builder = client
+            .request(method.clone(), url.clone())
+            .timeout(proxy_config.external_timeout())
+            .headers(forward::external_request_headers(request, &url));
+        if let Some(cookie) = cookies::request_header(app_handle, cookie_scope, &url) {
+            req_builder = req_builder.header(tauri::http::header::COOKIE, cookie);
+        }
+        if let Some(body) = &body {
+            req_builder = req_builder.body(body.clone());
+        }
+        let resp = req_builder.send().await?;
+        cookies::store_response(app_handle, cookie_scope, &url, resp.headers());
+
+        let location = resp
+            .headers()
+            .get(tauri::http::header::LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|location| url.join(location).ok());
+        match location {
+            Some(next) if resp.status().is_redirection() && redirects < MAX_EXTERNAL_REDIRECTS => {
+                redirects += 1;
+                url = next;
+                // Como los navegadores: 307/308 conservan método y cuerpo; el resto
+                // (p. ej. el 303 tras un POST de login) continúa con GET sin cuerpo
+                if !matches!(resp.status().as_u16(), 307 | 308) && method != Method::HEAD {
+                    method = Method::GET;
+                    body = None;
+                }
+            }
+            _ => break resp,
+        }
+    };
+
+    // Procesar respuesta (la URL final, tras redirecciones, es la base de los enlaces)
+    let status = resp.status();
+    let headers = resp.headers().clone();
+    let final_url = url;
+    let mut body = client::read_body(resp, proxy_config.max_body_bytes()).await?;
+
+    // Reescritura de enlaces HTML/CSS para que todo vuelva a pasar por el proxy
+    // (sólo si el cuerpo no viene comprimido)
+    let rewritable = headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(rewrite::Rewritable::from_content_type)
+        .filter(|_| !headers.contains_key("content-encoding"));
+    if let Some(kind) = rewritable {
+        body = rewrite::rewrite_body(kind, body, &final_url, &session.token);
+    }
+
+    // 1. Iniciar el builder con el status original
+    let mut response_builder = Response::builder().status(status.as_u16());
+
+    // 2. Copiar headers REMOTOS pero FILTRAR los problemáticos para iframes
+    for (name, value) in headers.iter() {
+        let name_str = name.as_str().to_lowercase();
+        // Omitimos estos headers para que NO bloqueen el iframe
+        if name_str != "x-frame-options"
+            && name_str != "content-security-policy"
+            && name_str != "access-control-allow-origin"
+            && name_str != "access-control-allow-credentials"
+            // Las cookies quedan en el jar del proxy, no en el origen sandra-app
+            && name_str != "set-cookie"
+            // El cuerpo reescrito cambia de tamaño
+            && !(rewritable.is_some() && name_str == "content-length")
+        // Limpiar el original para inyectar el nuestro
+        {
+            response_builder = response_builder.header(name, value);
+        }
+    }
+
+    // 3. Inyectar nuestros headers permisivos ("Engaño" al navegador)
+    Ok(cors_headers(response_builder, request)
+        .header("X-Frame-Options", "ALLOWALL")
+        .header("Referrer-Policy", "unsafe-url")
+        .header(
+            "Content-Security-Policy",
+            "default-src * 'unsafe-inline' 'unsafe-eval' data: blob: sandra-app:;", // sandra-app: añadido
+        )
+        .body(body)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get(uri: &str, referer: Option<&str>) -> Request<Vec<u8>> {
+        let mut builder = Request::builder().uri(uri);
+        if let Some(referer) = referer {
+            builder = builder.header("referer", referer);
+        }
+        builder.body(Vec::new()).unwrap()
+    }
+
+    fn resolve(
+        sessions: &ExternalSessions,
+        uri: &str,
+        referer: Option<&str>,
+    ) -> Option<Result<ExternalRequest, String>> {
+        external_request(sessions, &get(uri, referer), "main")
+    }
+
+    #[test]
+    fn app_id_comes_from_the_window_or_the_app_page() {
+        let request = get("sandra-app://localhost/v1/items", None);
+        assert_eq!(request_app_id(&request, "app-crm").as_deref(), Some("crm"));
+        assert_eq!(request_app_id(&request, "main"), None);
+
+        let request = get(
+            "sandra-app://localhost/v1/items",
+            Some("sandra-app://localhost/gdoc/index.html"),
+        );
+        assert_eq!(request_app_id(&request, "main").as_deref(), Some("gdoc"));
+    }
+
+    #[test]
+    fn subrequests_of_a_proxied_page_keep_the_app_scope() {
+        let sessions = ExternalSessions::default();
+
+        // Navegación inicial desde la app `crm`, en iframe dentro de `main`
+        let opened = resolve(
+            &sessions,
+            "sandra-app://localhost/external-proxy?target=https%3A%2F%2Fsite.example%2Flogin",
+            Some("sandra-app://localhost/crm/index.html"),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(opened.url, "https://site.example/login");
+        assert_eq!(opened.session.app_id, "crm");
+        let token = opened.session.token.clone();
+        let page = rewrite::proxied(&Url::parse("https://site.example/login").unwrap(), &token);
+        let page = format!("sandra-app://localhost{}", page);
+
+        // Recurso reescrito (con token) y XHR construido por un script (sólo Referer)
+        let asset = resolve(
+            &sessions,
+            &format!(
+                "sandra-app://localhost{}",
+                rewrite::proxied(&Url::parse("https://cdn.example/a.js").unwrap(), &token)
+            ),
+            Some(&page),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(asset.url, "https://cdn.example/a.js");
+        assert_eq!(asset.session.app_id, "crm");
+
+        let xhr = resolve(&sessions, "sandra-app://localhost/api/me?x=1", Some(&page))
+            .unwrap()
+            .unwrap();
+        assert_eq!(xhr.url, "https://site.example/api/me?x=1");
+        assert_eq!(xhr.session.app_id, "crm");
+        assert_eq!(xhr.session.token, token);
+
+        // Sin Referer proxificado no es tráfico externo
+        assert!(resolve(&sessions, "sandra-app://localhost/crm/app.js", None).is_none());
+    }
+
+    #[test]
+    fn proxied_traffic_without_a_valid_session_is_rejected() {
+        let sessions = ExternalSessions::default();
+        let forged =
+            "sandra-app://localhost/external-proxy?session=nope&target=https%3A%2F%2Fsite.example%2F";
+
+        // Nunca se atribuye al contenedor
+        assert!(resolve(&sessions, forged, None).unwrap().is_err());
+        assert!(
+            resolve(&sessions, "sandra-app://localhost/api/me", Some(forged))
+                .unwrap()
+                .is_err()
+        );
+        assert!(resolve(
+            &sessions,
+            "sandra-app://localhost/external-proxy?target=https%3A%2F%2Fother.example%2F",
+            Some(forged),
+        )
+        .unwrap()
+        .is_err());
+
+        // El token de otro webview tampoco sirve
+        let token = sessions.open("app-crm", "crm", "https://site.example/".into());
+        let page = format!(
+            "sandra-app://localhost/external-proxy?session={}&target=https%3A%2F%2Fsite.example%2F",
+            token
+        );
+        assert!(
+            resolve(&sessions, "sandra-app://localhost/api/me", Some(&page))
+                .unwrap()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,416 @@
+use crate::storage::DbState;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use tauri::{AppHandle, Emitter, Manager};
+use url::Url;
+
+/// Tipo de evento en `system_events` para los intentos bloqueados.
+pub const DENIED_EVENT: &str = "EXTERNAL_PROXY_DENIED";
+
+/// Regla de origen del proxy externo.
+///
+/// `pattern` admite `[esquema://]host[:puerto]`, con `*.` al inicio del host para
+/// cualquier subdominio (`https://*.example.com`) o `*` para cualquier host.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct OriginRule {
+    pub pattern: String,
+    /// Apps a las que aplica; vacío = todas.
+    #[serde(default)]
+    pub apps: Vec<String>,
+    /// En reglas `allow`: permite además destinos de red privada o loopback.
+    #[serde(default)]
+    pub private: bool,
+}
+
+/// Política de destinos del proxy externo, gestionada desde el servidor
+/// (`proxy.external_policy` en la configuración en tiempo de ejecución).
+///
+/// - `deny` manda sobre todo lo demás.
+/// - Con `allow` vacía se permite cualquier destino público; con reglas, sólo
+///   los que encajen con alguna regla aplicable a la app.
+/// - Las direcciones privadas, loopback y link-local se bloquean salvo que una
+///   regla `allow` con `private: true` las cubra.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExternalPolicy {
+    pub allow: Vec<OriginRule>,
+    pub deny: Vec<OriginRule>,
+}
+
+struct Pattern<'a> {
+    scheme: Option<&'a str>,
+    host: &'a str,
+    port: Option<u16>,
+}
+
+fn parse_pattern(pattern: &str) -> Result<Pattern<'_>, String> {
+    let (scheme, rest) = match pattern.split_once("://") {
+        Some((scheme, rest)) => (Some(scheme), rest),
+        None => (None, pattern),
+    };
+    if let Some(scheme) = scheme {
+        if scheme != "http" && scheme != "https" {
+            return Err(format!("Esquema no soportado en '{}'", pattern));
+        }
+    }
+
+    let rest = rest.trim_end_matches('/');
+    // Los corchetes de IPv6 contienen ':'; el puerto va tras el último ']'
+    let (host, port) = match rest.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') && !host.is_empty() => {
+            let port = port
+                .parse()
+                .map_err(|_| format!("Puerto inválido en '{}'", pattern))?;
+            (host, Some(port))
+        }
+        _ => (rest, None),
+    };
+
+    if host.is_empty()
+        || host.contains('/')
+        || (host.contains('*') && host != "*" && !host.starts_with("*."))
+    {
+        return Err(format!("Patrón de origen inválido: '{}'", pattern));
+    }
+    Ok(Pattern { scheme, host, port })
+}
+
+impl OriginRule {
+    fn applies_to(&self, app_id: &str) -> bool {
+        self.apps.is_empty() || self.apps.iter().any(|app| app == app_id)
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        let pattern = match parse_pattern(&self.pattern) {
+            Ok(pattern) => pattern,
+            Err(_) => return false,
+        };
+        let host = match url.host_str() {
+            Some(host) => host.to_ascii_lowercase(),
+            None => return false,
+        };
+        let pattern_host = pattern.host.to_ascii_lowercase();
+
+        let host_ok = if pattern_host == "*" {
+            true
+        } else if let Some(suffix) = pattern_host.strip_prefix("*.") {
+            host.ends_with(&format!(".{}", suffix))
+        } else {
+            host == pattern_host
+        };
+        host_ok
+            && pattern.scheme.is_none_or(|scheme| scheme == url.scheme())
+            && pattern
+                .port
+                .is_none_or(|port| url.port_or_known_default() == Some(port))
+    }
+}
+
+impl ExternalPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        for rule in self.allow.iter().chain(&self.deny) {
+            parse_pattern(&rule.pattern).map_err(|e| format!("proxy.external_policy: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Direcciones que no deben alcanzarse desde páginas externas (RFC 1918, loopback,
+/// link-local, CGNAT, ULA...). Las IPv4 mapeadas en IPv6 se comprueban como IPv4.
+fn is_private(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_private_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(v4) => is_private_v4(v4),
+            None => is_private_v6(ip),
+        },
+    }
+}
+
+fn is_private_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || a == 0 // 0.0.0.0/8 ("esta red")
+        || (a == 100 && (64..128).contains(&b)) // 100.64.0.0/10 (CGNAT)
+        || (a == 198 && (b & 0xfe) == 18) // 198.18.0.0/15 (benchmarking)
+        || a >= 240 // 240.0.0.0/4 (reservada)
+}
+
+fn is_private_v6(ip: Ipv6Addr) -> bool {
+    let segments = ip.segments();
+    let first = segments[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80
+        || (first & 0xffc0) == 0xfec0 // site-local (obsoleta, aún enrutada en algunas redes)
+        // IPv4 compatible (::a.b.c.d, obsoleta): muchas pilas la entregan como IPv4
+        || (segments[..6] == [0; 6] && is_private_v4(embedded_v4(segments[6], segments[7])))
+        // NAT64 (64:ff9b::/96) y 6to4 (2002::/16) llevan una IPv4 dentro
+        || (segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] && is_private_v4(embedded_v4(segments[6], segments[7])))
+        || (first == 0x2002 && is_private_v4(embedded_v4(segments[1], segments[2])))
+}
+
+fn embedded_v4(high: u16, low: u16) -> Ipv4Addr {
+    Ipv4Addr::from(((high as u32) << 16) | low as u32)
+}
+
+/// Resolutor DNS del cliente externo: descarta las direcciones privadas al conectar.
+/// `check` resuelve el host por su cuenta, así que sin esto un nombre podría apuntar
+/// a una IP pública al comprobarlo y a la red interna cuando reqwest conecta (DNS rebinding).
+pub struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|addr| !is_private(addr.ip()))
+                .collect();
+            if addresses.is_empty() {
+                return Err(
+                    format!("'{}' sólo resuelve a direcciones de red privada", host).into(),
+                );
+            }
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Alcance permitido para un destino del proxy externo.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reach {
+    /// Sólo direcciones públicas: se usa el cliente con `PublicResolver`.
+    Public,
+    /// Una regla `allow` con `private: true` cubre el destino.
+    Private,
+}
+
+/// Decide si `app_id` puede cargar `url` por el proxy externo. Resuelve el host
+/// para detectar nombres que apuntan a la red interna; la conexión en sí la vuelve
+/// a filtrar `PublicResolver` salvo que el resultado sea `Reach::Private`.
+pub async fn check(policy: &ExternalPolicy, app_id: &str, url: &Url) -> Result<Reach, String> {
+    match url.scheme() {
+        "http" | "https" => {}
+        other => return Err(format!("esquema '{}' no permitido", other)),
+    }
+
+    if let Some(rule) = policy
+        .deny
+        .iter()
+        .find(|rule| rule.applies_to(app_id) && rule.matches(url))
+    {
+        return Err(format!("denegado por la regla '{}'", rule.pattern));
+    }
+
+    let allowed_by = policy
+        .allow
+        .iter()
+        .filter(|rule| rule.applies_to(app_id) && rule.matches(url))
+        .collect::<Vec<_>>();
+    if !policy.allow.is_empty() && allowed_by.is_empty() {
+        return Err("el origen no está en la lista permitida".into());
+    }
+    if allowed_by.iter().any(|rule| rule.private) {
+        return Ok(Reach::Private);
+    }
+
+    let host = url.host_str().ok_or("URL sin host")?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.eq_ignore_ascii_case("localhost") || host.to_ascii_lowercase().ends_with(".localhost") {
+        return Err("destino loopback no permitido".into());
+    }
+
+    let addresses: Vec<IpAddr> = match host.parse::<IpAddr>() {
+        Ok(ip) => vec![ip],
+        Err(_) => {
+            let port = url.port_or_known_default().unwrap_or(443);
+            tokio::net::lookup_host((host, port))
+                .await
+                .map_err(|e| format!("no se pudo resolver '{}': {}", host, e))?
+                .map(|addr| addr.ip())
+                .collect()
+        }
+    };
+    if let Some(ip) = addresses.into_iter().find(|ip| is_private(*ip)) {
+        return Err(format!("destino de red privada no permitido ({})", ip));
+    }
+    Ok(Reach::Public)
+}
+
+/// Registra un intento bloqueado en `system_events` y lo notifica a la UI.
+pub fn record_denial(app_handle: &AppHandle, app_id: &str, url: &Url, reason: &str) {
+    println!("🛑 [External Proxy] {} -> {}: {}", app_id, url, reason);
+
+    let metadata = serde_json::json!({
+        "app_id": app_id,
+        "url": url.as_str(),
+        "reason": reason,
+    });
+    let state = app_handle.state::<DbState>();
+    if let Ok(conn) = state.0.lock() {
+        let _ = conn.execute(
+            "INSERT INTO system_events (event_type, description, metadata) VALUES (?1, ?2, ?3)",
+            rusqlite::params![
+                DENIED_EVENT,
+                format!("Proxy externo bloqueado: {}", url),
+                metadata.to_string()
+            ],
+        );
+    };
+    let _ = app_handle.emit("external-proxy-denied", metadata);
+}
+
+/// Intento bloqueado tal como quedó registrado en `system_events`.
+#[derive(Serialize, Debug)]
+pub struct DeniedAttempt {
+    pub id: i64,
+    pub description: Option<String>,
+    /// `{app_id, url, reason}`
+    pub metadata: serde_json::Value,
+    pub timestamp: String,
+}
+
+/// Últimos intentos bloqueados, del más reciente al más antiguo.
+pub fn recent_denials(
+    conn: &rusqlite::Connection,
+    limit: i64,
+) -> Result<Vec<DeniedAttempt>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, description, metadata, timestamp FROM system_events
+             WHERE event_type = ?1 ORDER BY id DESC LIMIT ?2",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params![DENIED_EVENT, limit], |row| {
+            let metadata: Option<String> = row.get(2)?;
+            Ok(DeniedAttempt {
+                id: row.get(0)?,
+                description: row.get(1)?,
+                metadata: metadata
+                    .and_then(|m| serde_json::from_str(&m).ok())
+                    .unwrap_or(serde_json::Value::Null),
+                timestamp: row.get(3)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn private(s: &str) -> bool {
+        is_private(s.parse().unwrap())
+    }
+
+    #[test]
+    fn private_v4_ranges() {
+        for ip in [
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "127.0.0.1",
+            "169.254.169.254",
+            "0.0.0.0",
+            "0.1.2.3",
+            "100.64.0.1",
+            "100.127.255.255",
+            "198.18.0.1",
+            "198.19.255.255",
+            "224.0.0.1",
+            "240.0.0.1",
+            "255.255.255.255",
+        ] {
+            assert!(private(ip), "{} debería ser privada", ip);
+        }
+        for ip in [
+            "8.8.8.8",
+            "100.128.0.1",
+            "198.20.0.1",
+            "172.32.0.1",
+            "1.1.1.1",
+        ] {
+            assert!(!private(ip), "{} debería ser pública", ip);
+        }
+    }
+
+    #[test]
+    fn private_v6_ranges() {
+        for ip in [
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "ff02::1",
+            "::ffff:127.0.0.1",
+            "::ffff:10.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+            "64:ff9b::7f00:1",
+            "2002:7f00:1::",
+            "2002:c0a8:101::1",
+            "::127.0.0.1",
+            "::10.0.0.1",
+            "fec0::1",
+            "feff::1",
+        ] {
+            assert!(private(ip), "{} debería ser privada", ip);
+        }
+        for ip in [
+            "2606:4700::1111",
+            "64:ff9b::808:808",
+            "2002:808:808::1",
+            "::ffff:8.8.8.8",
+            "::8.8.8.8",
+        ] {
+            assert!(!private(ip), "{} debería ser pública", ip);
+        }
+    }
+
+    fn rule(pattern: &str, private: bool) -> OriginRule {
+        OriginRule {
+            pattern: pattern.into(),
+            apps: vec![],
+            private,
+        }
+    }
+
+    fn check_now(policy: &ExternalPolicy, url: &str) -> Result<Reach, String> {
+        tauri::async_runtime::block_on(check(policy, "crm", &Url::parse(url).unwrap()))
+    }
+
+    #[test]
+    fn check_blocks_private_literals_unless_allowed() {
+        let open = ExternalPolicy::default();
+        assert_eq!(check_now(&open, "https://8.8.8.8/"), Ok(Reach::Public));
+        assert!(check_now(&open, "http://127.0.0.1/").is_err());
+        assert!(check_now(&open, "http://[::1]/").is_err());
+        assert!(check_now(&open, "http://localhost/").is_err());
+        assert!(check_now(&open, "ftp://8.8.8.8/").is_err());
+
+        let lan = ExternalPolicy {
+            allow: vec![rule("http://192.168.1.10", true)],
+            deny: vec![],
+        };
+        assert_eq!(check_now(&lan, "http://192.168.1.10/"), Ok(Reach::Private));
+        assert!(check_now(&lan, "https://8.8.8.8/").is_err());
+
+        let deny = ExternalPolicy {
+            allow: vec![],
+            deny: vec![rule("*.example.com", false)],
+        };
+        assert!(check_now(&deny, "https://a.example.com/").is_err());
+    }
+}
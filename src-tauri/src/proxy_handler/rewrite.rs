@@ -0,0 +1,288 @@
+use regex::{Captures, Regex};
+use std::sync::LazyLock;
+use url::Url;
+
+/// Ruta del proxy externo. Se emite relativa a la raíz para que resuelva contra
+/// el origen del protocolo en cada plataforma (`sandra-app://localhost` o
+/// `http://sandra-app.localhost` en Windows).
+const PROXY_PATH: &str = "/external-proxy?";
+
+/// Atributos HTML que contienen una única URL.
+static URL_ATTR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?i)(\s(?:href|src|action|formaction|poster|data|background)\s*=\s*)(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#,
+    )
+    .unwrap()
+});
+/// `srcset` / `imagesrcset`: lista de candidatos `url descriptor`.
+static SRCSET_ATTR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)(\s(?:srcset|imagesrcset)\s*=\s*)(?:"([^"]*)"|'([^']*)')"#).unwrap()
+});
+static BASE_TAG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)<base\s[^>]*?href\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))[^>]*>"#).unwrap()
+});
+static CSS_URL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)url\(\s*(?:"([^"]*)"|'([^']*)'|([^)"'\s]*))\s*\)"#).unwrap()
+});
+static CSS_IMPORT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)@import\s+(?:"([^"]*)"|'([^']*)')"#).unwrap());
+/// Partes del HTML que se reescriben: bloques `<script>` y `<style>` completos,
+/// comentarios y etiquetas de apertura. El texto entre ellas no se toca.
+static HTML_TOKEN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?is)(<script\b[^>]*>)(.*?)(</script\s*>)|(<style\b[^>]*>)(.*?)(</style\s*>)|<!--.*?-->|<[a-z][^>]*>"#,
+    )
+    .unwrap()
+});
+
+/// Tipo de contenido reescribible.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Rewritable {
+    Html,
+    Css,
+}
+
+impl Rewritable {
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next()?.trim().to_ascii_lowercase();
+        match mime.as_str() {
+            "text/html" | "application/xhtml+xml" => Some(Rewritable::Html),
+            "text/css" => Some(Rewritable::Css),
+            _ => None,
+        }
+    }
+}
+
+/// URL del proxy externo para un recurso absoluto, dentro de la sesión `session`
+/// (así las subpeticiones de la página siguen siendo de la app que la abrió).
+pub fn proxied(url: &Url, session: &str) -> String {
+    format!(
+        "{}session={}&target={}",
+        PROXY_PATH,
+        urlencoding::encode(session),
+        urlencoding::encode(url.as_str())
+    )
+}
+
+/// Resuelve `raw` contra `base` y lo envía por el proxy. Devuelve `None` para lo
+/// que no debe tocarse (anclas, `data:`, `javascript:`, URLs ya proxificadas...).
+fn rewrite_url(raw: &str, base: &Url, session: &str) -> Option<String> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with(PROXY_PATH) {
+        return None;
+    }
+
+    // Los atributos HTML pueden traer entidades
+    let decoded = trimmed.replace("&amp;", "&");
+    let resolved = base.join(&decoded).ok()?;
+    match resolved.scheme() {
+        "http" | "https" => Some(proxied(&resolved, session)),
+        _ => None,
+    }
+}
+
+/// Reescribe el cuerpo si es HTML o CSS en UTF-8; si no, lo devuelve igual.
+pub fn rewrite_body(kind: Rewritable, body: Vec<u8>, base: &Url, session: &str) -> Vec<u8> {
+    let text = match String::from_utf8(body) {
+        Ok(text) => text,
+        Err(e) => return e.into_bytes(),
+    };
+
+    let rewritten = match kind {
+        Rewritable::Html => rewrite_html(&text, base, session),
+        Rewritable::Css => rewrite_css(&text, base, session),
+    };
+    rewritten.into_bytes()
+}
+
+/// Sólo se reescriben los atributos de las etiquetas y el CSS de `<style>` y `style=""`.
+/// El código de los `<script>` queda intacto: un `el.src = '/x'` no es un atributo.
+fn rewrite_html(html: &str, base: &Url, session: &str) -> String {
+    // <base href> cambia la resolución de todo el documento; tras reescribir ya no
+    // hace falta (y rompería las rutas del proxy), así que se elimina.
+    let base = HTML_TOKEN
+        .find_iter(html)
+        .find_map(|token| BASE_TAG.captures(token.as_str()))
+        .and_then(|caps| value_group(&caps, 1).map(str::to_string))
+        .and_then(|href| base.join(href.trim()).ok())
+        .unwrap_or_else(|| base.clone());
+
+    HTML_TOKEN
+        .replace_all(html, |caps: &Captures| {
+            if let Some(open) = caps.get(1) {
+                // <script src="..."> sí se reescribe; su contenido no
+                format!(
+                    "{}{}{}",
+                    rewrite_tag(open.as_str(), &base, session),
+                    &caps[2],
+                    &caps[3]
+                )
+            } else if let Some(open) = caps.get(4) {
+                format!(
+                    "{}{}{}",
+                    rewrite_tag(open.as_str(), &base, session),
+                    rewrite_css(&caps[5], &base, session),
+                    &caps[6]
+                )
+            } else if caps[0].starts_with("<!--") {
+                caps[0].to_string()
+            } else if BASE_TAG.is_match(&caps[0]) {
+                String::new()
+            } else {
+                rewrite_tag(&caps[0], &base, session)
+            }
+        })
+        .into_owned()
+}
+
+/// Atributos con URL, `srcset` y `style="..."` de una etiqueta de apertura.
+fn rewrite_tag(tag: &str, base: &Url, session: &str) -> String {
+    let tag = URL_ATTR.replace_all(tag, |caps: &Captures| {
+        let value = value_group(caps, 2).unwrap_or_default();
+        match rewrite_url(value, base, session) {
+            Some(url) => format!("{}\"{}\"", &caps[1], url),
+            None => caps[0].to_string(),
+        }
+    });
+
+    let tag = SRCSET_ATTR.replace_all(&tag, |caps: &Captures| {
+        let value = value_group(caps, 2).unwrap_or_default();
+        format!("{}\"{}\"", &caps[1], rewrite_srcset(value, base, session))
+    });
+
+    rewrite_css(&tag, base, session)
+}
+
+fn rewrite_srcset(srcset: &str, base: &Url, session: &str) -> String {
+    srcset
+        .split(',')
+        .map(|candidate| {
+            let candidate = candidate.trim();
+            let (url, descriptor) = match candidate.split_once(char::is_whitespace) {
+                Some((url, descriptor)) => (url, Some(descriptor.trim())),
+                None => (candidate, None),
+            };
+            let url = rewrite_url(url, base, session).unwrap_or_else(|| url.to_string());
+            match descriptor {
+                Some(descriptor) => format!("{} {}", url, descriptor),
+                None => url,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn rewrite_css(css: &str, base: &Url, session: &str) -> String {
+    let css = CSS_URL.replace_all(css, |caps: &Captures| {
+        let value = value_group(caps, 1).unwrap_or_default();
+        match rewrite_url(value, base, session) {
+            // Sin comillas: puede estar dentro de un atributo style="..."
+            // y la URL proxificada va codificada (sin espacios ni paréntesis)
+            Some(url) => format!("url({})", url),
+            None => caps[0].to_string(),
+        }
+    });
+
+    CSS_IMPORT
+        .replace_all(&css, |caps: &Captures| {
+            let value = value_group(caps, 1).unwrap_or_default();
+            match rewrite_url(value, base, session) {
+                Some(url) => format!("@import url({})", url),
+                None => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// Valor capturado en el primer grupo presente desde `from` (las alternativas de comillas).
+fn value_group<'a>(caps: &Captures<'a>, from: usize) -> Option<&'a str> {
+    (from..caps.len())
+        .find_map(|i| caps.get(i))
+        .map(|m| m.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> Url {
+        Url::parse("https://example.com/docs/page.html").unwrap()
+    }
+
+    const SESSION: &str = "s1";
+
+    fn proxy(url: &str) -> String {
+        proxied(&Url::parse(url).unwrap(), SESSION)
+    }
+
+    #[test]
+    fn rewrites_attributes_and_srcset() {
+        let html = r#"<a href="/about">x</a><img src='img/a.png' srcset="a.png 1x, /b.png 2x"><a href=#top>t</a>"#;
+        let out = rewrite_html(html, &base(), SESSION);
+
+        assert!(out.contains(&format!(r#"href="{}""#, proxy("https://example.com/about"))));
+        assert!(out.contains(&format!(
+            r#"src="{}""#,
+            proxy("https://example.com/docs/img/a.png")
+        )));
+        assert!(out.contains(&format!(
+            "{} 1x, {} 2x",
+            proxy("https://example.com/docs/a.png"),
+            proxy("https://example.com/b.png")
+        )));
+        assert!(out.contains("href=#top"));
+    }
+
+    #[test]
+    fn script_bodies_are_left_alone() {
+        let script = r#"el.src = '/x'; document.body.style.background = "url(/bg.png)";"#;
+        let html = format!(
+            r#"<script src="/app.js"></script><script>{}</script>"#,
+            script
+        );
+        let out = rewrite_html(&html, &base(), SESSION);
+
+        assert!(out.contains(&format!(r#"src="{}""#, proxy("https://example.com/app.js"))));
+        assert!(out.contains(&format!("<script>{}</script>", script)));
+    }
+
+    #[test]
+    fn style_blocks_and_attributes_are_rewritten() {
+        let html = r#"<style>body { background: url("/bg.png") }</style><div style="background:url(x.png)">url(/text)</div>"#;
+        let out = rewrite_html(html, &base(), SESSION);
+
+        assert!(out.contains(&format!("url({})", proxy("https://example.com/bg.png"))));
+        assert!(out.contains(&format!("url({})", proxy("https://example.com/docs/x.png"))));
+        // El texto fuera de etiquetas no se toca
+        assert!(out.contains(">url(/text)</div>"));
+    }
+
+    #[test]
+    fn base_tag_changes_resolution_and_is_removed() {
+        let html = r#"<head><base href="https://cdn.example.net/v2/"></head><img src="logo.png">"#;
+        let out = rewrite_html(html, &base(), SESSION);
+
+        assert!(!out.contains("<base"));
+        assert!(out.contains(&proxy("https://cdn.example.net/v2/logo.png")));
+    }
+
+    #[test]
+    fn leaves_non_http_and_proxied_urls() {
+        assert_eq!(
+            rewrite_url("data:image/png;base64,AAAA", &base(), SESSION),
+            None
+        );
+        assert_eq!(rewrite_url("javascript:void(0)", &base(), SESSION), None);
+        assert_eq!(
+            rewrite_url("/external-proxy?target=x", &base(), SESSION),
+            None
+        );
+        assert_eq!(
+            rewrite_css("@import 'print.css';", &base(), SESSION),
+            format!(
+                "@import url({});",
+                proxy("https://example.com/docs/print.css")
+            )
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use crate::commands::connections::Connection;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Regla de enrutado de una conexión: las peticiones cuyo path encaja con `path`
+/// van a `upstream` en lugar de a la URL base de la API.
+///
+/// `path` admite un `*` final como prefijo (`/v1/reports/*`); sin él es exacto.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RouteRule {
+    pub path: String,
+    /// URL base (esquema, host, puerto y prefijo opcional), p. ej. `http://localhost:8081/api`.
+    pub upstream: String,
+}
+
+impl RouteRule {
+    fn matches(&self, path: &str) -> bool {
+        match self.path.strip_suffix('*') {
+            Some(prefix) => path.starts_with(prefix),
+            None => path == self.path,
+        }
+    }
+}
+
+/// Comprueba que una URL base sea utilizable como upstream.
+pub fn validate_base_url(base: &str) -> Result<Url, String> {
+    let url = Url::parse(base).map_err(|e| format!("URL base inválida '{}': {}", base, e))?;
+    match url.scheme() {
+        "http" | "https" => {}
+        other => return Err(format!("Esquema no soportado en '{}': {}", base, other)),
+    }
+    if url.host_str().is_none() {
+        return Err(format!("La URL base '{}' no tiene host", base));
+    }
+    if url.query().is_some() || url.fragment().is_some() {
+        return Err(format!(
+            "La URL base '{}' no puede llevar query ni fragmento",
+            base
+        ));
+    }
+    Ok(url)
+}
+
+/// Valida la URL base, las reglas, el modo simulado y la política de reconexión de un
+/// perfil antes de guardarlo.
+pub fn validate_connection(conn: &Connection) -> Result<(), String> {
+    if let Some(base) = conn.api_base_url.as_deref().filter(|s| !s.is_empty()) {
+        validate_base_url(base)?;
+    }
+    for rule in conn.route_rules.iter().flatten() {
+        if !rule.path.starts_with('/') {
+            return Err(format!("La regla '{}' debe empezar por '/'", rule.path));
+        }
+        validate_base_url(&rule.upstream)?;
+    }
+    if let Some(mock) = &conn.mock {
+        mock.validate()?;
+    }
+    if let Some(policy) = &conn.reconnect_policy {
+        policy
+            .validate()
+            .map_err(|e| format!("Política de reconexión: {}", e))?;
+    }
+    Ok(())
+}
+
+/// URL final de una petición `/v1/`: primera regla que encaje o, si no,
+/// la URL base de la conexión (por defecto `https://{ip_address}:{port}`).
+/// El path de la petición se añade tras el prefijo de la base.
+pub fn upstream_url(conn: &Connection, path: &str, query: Option<&str>) -> Result<Url, String> {
+    let rule = conn
+        .route_rules
+        .iter()
+        .flatten()
+        .find(|rule| rule.matches(path));
+
+    let base = match (rule, conn.api_base_url.as_deref().filter(|s| !s.is_empty())) {
+        (Some(rule), _) => rule.upstream.clone(),
+        (None, Some(base)) => base.to_string(),
+        (None, None) => format!("https://{}:{}", conn.ip_address, conn.port),
+    };
+
+    let mut url = validate_base_url(&base)?;
+    let prefix = url.path().trim_end_matches('/').to_string();
+    url.set_path(&format!("{}{}", prefix, path));
+    url.set_query(query);
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection(api_base_url: Option<&str>, route_rules: Vec<RouteRule>) -> Connection {
+        Connection {
+            id: Some(1),
+            name: "prueba".into(),
+            ip_address: "10.0.0.5".into(),
+            port: 8443,
+            username: None,
+            password: None,
+            last_connected: None,
+            wss_host: None,
+            wss_port: None,
+            is_connected: None,
+            reconnect_policy: None,
+            api_base_url: api_base_url.map(String::from),
+            route_rules: Some(route_rules),
+            offline_cache: None,
+            offline_writes: None,
+            mock: None,
+        }
+    }
+
+    fn rule(path: &str, upstream: &str) -> RouteRule {
+        RouteRule {
+            path: path.into(),
+            upstream: upstream.into(),
+        }
+    }
+
+    #[test]
+    fn defaults_to_the_connection_address() {
+        let url = upstream_url(&connection(None, vec![]), "/v1/items", Some("page=2")).unwrap();
+        assert_eq!(url.as_str(), "https://10.0.0.5:8443/v1/items?page=2");
+    }
+
+    #[test]
+    fn base_url_prefix_is_kept() {
+        let conn = connection(Some("http://localhost:8080/gateway/"), vec![]);
+        let url = upstream_url(&conn, "/v1/items", None).unwrap();
+        assert_eq!(url.as_str(), "http://localhost:8080/gateway/v1/items");
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let conn = connection(
+            Some("http://localhost:8080"),
+            vec![
+                rule("/v1/reports/*", "http://reports:8081/api"),
+                rule("/v1/reports/special", "http://never:1"),
+                rule("/v1/health", "http://health:9000"),
+            ],
+        );
+
+        let reports = upstream_url(&conn, "/v1/reports/2024/q1", None).unwrap();
+        assert_eq!(
+            reports.as_str(),
+            "http://reports:8081/api/v1/reports/2024/q1"
+        );
+        let special = upstream_url(&conn, "/v1/reports/special", None).unwrap();
+        assert_eq!(special.host_str(), Some("reports"));
+        // Las reglas sin `*` son exactas
+        let health = upstream_url(&conn, "/v1/health/deep", None).unwrap();
+        assert_eq!(health.host_str(), Some("localhost"));
+    }
+
+    #[test]
+    fn invalid_base_urls_are_rejected() {
+        assert!(validate_base_url("ftp://host").is_err());
+        assert!(validate_base_url("http://host/api?x=1").is_err());
+        assert!(validate_base_url("no es una url").is_err());
+        assert!(validate_connection(&connection(None, vec![rule("v1/x", "http://a")])).is_err());
+    }
+}
@@ -0,0 +1,207 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Vida de una sesión externa desde la última navegación explícita a
+/// `/external-proxy`. Es absoluta: las peticiones que captura no la renuevan.
+pub const EXTERNAL_SESSION_TTL: Duration = Duration::from_secs(30 * 60);
+
+struct ExternalSession {
+    webview: String,
+    app_id: String,
+    target: String,
+    opened: Instant,
+}
+
+impl ExternalSession {
+    fn expired(&self) -> bool {
+        self.opened.elapsed() > EXTERNAL_SESSION_TTL
+    }
+
+    fn to_ref(&self, token: &str) -> SessionRef {
+        SessionRef {
+            token: token.to_string(),
+            app_id: self.app_id.clone(),
+            target: self.target.clone(),
+        }
+    }
+}
+
+/// Sesión externa vigente: el token que llevan sus URLs proxificadas, la app en
+/// cuyo nombre se navega (`""` = contenedor) y el último destino abierto.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionRef {
+    pub token: String,
+    pub app_id: String,
+    pub target: String,
+}
+
+#[derive(Serialize)]
+pub struct ExternalSessionInfo {
+    pub webview: String,
+    pub app_id: String,
+    pub target: String,
+    pub age_secs: u64,
+    pub expires_in_secs: u64,
+}
+
+/// Último sitio externo abierto con `/external-proxy` por cada app de cada webview.
+/// Las apps en iframe comparten el webview `main`, así que la sesión se identifica
+/// por un token opaco que viaja en las URLs reescritas (`?session=...`): una página
+/// proxificada sigue siendo de su app aunque el Referer ya no lo diga.
+#[derive(Default)]
+pub struct ExternalSessions {
+    entries: Mutex<HashMap<String, ExternalSession>>,
+}
+
+impl ExternalSessions {
+    /// Abre (o renueva) la sesión de la app en el webview y devuelve su token, que
+    /// se conserva entre navegaciones para no romper las páginas ya abiertas.
+    pub fn open(&self, webview: &str, app_id: &str, target: String) -> String {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, session| !session.expired());
+
+        let existing = entries
+            .iter_mut()
+            .find(|(_, session)| session.webview == webview && session.app_id == app_id);
+        if let Some((token, session)) = existing {
+            session.target = target;
+            session.opened = Instant::now();
+            return token.clone();
+        }
+
+        let token = uuid::Uuid::new_v4().simple().to_string();
+        entries.insert(
+            token.clone(),
+            ExternalSession {
+                webview: webview.to_string(),
+                app_id: app_id.to_string(),
+                target,
+                opened: Instant::now(),
+            },
+        );
+        token
+    }
+
+    /// Sesión de un token, sólo si pertenece a este webview y no ha caducado.
+    pub fn by_token(&self, webview: &str, token: &str) -> Option<SessionRef> {
+        let mut entries = self.entries.lock().unwrap();
+        let session = entries.get(token).filter(|s| s.webview == webview)?;
+        if session.expired() {
+            println!("⌛ [External] Sesión de '{}' caducada", webview);
+            entries.remove(token);
+            return None;
+        }
+        Some(session.to_ref(token))
+    }
+
+    /// Sesión vigente de una app en el webview; no renueva la caducidad.
+    pub fn get(&self, webview: &str, app_id: &str) -> Option<SessionRef> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, session| !session.expired());
+        entries
+            .iter()
+            .find(|(_, s)| s.webview == webview && s.app_id == app_id)
+            .map(|(token, session)| session.to_ref(token))
+    }
+
+    /// Cierra las sesiones de un webview o, sin argumento, todas. Devuelve cuántas había.
+    pub fn clear(&self, webview: Option<&str>) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|_, session| webview.is_some_and(|label| session.webview != label));
+        before - entries.len()
+    }
+
+    pub fn list(&self) -> Vec<ExternalSessionInfo> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, session| !session.expired());
+
+        let mut list: Vec<_> = entries
+            .values()
+            .map(|session| ExternalSessionInfo {
+                webview: session.webview.clone(),
+                app_id: session.app_id.clone(),
+                target: session.target.clone(),
+                age_secs: session.opened.elapsed().as_secs(),
+                expires_in_secs: EXTERNAL_SESSION_TTL
+                    .saturating_sub(session.opened.elapsed())
+                    .as_secs(),
+            })
+            .collect();
+        list.sort_by(|a, b| (&a.webview, &a.app_id).cmp(&(&b.webview, &b.app_id)));
+        list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_do_not_extend_the_session() {
+        let sessions = ExternalSessions::default();
+        let token = sessions.open("main", "", "https://example.com/".into());
+        assert_eq!(
+            sessions.get("main", "").map(|s| s.target).as_deref(),
+            Some("https://example.com/")
+        );
+        assert_eq!(sessions.get("app-gdoc", ""), None);
+
+        // Una sesión abierta hace más del TTL caduca aunque se haya leído después
+        let opened = Instant::now()
+            .checked_sub(EXTERNAL_SESSION_TTL + Duration::from_secs(1))
+            .unwrap();
+        sessions
+            .entries
+            .lock()
+            .unwrap()
+            .get_mut(&token)
+            .unwrap()
+            .opened = opened;
+        assert_eq!(sessions.get("main", ""), None);
+        assert_eq!(sessions.by_token("main", &token), None);
+        assert!(sessions.list().is_empty());
+    }
+
+    #[test]
+    fn apps_in_the_same_webview_do_not_share_a_session() {
+        let sessions = ExternalSessions::default();
+        let crm = sessions.open("main", "crm", "https://a.example/".into());
+        let gdoc = sessions.open("main", "gdoc", "https://b.example/".into());
+        assert_ne!(crm, gdoc);
+
+        assert_eq!(
+            sessions.get("main", "crm").unwrap().target,
+            "https://a.example/"
+        );
+        assert_eq!(
+            sessions.get("main", "gdoc").unwrap().target,
+            "https://b.example/"
+        );
+        assert_eq!(sessions.get("main", ""), None);
+
+        // Navegar de nuevo conserva el token y actualiza el destino
+        let again = sessions.open("main", "crm", "https://a.example/next".into());
+        assert_eq!(again, crm);
+        let session = sessions.by_token("main", &crm).unwrap();
+        assert_eq!(session.app_id, "crm");
+        assert_eq!(session.target, "https://a.example/next");
+
+        // El token no vale desde otro webview
+        assert_eq!(sessions.by_token("app-crm", &crm), None);
+    }
+
+    #[test]
+    fn clear_by_webview_or_all() {
+        let sessions = ExternalSessions::default();
+        sessions.open("main", "crm", "https://a.example/".into());
+        sessions.open("main", "gdoc", "https://a.example/".into());
+        sessions.open("app-gdoc", "gdoc", "https://b.example/".into());
+        assert_eq!(sessions.clear(Some("main")), 2);
+        assert_eq!(sessions.clear(Some("main")), 0);
+        assert_eq!(sessions.list().len(), 1);
+        assert_eq!(sessions.clear(None), 1);
+    }
+}
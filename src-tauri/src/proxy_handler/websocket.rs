@@ -0,0 +1,307 @@
+use super::{forward, routing, ProxyClients};
+use base64::{engine::general_purpose, Engine as _};
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::protocol::Message;
+use tokio_tungstenite::{connect_async_tls_with_config, Connector};
+
+/// Sólo estas rutas se pueden abrir como WebSocket a través del puente.
+const SOCKET_PREFIX: &str = "/v1/ws/";
+/// Mensajes de la app pendientes de enviar por socket; al llenarse, `send` falla
+/// en lugar de acumular memoria si el servidor no lee.
+const OUTBOUND_BUFFER: usize = 256;
+
+/// Eventos que el puente entrega a la app por su `Channel`.
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SocketEvent {
+    Open {
+        socket_id: String,
+    },
+    Message {
+        data: String,
+    },
+    /// Mensaje binario en base64.
+    Binary {
+        data: String,
+    },
+    Closed {
+        code: Option<u16>,
+        reason: String,
+    },
+    Error {
+        message: String,
+    },
+}
+
+struct OpenSocket {
+    /// Webview que abrió el socket: se cierra con él.
+    webview: String,
+    outbound: mpsc::Sender<Message>,
+}
+
+/// WebSockets abiertos por las apps hacia la conexión activa (`/v1/ws/...`).
+#[derive(Default)]
+pub struct SocketBridge {
+    sockets: Mutex<HashMap<String, OpenSocket>>,
+}
+
+impl SocketBridge {
+    /// Envía por un socket del webview `webview`; los de otra ventana se tratan como
+    /// inexistentes para no revelar ids ajenos.
+    pub fn send(&self, webview: &str, socket_id: &str, message: Message) -> Result<(), String> {
+        let sockets = self.sockets.lock().unwrap();
+        let socket = sockets
+            .get(socket_id)
+            .filter(|socket| socket.webview == webview)
+            .ok_or_else(|| format!("El socket {} no está abierto", socket_id))?;
+        socket.outbound.try_send(message).map_err(|e| match e {
+            mpsc::error::TrySendError::Full(_) => format!(
+                "El socket {} tiene demasiados mensajes pendientes de enviar",
+                socket_id
+            ),
+            mpsc::error::TrySendError::Closed(_) => {
+                format!("El socket {} se está cerrando", socket_id)
+            }
+        })
+    }
+
+    /// Cierra un socket del webview `webview`: al soltar su emisor, la tarea envía el
+    /// `Close` y termina.
+    pub fn close(&self, webview: &str, socket_id: &str) -> bool {
+        let mut sockets = self.sockets.lock().unwrap();
+        if sockets
+            .get(socket_id)
+            .is_some_and(|socket| socket.webview == webview)
+        {
+            sockets.remove(socket_id);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Cierra los sockets de un webview (p. ej. al destruirse su ventana).
+    pub fn close_webview(&self, webview: &str) -> usize {
+        let mut sockets = self.sockets.lock().unwrap();
+        let before = sockets.len();
+        sockets.retain(|_, socket| socket.webview != webview);
+        before - sockets.len()
+    }
+}
+
+/// Abre `path` (`/v1/ws/...`, con query opcional) en la conexión que corresponde a
+/// la app, con la misma autenticación y política TLS que el tráfico `/v1/`.
+///
+/// La app sale de la ventana propia que lo pide (`app-<id>`), nunca del llamante. A
+/// diferencia de `/v1/` no hay Referer: Tauri sólo identifica el webview, no el iframe
+/// que invoca, así que las llamadas desde la ventana principal se rechazan en vez de
+/// atribuirse al contenedor.
+pub async fn open(
+    app_handle: &AppHandle,
+    webview: &str,
+    path: &str,
+    channel: Channel<SocketEvent>,
+) -> Result<String, String> {
+    let (path, query) = match path.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (path, None),
+    };
+    if !path.starts_with(SOCKET_PREFIX) {
+        return Err(format!("Sólo se admiten rutas {}", SOCKET_PREFIX));
+    }
+
+    let app_id = super::webview_app_id(webview).ok_or(
+        "Los WebSocket sólo se abren desde la ventana propia de una app: \
+         desde la ventana principal no se sabe qué app lo pide",
+    )?;
+    let app_id = Some(app_id.as_str());
+    let conn =
+        super::resolve_connection(app_handle, app_id)?.ok_or("No hay ninguna conexión activa")?;
+    if conn.mock.as_ref().is_some_and(|mock| mock.enabled) {
+        return Err("El modo simulado no admite WebSocket".into());
+    }
+
+    let mut url = routing::upstream_url(&conn, path, query)?;
+    let scheme = if url.scheme() == "https" { "wss" } else { "ws" };
+    url.set_scheme(scheme)
+        .map_err(|_| format!("No se pudo convertir {} a WebSocket", url))?;
+
+    let mut ws_request = url
+        .as_str()
+        .into_client_request()
+        .map_err(|e| e.to_string())?;
+    forward::add_identity(
+        ws_request.headers_mut(),
+        &super::forward_context(app_handle, app_id),
+    );
+
+    // Misma política que el cliente `/v1/`: certificados internos sin validar
+    let connector = Connector::NativeTls(app_handle.state::<ProxyClients>().remote_tls.clone());
+
+    let timeout = crate::runtime_config::current(app_handle)
+        .proxy
+        .remote_timeout();
+    let (stream, _) = tokio::time::timeout(
+        timeout,
+        connect_async_tls_with_config(ws_request, None, false, Some(connector)),
+    )
+    .await
+    .map_err(|_| format!("Tiempo de espera agotado abriendo {}", url))?
+    .map_err(|e| format!("No se pudo abrir {}: {}", url, e))?;
+
+    let socket_id = uuid::Uuid::new_v4().to_string();
+    let (outbound, outbound_rx) = mpsc::channel(OUTBOUND_BUFFER);
+    app_handle
+        .state::<SocketBridge>()
+        .sockets
+        .lock()
+        .unwrap()
+        .insert(
+            socket_id.clone(),
+            OpenSocket {
+                webview: webview.to_string(),
+                outbound,
+            },
+        );
+    println!("🔌 [WS Bridge] {} abierto -> {}", socket_id, url);
+
+    let _ = channel.send(SocketEvent::Open {
+        socket_id: socket_id.clone(),
+    });
+    let app_handle = app_handle.clone();
+    let id = socket_id.clone();
+    tauri::async_runtime::spawn(async move {
+        pump(stream, outbound_rx, &channel).await;
+        app_handle
+            .state::<SocketBridge>()
+            .sockets
+            .lock()
+            .unwrap()
+            .remove(&id);
+        println!("🔌 [WS Bridge] {} cerrado", id);
+    });
+
+    Ok(socket_id)
+}
+
+/// Copia mensajes en ambos sentidos hasta que cierre cualquiera de los dos extremos.
+async fn pump<S>(
+    stream: tokio_tungstenite::WebSocketStream<S>,
+    mut outbound_rx: mpsc::Receiver<Message>,
+    channel: &Channel<SocketEvent>,
+) where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    let (mut sink, mut source) = stream.split();
+
+    let closed = loop {
+        tokio::select! {
+            incoming = source.next() => {
+                let event = match incoming {
+                    Some(Ok(Message::Text(text))) => SocketEvent::Message { data: text.to_string() },
+                    Some(Ok(Message::Binary(data))) => SocketEvent::Binary {
+                        data: general_purpose::STANDARD.encode(&data),
+                    },
+                    Some(Ok(Message::Close(frame))) => break SocketEvent::Closed {
+                        code: frame.as_ref().map(|f| u16::from(f.code)),
+                        reason: frame.map(|f| f.reason.to_string()).unwrap_or_default(),
+                    },
+                    // Ping/Pong los gestiona tungstenite
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => break SocketEvent::Error { message: e.to_string() },
+                    None => break SocketEvent::Closed { code: None, reason: "El servidor cerró la conexión".into() },
+                };
+                // Si el webview ya no existe no hay a quién entregar: se cierra
+                if channel.send(event).is_err() {
+                    let _ = sink.send(Message::Close(None)).await;
+                    return;
+                }
+            }
+            outgoing = outbound_rx.recv() => match outgoing {
+                Some(message) => {
+                    if let Err(e) = sink.send(message).await {
+                        break SocketEvent::Error { message: e.to_string() };
+                    }
+                }
+                // La app (o su ventana) cerró el socket
+                None => {
+                    let _ = sink.send(Message::Close(None)).await;
+                    break SocketEvent::Closed { code: Some(1000), reason: "Cerrado por la app".into() };
+                }
+            },
+        }
+    };
+
+    let _ = channel.send(closed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn send_fails_when_the_buffer_is_full() {
+        let bridge = SocketBridge::default();
+        let (outbound, mut outbound_rx) = mpsc::channel(OUTBOUND_BUFFER);
+        bridge.sockets.lock().unwrap().insert(
+            "s1".into(),
+            OpenSocket {
+                webview: "app-crm".into(),
+                outbound,
+            },
+        );
+
+        for _ in 0..OUTBOUND_BUFFER {
+            bridge.send("app-crm", "s1", Message::text("hola")).unwrap();
+        }
+        assert!(bridge
+            .send("app-crm", "s1", Message::text("de más"))
+            .unwrap_err()
+            .contains("pendientes"));
+
+        // En cuanto la tarea consume, vuelve a admitir mensajes
+        assert!(outbound_rx.try_recv().is_ok());
+        assert!(bridge.send("app-crm", "s1", Message::text("otra")).is_ok());
+
+        assert!(bridge.send("app-crm", "s2", Message::text("x")).is_err());
+        assert_eq!(bridge.close_webview("app-crm"), 1);
+        assert!(!bridge.close("app-crm", "s1"));
+    }
+
+    #[test]
+    fn only_the_owner_webview_can_use_a_socket() {
+        let bridge = SocketBridge::default();
+        let (outbound, _outbound_rx) = mpsc::channel(OUTBOUND_BUFFER);
+        bridge.sockets.lock().unwrap().insert(
+            "s1".into(),
+            OpenSocket {
+                webview: "app-crm".into(),
+                outbound,
+            },
+        );
+
+        assert!(bridge.send("app-otra", "s1", Message::text("x")).is_err());
+        assert!(bridge.send("main", "s1", Message::text("x")).is_err());
+        assert!(!bridge.close("app-otra", "s1"));
+
+        assert!(bridge.send("app-crm", "s1", Message::text("x")).is_ok());
+        assert!(bridge.close("app-crm", "s1"));
+    }
+
+    #[test]
+    fn app_comes_from_the_window_label() {
+        assert_eq!(
+            crate::proxy_handler::webview_app_id("app-crm").as_deref(),
+            Some("crm")
+        );
+        assert_eq!(crate::proxy_handler::webview_app_id("app-"), None);
+        assert_eq!(crate::proxy_handler::webview_app_id("main"), None);
+    }
+}
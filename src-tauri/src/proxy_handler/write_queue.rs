@@ -0,0 +1,431 @@
+use super::{client, forward, ProxyClients, ProxyError};
+use crate::storage::DbState;
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tauri::http::{HeaderMap, HeaderName, HeaderValue, Method, Request, Response};
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Cabecera con el identificador de la escritura. Se envía ya en el primer intento y
+/// en cada reenvío: tras un timeout no se sabe si el servidor la aplicó, así que es
+/// él quien debe descartar los duplicados.
+pub const QUEUE_ID_HEADER: &str = "X-Sandra-Queue-Id";
+
+/// Fija `QUEUE_ID_HEADER` sustituyendo cualquier valor previo: el identificador lo pone
+/// siempre el contenedor, nunca la app.
+pub fn set_queue_id(headers: &mut HeaderMap, queue_id: &str) {
+    if let Ok(value) = HeaderValue::from_str(queue_id) {
+        headers.insert(HeaderName::from_static("x-sandra-queue-id"), value);
+    }
+}
+
+/// Métodos que modifican datos y, por tanto, se encolan en vez de perderse.
+pub fn is_mutation(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+    )
+}
+
+/// Sólo los fallos de red (no se llegó a obtener respuesta) justifican encolar.
+pub fn is_network_error(error: &ProxyError) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .is_some_and(|e| e.is_connect() || e.is_timeout())
+}
+
+#[derive(Serialize, Debug)]
+pub struct QueuedWrite {
+    pub queue_id: String,
+    pub connection_id: i64,
+    pub app_id: String,
+    pub method: String,
+    pub url: String,
+    /// `pending` o `failed` (no se pudo reenviar por un error que no es de red).
+    pub status: String,
+    pub attempts: i64,
+    pub last_error: Option<String>,
+    pub created_at: String,
+}
+
+/// Resultado final de una escritura encolada, emitido en `proxy-write-result`.
+#[derive(Serialize, Clone, Debug)]
+pub struct WriteOutcome {
+    pub queue_id: String,
+    pub connection_id: i64,
+    pub app_id: String,
+    pub method: String,
+    pub url: String,
+    /// Estado HTTP de la respuesta del servidor; `None` si falló sin respuesta.
+    pub status: Option<u16>,
+    pub ok: bool,
+    pub body: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Evita dos reenvíos simultáneos de la cola de una misma conexión.
+#[derive(Default)]
+pub struct WriteQueue {
+    replaying: Mutex<HashSet<i64>>,
+}
+
+pub fn has_pending(conn: &Connection, connection_id: i64) -> bool {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM proxy_write_queue WHERE connection_id = ?1 AND status = 'pending')",
+        [connection_id],
+        |row| row.get(0),
+    )
+    .unwrap_or(false)
+}
+
+/// Id para una escritura que puede acabar en la cola.
+pub fn new_queue_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// Guarda una escritura y devuelve su posición en la cola de la conexión.
+/// Las cabeceras se guardan sin la identidad ni el token de la app (`forward::app_headers`).
+pub fn enqueue(
+    conn: &Connection,
+    queue_id: &str,
+    connection_id: i64,
+    app_id: &str,
+    url: &str,
+    request: &Request<Vec<u8>>,
+    allowlist: &[String],
+) -> Result<i64, String> {
+    let headers: Vec<(String, String)> = forward::app_headers(request, allowlist)
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+    let headers = serde_json::to_string(&headers).map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "INSERT INTO proxy_write_queue (queue_id, connection_id, app_id, method, url, headers, body)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        rusqlite::params![
+            queue_id,
+            connection_id,
+            app_id,
+            request.method().as_str(),
+            url,
+            headers,
+            request.body()
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let position: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM proxy_write_queue WHERE connection_id = ?1 AND status = 'pending'",
+            [connection_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(position)
+}
+
+/// `202 Accepted` con el id para que la app pueda asociar el resultado posterior.
+pub fn accepted_response(queue_id: &str, position: i64) -> Response<Vec<u8>> {
+    let body = serde_json::json!({
+        "queued": true,
+        "queue_id": queue_id,
+        "position": position,
+    });
+    Response::builder()
+        .status(202)
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .header("Access-Control-Expose-Headers", QUEUE_ID_HEADER)
+        .header(QUEUE_ID_HEADER, queue_id)
+        .body(body.to_string().into_bytes())
+        .unwrap()
+}
+
+struct PendingWrite {
+    id: i64,
+    queue_id: String,
+    app_id: String,
+    method: String,
+    url: String,
+    headers: String,
+    body: Vec<u8>,
+}
+
+fn next_pending(conn: &Connection, connection_id: i64) -> Result<Option<PendingWrite>, String> {
+    conn.query_row(
+        "SELECT id, queue_id, app_id, method, url, headers, body FROM proxy_write_queue
+         WHERE connection_id = ?1 AND status = 'pending' ORDER BY id ASC LIMIT 1",
+        [connection_id],
+        |row| {
+            Ok(PendingWrite {
+                id: row.get(0)?,
+                queue_id: row.get(1)?,
+                app_id: row.get(2)?,
+                method: row.get(3)?,
+                url: row.get(4)?,
+                headers: row.get(5)?,
+                body: row.get(6)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// Reenvía en orden las escrituras pendientes de `connection_id`. Se detiene en el
+/// primer fallo de red (el orden se conserva para el siguiente intento).
+pub async fn replay(app_handle: &AppHandle, connection_id: i64) {
+    let queue = app_handle.state::<WriteQueue>();
+    if !queue.replaying.lock().unwrap().insert(connection_id) {
+        return;
+    }
+
+    let mut delivered = 0;
+    loop {
+        let pending = {
+            let state = app_handle.state::<DbState>();
+            let conn = state.0.lock().unwrap();
+            next_pending(&conn, connection_id)
+        };
+        let write = match pending {
+            Ok(Some(write)) => write,
+            Ok(None) => break,
+            Err(e) => {
+                println!("⚠️ [Write Queue] {}", e);
+                break;
+            }
+        };
+
+        match send(app_handle, &write).await {
+            Ok((status, body)) => {
+                delivered += 1;
+                finish(
+                    app_handle,
+                    connection_id,
+                    &write,
+                    Some(status),
+                    Some(body),
+                    None,
+                );
+            }
+            Err(e) if is_network_error(&e) => {
+                println!(
+                    "📴 [Write Queue] Servidor aún no disponible ({}); {} reenviadas",
+                    e, delivered
+                );
+                let state = app_handle.state::<DbState>();
+                let conn = state.0.lock().unwrap();
+                let _ = conn.execute(
+                    "UPDATE proxy_write_queue SET attempts = attempts + 1, last_error = ?1 WHERE id = ?2",
+                    rusqlite::params![e.to_string(), write.id],
+                );
+                break;
+            }
+            Err(e) => finish(
+                app_handle,
+                connection_id,
+                &write,
+                None,
+                None,
+                Some(e.to_string()),
+            ),
+        }
+    }
+
+    if delivered > 0 {
+        println!(
+            "📤 [Write Queue] {} escrituras reenviadas a la conexión {}",
+            delivered, connection_id
+        );
+    }
+    queue.replaying.lock().unwrap().remove(&connection_id);
+}
+
+async fn send(app_handle: &AppHandle, write: &PendingWrite) -> Result<(u16, String), ProxyError> {
+    let method = Method::from_bytes(write.method.as_bytes())?;
+    let stored: Vec<(String, String)> = serde_json::from_str(&write.headers)?;
+    let mut headers = HeaderMap::new();
+    for (name, value) in stored {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            headers.append(name, value);
+        }
+    }
+    // La identidad y el token de la app no se guardan en la cola: se leen de nuevo
+    let app_id = Some(write.app_id.as_str()).filter(|app_id| !app_id.is_empty());
+    forward::add_identity(&mut headers, &super::forward_context(app_handle, app_id));
+    set_queue_id(&mut headers, &write.queue_id);
+
+    let proxy_config = crate::runtime_config::current(app_handle).proxy;
+    let clients = app_handle.state::<ProxyClients>();
+    let mut req_builder = clients
+        .remote
+        .request(method, &write.url)
+        .timeout(proxy_config.remote_timeout())
+        .headers(headers);
+    if !write.body.is_empty() {
+        req_builder = req_builder.body(write.body.clone());
+    }
+
+    let resp = req_builder.send().await?;
+    let status = resp.status().as_u16();
+    let body = client::read_body(resp, proxy_config.max_body_bytes()).await?;
+    Ok((status, String::from_utf8_lossy(&body).into_owned()))
+}
+
+/// Cierra una escritura: con respuesta del servidor sale de la cola; sin ella
+/// queda como `failed` para revisarla. En ambos casos se notifica a la app.
+fn finish(
+    app_handle: &AppHandle,
+    connection_id: i64,
+    write: &PendingWrite,
+    status: Option<u16>,
+    body: Option<String>,
+    error: Option<String>,
+) {
+    {
+        let state = app_handle.state::<DbState>();
+        let conn = state.0.lock().unwrap();
+        let _ = match &error {
+            None => conn.execute("DELETE FROM proxy_write_queue WHERE id = ?1", [write.id]),
+            Some(e) => conn.execute(
+                "UPDATE proxy_write_queue SET status = 'failed', attempts = attempts + 1, last_error = ?1 WHERE id = ?2",
+                rusqlite::params![e, write.id],
+            ),
+        };
+    }
+
+    let outcome = WriteOutcome {
+        queue_id: write.queue_id.clone(),
+        connection_id,
+        app_id: write.app_id.clone(),
+        method: write.method.clone(),
+        url: write.url.clone(),
+        status,
+        ok: status.is_some_and(|s| (200..300).contains(&s)),
+        body,
+        error,
+    };
+    let _ = app_handle.emit("proxy-write-result", outcome);
+}
+
+pub fn list(
+    conn: &Connection,
+    connection_id: Option<i64>,
+    app_id: Option<&str>,
+) -> Result<Vec<QueuedWrite>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT queue_id, connection_id, app_id, method, url, status, attempts, last_error, created_at
+             FROM proxy_write_queue
+             WHERE (?1 IS NULL OR connection_id = ?1) AND (?2 IS NULL OR app_id = ?2)
+             ORDER BY id ASC",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params![connection_id, app_id], |row| {
+            Ok(QueuedWrite {
+                queue_id: row.get(0)?,
+                connection_id: row.get(1)?,
+                app_id: row.get(2)?,
+                method: row.get(3)?,
+                url: row.get(4)?,
+                status: row.get(5)?,
+                attempts: row.get(6)?,
+                last_error: row.get(7)?,
+                created_at: row.get(8)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Descarta una escritura encolada (p. ej. una `failed` ya revisada).
+pub fn discard(conn: &Connection, queue_id: &str) -> Result<usize, String> {
+    conn.execute(
+        "DELETE FROM proxy_write_queue WHERE queue_id = ?1",
+        [queue_id],
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::storage::init_tables(&conn).unwrap();
+        conn
+    }
+
+    fn stored_headers(conn: &Connection, queue_id: &str) -> Vec<(String, String)> {
+        let json: String = conn
+            .query_row(
+                "SELECT headers FROM proxy_write_queue WHERE queue_id = ?1",
+                [queue_id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn mutations_only() {
+        assert!(is_mutation(&Method::POST));
+        assert!(is_mutation(&Method::DELETE));
+        assert!(!is_mutation(&Method::GET));
+        assert!(!is_mutation(&Method::OPTIONS));
+    }
+
+    #[test]
+    fn enqueue_keeps_order_and_omits_container_identity() {
+        let conn = db();
+        let allowlist = forward::default_forward_headers();
+        let request = Request::builder()
+            .method("POST")
+            .uri("sandra-app://localhost/v1/items")
+            .header("content-type", "application/json")
+            .header("x-sandra-client", "spoofed")
+            .header("X-Sandra-Queue-Id", "spoofed")
+            .body(b"{\"a\":1}".to_vec())
+            .unwrap();
+
+        let first = new_queue_id();
+        let second = new_queue_id();
+        let url = "https://srv.local/v1/items";
+        assert_eq!(
+            enqueue(&conn, &first, 1, "crm", url, &request, &allowlist),
+            Ok(1)
+        );
+        assert_eq!(
+            enqueue(&conn, &second, 1, "crm", url, &request, &allowlist),
+            Ok(2)
+        );
+        assert!(has_pending(&conn, 1));
+        assert!(!has_pending(&conn, 2));
+
+        let headers = stored_headers(&conn, &first);
+        assert!(headers.contains(&("content-type".into(), "application/json".into())));
+        for (name, _) in &headers {
+            assert!(
+                !matches!(
+                    name.as_str(),
+                    "authorization" | "x-sandra-client" | "x-sandra-queue-id" | "x-forwarded-for"
+                ),
+                "'{}' no debería guardarse",
+                name
+            );
+        }
+
+        let next = next_pending(&conn, 1).unwrap().unwrap();
+        assert_eq!(next.queue_id, first);
+        assert_eq!(next.body, b"{\"a\":1}");
+    }
+}
@@ -0,0 +1,170 @@
+use super::{telemetry, RemoteSession};
+use crate::commands::apps::{
+    clone_app_repo, load_remote_app_policy, open_app_webview, pull_app_repo, remove_app_repo,
+    validate_repo_url,
+};
+use crate::storage::DbState;
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Gestión remota de apps: install_app, update_app, remove_app, list_apps, open_app.
+///
+/// Formato: `{"cmd": "install_app", "id": "<request_id>", "app_id": "gdoc", "repo": "https://...", "name": "GDoc"}`
+/// El progreso se reporta con `command_progress` y el final con `command_result`.
+pub fn handle(cmd: &str, json: &Value, session: &RemoteSession) {
+    let request_id = json["id"].clone();
+    let sender = session.sender.clone();
+    let app_handle = session.app_handle.clone();
+    let cmd = cmd.to_string();
+    let json = json.clone();
+
+    // git y el disco son bloqueantes: fuera del bucle de la sesión
+    tauri::async_runtime::spawn_blocking(move || {
+        let outcome = run(&cmd, &json, &app_handle, &|stage, detail| {
+            sender.progress(&request_id, &cmd, stage, detail)
+        });
+
+        if let Err(e) = &outcome {
+            println!("❌ [Remote] {} falló: {}", cmd, e);
+        }
+        sender.result(&request_id, &cmd, outcome);
+    });
+}
+
+fn run(
+    cmd: &str,
+    json: &Value,
+    app_handle: &AppHandle,
+    progress: &dyn Fn(&str, Option<String>),
+) -> Result<Value, String> {
+    if cmd == "list_apps" {
+        return serde_json::to_value(telemetry::installed_apps(app_handle))
+            .map_err(|e| e.to_string());
+    }
+
+    let app_id = json["app_id"]
+        .as_str()
+        .filter(|s| !s.is_empty())
+        .ok_or("Falta 'app_id'")?
+        .to_string();
+
+    let policy = {
+        let state = app_handle.state::<DbState>();
+        let conn = state.0.lock().unwrap();
+        load_remote_app_policy(&conn)
+    };
+
+    match cmd {
+        "install_app" => {
+            if !policy.allow_install {
+                return Err("La política local prohíbe instalar apps de forma remota.".into());
+            }
+            let repo = match json["repo"].as_str() {
+                Some(repo) => repo.to_string(),
+                None => registered_repo(app_handle, &app_id)?
+                    .ok_or("Falta 'repo' y la app no tiene repositorio registrado")?,
+            };
+
+            progress("cloning", Some(repo.clone()));
+            clone_app_repo(app_handle, &repo, &app_id)?;
+
+            progress("registering", None);
+            register_installed(app_handle, &app_id, &repo, json)?;
+        }
+        "update_app" => {
+            if !policy.allow_update {
+                return Err("La política local prohíbe actualizar apps de forma remota.".into());
+            }
+            progress("pulling", None);
+            pull_app_repo(app_handle, &app_id)?;
+        }
+        "remove_app" => {
+            if !policy.allow_remove {
+                return Err("La política local prohíbe eliminar apps de forma remota.".into());
+            }
+            progress("removing", None);
+            remove_app_repo(app_handle, &app_id)?;
+            set_installed_flag(app_handle, &app_id, false)?;
+        }
+        "open_app" => {
+            if !policy.allow_open {
+                return Err("La política local prohíbe abrir apps de forma remota.".into());
+            }
+            open_app_webview(app_handle, &app_id)?;
+        }
+        other => return Err(format!("Comando desconocido: {}", other)),
+    }
+
+    // La UI local refresca su lista de apps
+    let _ = app_handle.emit(
+        "remote-apps-changed",
+        serde_json::json!({ "cmd": cmd, "app_id": app_id }),
+    );
+
+    let version = crate::commands::apps::app_install_dir(app_handle, &app_id)
+        .ok()
+        .and_then(|dir| crate::commands::apps::installed_app_version(&dir));
+    Ok(serde_json::json!({ "app_id": app_id, "version": version }))
+}
+
+/// Repositorio guardado para la app. Se valida igual que uno recibido: la fila pudo
+/// escribirse desde la UI o por una versión anterior sin comprobaciones.
+fn registered_repo(app_handle: &AppHandle, app_id: &str) -> Result<Option<String>, String> {
+    let state = app_handle.state::<DbState>();
+    let conn = state.0.lock().unwrap();
+    let repo = conn
+        .query_row(
+            "SELECT repo FROM desktop_apps WHERE app_id = ?1",
+            [app_id],
+            |row| row.get::<_, Option<String>>(0),
+        )
+        .or_else(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => Ok(None),
+            e => Err(e.to_string()),
+        })?;
+    if let Some(repo) = &repo {
+        validate_repo_url(repo)?;
+    }
+    Ok(repo)
+}
+
+/// Marca la app como instalada, creándola en `desktop_apps` si el servidor la envía por primera vez.
+fn register_installed(
+    app_handle: &AppHandle,
+    app_id: &str,
+    repo: &str,
+    json: &Value,
+) -> Result<(), String> {
+    validate_repo_url(repo)?;
+    let state = app_handle.state::<DbState>();
+    let conn = state.0.lock().unwrap();
+
+    let updated = conn
+        .execute(
+            "UPDATE desktop_apps SET is_installed = 1, repo = ?1 WHERE app_id = ?2",
+            [repo, app_id],
+        )
+        .map_err(|e| e.to_string())?;
+
+    if updated == 0 {
+        let name = json["name"].as_str().unwrap_or(app_id);
+        let icon = json["icon"].as_str().unwrap_or("fas fa-cube");
+        conn.execute(
+            "INSERT INTO desktop_apps (app_id, name, icon, repo, is_installed) VALUES (?1, ?2, ?3, ?4, 1)",
+            [app_id, name, icon, repo],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn set_installed_flag(app_handle: &AppHandle, app_id: &str, installed: bool) -> Result<(), String> {
+    let state = app_handle.state::<DbState>();
+    let conn = state.0.lock().unwrap();
+    conn.execute(
+        "UPDATE desktop_apps SET is_installed = ?1 WHERE app_id = ?2",
+        rusqlite::params![installed, app_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
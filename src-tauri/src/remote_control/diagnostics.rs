@@ -0,0 +1,277 @@
+use super::{ask_consent, logs, outbox, telemetry, RemoteSender, RemoteSession};
+use crate::commands::handler_error::collect_db_stats;
+use crate::connection_manager::ConnectionManager;
+use crate::storage::DbState;
+use serde::Serialize;
+use serde_json::Value;
+use std::process::Command;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const DEFAULT_ERROR_LIMIT: i64 = 50;
+const MAX_ERROR_LIMIT: i64 = 500;
+/// Tiempo para que la ventana quede al frente antes de capturarla.
+const FOCUS_SETTLE: Duration = Duration::from_millis(400);
+
+#[derive(Serialize)]
+struct WindowInfo {
+    label: String,
+    title: Option<String>,
+    url: Option<String>,
+    visible: bool,
+    focused: bool,
+}
+
+/// Resumen de la captura en el resultado; la imagen viaja aparte en trozos.
+#[derive(Serialize)]
+struct Screenshot {
+    window: String,
+    format: &'static str,
+    size_bytes: usize,
+    chunks: usize,
+}
+
+/// `{"cmd": "diagnostics", "id": "...", "screenshot": "main", "error_limit": 50}`
+///
+/// El paquete (ventanas, conexiones, errores recientes, telemetría, BD) y la
+/// captura opcional sólo se envían si el usuario lo acepta en pantalla.
+/// La captura no va en el `command_result` (duradero y limitado por el outbox):
+/// se envía antes como `screenshot_chunk` en trozos base64 no duraderos.
+pub fn handle(json: &Value, session: &RemoteSession) {
+    let request_id = json["id"].clone();
+    let sender = session.sender.clone();
+    let app_handle = session.app_handle.clone();
+    let screenshot = json["screenshot"].as_str().map(String::from);
+    let error_limit = json["error_limit"]
+        .as_i64()
+        .unwrap_or(DEFAULT_ERROR_LIMIT)
+        .clamp(1, MAX_ERROR_LIMIT);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        sender.progress(&request_id, "diagnostics", "awaiting_consent", None);
+        let message = match &screenshot {
+            Some(window) => format!(
+                "El servidor solicita un diagnóstico del equipo y una captura de la ventana '{}'.\n¿Desea enviarlos?",
+                window
+            ),
+            None => "El servidor solicita un diagnóstico del equipo.\n¿Desea enviarlo?".to_string(),
+        };
+
+        let outcome = if ask_consent(&app_handle, "Solicitud de diagnóstico", &message) {
+            sender.progress(&request_id, "diagnostics", "collecting", None);
+            collect(
+                &app_handle,
+                &sender,
+                &request_id,
+                screenshot.as_deref(),
+                error_limit,
+            )
+        } else {
+            Err("El usuario rechazó el envío del diagnóstico.".to_string())
+        };
+
+        if let Err(e) = &outcome {
+            println!("❌ [Remote] diagnostics falló: {}", e);
+        }
+        sender.result(&request_id, "diagnostics", outcome);
+    });
+}
+
+fn collect(
+    app_handle: &AppHandle,
+    sender: &RemoteSender,
+    request_id: &Value,
+    screenshot: Option<&str>,
+    error_limit: i64,
+) -> Result<Value, String> {
+    let windows: Vec<WindowInfo> = app_handle
+        .webview_windows()
+        .into_iter()
+        .map(|(label, window)| WindowInfo {
+            label,
+            title: window.title().ok(),
+            url: window.url().ok().map(|u| u.to_string()),
+            visible: window.is_visible().unwrap_or(false),
+            focused: window.is_focused().unwrap_or(false),
+        })
+        .collect();
+
+    let connections = app_handle.state::<ConnectionManager>().snapshots();
+
+    let (recent_errors, db_stats, pending_outbound) = {
+        let state = app_handle.state::<DbState>();
+        let conn = state.0.lock().unwrap();
+        (
+            logs::recent_errors(&conn, error_limit)?,
+            collect_db_stats(&conn)?,
+            outbox::list(&conn, None)?.len(),
+        )
+    };
+
+    let screenshot = match screenshot {
+        Some(label) => {
+            let image = capture_window(app_handle, label)?;
+            let chunks = logs::stream_chunks(
+                "screenshot_chunk",
+                "png+base64",
+                &image,
+                logs::DEFAULT_CHUNK_SIZE,
+                request_id,
+                sender,
+            );
+            Some(Screenshot {
+                window: label.to_string(),
+                format: "png",
+                size_bytes: image.len(),
+                chunks,
+            })
+        }
+        None => None,
+    };
+
+    Ok(serde_json::json!({
+        "generated_at": chrono::Utc::now().to_rfc3339(),
+        "runtime_config_version": crate::runtime_config::current(app_handle).version,
+        "windows": windows,
+        "connections": connections,
+        "pending_outbound": pending_outbound,
+        "recent_errors": recent_errors,
+        "db_stats": db_stats,
+        "telemetry": telemetry::build_report(app_handle, sender.client_id.clone()),
+        "screenshot": screenshot,
+    }))
+}
+
+/// Captura la región de pantalla que ocupa la ventana indicada (PNG).
+/// Se trae la ventana al frente para que no la tape otra.
+fn capture_window(app_handle: &AppHandle, label: &str) -> Result<Vec<u8>, String> {
+    let window = app_handle
+        .get_webview_window(label)
+        .ok_or(format!("No existe la ventana '{}'", label))?;
+
+    let _ = window.unminimize();
+    let _ = window.set_focus();
+    std::thread::sleep(FOCUS_SETTLE);
+
+    let position = window.outer_position().map_err(|e| e.to_string())?;
+    let size = window.outer_size().map_err(|e| e.to_string())?;
+    let scale = window.scale_factor().unwrap_or(1.0);
+    let region = Region {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        scale,
+    };
+
+    let path = std::env::temp_dir().join(format!("sandra-diag-{}.png", uuid::Uuid::new_v4()));
+    let image = run_capture(&region, &path);
+    let _ = std::fs::remove_file(&path);
+    image
+}
+
+/// Prueba las herramientas de captura en orden; una que no esté instalada
+/// no es un error mientras quede otra por probar.
+fn run_capture(region: &Region, path: &std::path::Path) -> Result<Vec<u8>, String> {
+    let mut missing = Vec::new();
+    for mut command in capture_commands(region, path) {
+        let status = match command.status() {
+            Ok(status) => status,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                missing.push(command.get_program().to_string_lossy().into_owned());
+                continue;
+            }
+            Err(e) => return Err(format!("No se pudo ejecutar {:?}: {}", command, e)),
+        };
+        return if status.success() {
+            std::fs::read(path).map_err(|e| e.to_string())
+        } else {
+            Err(format!("{:?} terminó con {}", command, status))
+        };
+    }
+    Err(format!(
+        "No hay ninguna herramienta de captura instalada (se probó: {}).",
+        missing.join(", ")
+    ))
+}
+
+/// Región en píxeles físicos.
+struct Region {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    /// Sólo macOS la necesita (screencapture usa puntos).
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    scale: f64,
+}
+
+#[cfg(target_os = "windows")]
+fn capture_commands(region: &Region, path: &std::path::Path) -> Vec<Command> {
+    let script = format!(
+        "Add-Type -AssemblyName System.Drawing; \
+         $b = New-Object System.Drawing.Bitmap {w}, {h}; \
+         $g = [System.Drawing.Graphics]::FromImage($b); \
+         $g.CopyFromScreen({x}, {y}, 0, 0, $b.Size); \
+         $b.Save('{path}', [System.Drawing.Imaging.ImageFormat]::Png)",
+        w = region.width,
+        h = region.height,
+        x = region.x,
+        y = region.y,
+        path = path.display()
+    );
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-NonInteractive", "-Command", &script]);
+    vec![command]
+}
+
+#[cfg(target_os = "macos")]
+fn capture_commands(region: &Region, path: &std::path::Path) -> Vec<Command> {
+    // screencapture trabaja en puntos, no en píxeles físicos
+    let rect = format!(
+        "{},{},{},{}",
+        (region.x as f64 / region.scale) as i32,
+        (region.y as f64 / region.scale) as i32,
+        (region.width as f64 / region.scale) as u32,
+        (region.height as f64 / region.scale) as u32
+    );
+    let mut command = Command::new("screencapture");
+    command.args(["-x", "-R", &rect]).arg(path);
+    vec![command]
+}
+
+/// X11 (ImageMagick, maim, scrot) y Wayland (grim, sólo compositores wlroots).
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn capture_commands(region: &Region, path: &std::path::Path) -> Vec<Command> {
+    let (x, y, w, h) = (region.x, region.y, region.width, region.height);
+
+    // ImageMagick: captura la pantalla completa y recorta la ventana
+    let mut import = Command::new("import");
+    import
+        .args([
+            "-window",
+            "root",
+            "-crop",
+            &format!("{}x{}+{}+{}", w, h, x, y),
+        ])
+        .arg(path);
+
+    let mut grim = Command::new("grim");
+    grim.args(["-g", &format!("{},{} {}x{}", x, y, w, h)])
+        .arg(path);
+
+    let mut maim = Command::new("maim");
+    maim.args(["-g", &format!("{}x{}+{}+{}", w, h, x, y)])
+        .arg(path);
+
+    let mut scrot = Command::new("scrot");
+    scrot
+        .args(["-o", "-a", &format!("{},{},{},{}", x, y, w, h)])
+        .arg(path);
+
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        vec![grim, import, maim, scrot]
+    } else {
+        vec![import, maim, scrot, grim]
+    }
+}
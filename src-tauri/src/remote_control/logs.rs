@@ -0,0 +1,402 @@
+use super::{ask_consent, RemoteSender, RemoteSession};
+use crate::connection_manager::ConnectionManager;
+use crate::storage::DbState;
+use base64::{engine::general_purpose, Engine as _};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use serde_json::Value;
+use std::io::Write;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+/// Tope de filas por tabla para que un rango mal elegido no agote la memoria.
+const MAX_ROWS_PER_TABLE: i64 = 50_000;
+/// Tamaño por defecto de cada trozo enviado por el WebSocket (antes de base64).
+pub(super) const DEFAULT_CHUNK_SIZE: usize = 256 * 1024;
+const MAX_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, Default)]
+struct LogFilter {
+    app_id: Option<String>,
+    level: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+}
+
+#[derive(Serialize)]
+pub(super) struct LogRow {
+    id: i64,
+    app_id: String,
+    log_type: String,
+    message: String,
+    details: Option<String>,
+    source: Option<String>,
+    timestamp: String,
+}
+
+#[derive(Serialize)]
+struct SystemEventRow {
+    id: i64,
+    event_type: String,
+    description: Option<String>,
+    metadata: Option<String>,
+    timestamp: String,
+}
+
+#[derive(Serialize)]
+struct LogBundle {
+    client_id: String,
+    generated_at: String,
+    filter: Value,
+    app_logs: Vec<LogRow>,
+    system_events: Vec<SystemEventRow>,
+}
+
+/// `{"cmd": "collect_logs", "id": "...", "app_id": "gdoc", "level": "ERROR",
+///   "since": "2026-01-01T00:00:00Z", "until": "...",
+///   "upload_url": "https://.../logs", "chunk_size": 262144}`
+///
+/// Siempre se pide consentimiento al usuario local: el servidor no puede omitirlo.
+/// `upload_url` debe apuntar al mismo host que la conexión WSS; sin ella el paquete
+/// gzip se envía en trozos base64 (`log_chunk`) por el socket.
+pub fn handle(json: &Value, session: &RemoteSession) {
+    let request_id = json["id"].clone();
+    let sender = session.sender.clone();
+    let app_handle = session.app_handle.clone();
+    let connection_id = session.connection_id;
+    let json = json.clone();
+
+    tauri::async_runtime::spawn(async move {
+        let outcome = collect(&json, &request_id, &app_handle, connection_id, &sender).await;
+        if let Err(e) = &outcome {
+            println!("❌ [Remote] collect_logs falló: {}", e);
+        }
+        sender.result(&request_id, "collect_logs", outcome);
+    });
+}
+
+async fn collect(
+    json: &Value,
+    request_id: &Value,
+    app_handle: &AppHandle,
+    connection_id: i64,
+    sender: &RemoteSender,
+) -> Result<Value, String> {
+    let filter = LogFilter {
+        app_id: json["app_id"].as_str().map(String::from),
+        level: json["level"].as_str().map(|s| s.to_uppercase()),
+        since: json["since"].as_str().map(normalize_timestamp),
+        until: json["until"].as_str().map(normalize_timestamp),
+    };
+
+    // Se valida antes de preguntar: no tiene sentido pedir permiso para un destino ajeno
+    let upload_url = json["upload_url"]
+        .as_str()
+        .map(|upload_url| check_upload_url(app_handle, connection_id, upload_url))
+        .transpose()?;
+
+    sender.progress(request_id, "collect_logs", "awaiting_consent", None);
+    let handle = app_handle.clone();
+    let message = format!(
+        "El servidor solicita los registros de {}.\n¿Desea enviarlos?",
+        filter.app_id.as_deref().unwrap_or("todas las aplicaciones")
+    );
+    let accepted = tauri::async_runtime::spawn_blocking(move || {
+        ask_consent(&handle, "Solicitud de registros", &message)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    if !accepted {
+        return Err("El usuario rechazó el envío de registros.".into());
+    }
+
+    sender.progress(request_id, "collect_logs", "collecting", None);
+    let handle = app_handle.clone();
+    let client_id = sender.client_id.clone();
+    let (archive, app_log_count, event_count) =
+        tauri::async_runtime::spawn_blocking(move || build_archive(&handle, &filter, client_id))
+            .await
+            .map_err(|e| e.to_string())??;
+
+    let summary = serde_json::json!({
+        "app_logs": app_log_count,
+        "system_events": event_count,
+        "compressed_bytes": archive.len(),
+        "encoding": "gzip",
+    });
+
+    match upload_url {
+        Some(upload_url) => {
+            sender.progress(
+                request_id,
+                "collect_logs",
+                "uploading",
+                Some(upload_url.to_string()),
+            );
+            upload(upload_url, archive, &sender.client_id, request_id).await?;
+        }
+        None => {
+            let chunk_size = json["chunk_size"]
+                .as_u64()
+                .map(|n| (n as usize).clamp(1024, MAX_CHUNK_SIZE))
+                .unwrap_or(DEFAULT_CHUNK_SIZE);
+            stream_chunks(
+                "log_chunk",
+                "gzip+base64",
+                &archive,
+                chunk_size,
+                request_id,
+                sender,
+            );
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Acepta RFC 3339 y lo pasa al formato de `CURRENT_TIMESTAMP` de SQLite (UTC).
+fn normalize_timestamp(value: &str) -> String {
+    match chrono::DateTime::parse_from_rfc3339(value) {
+        Ok(dt) => dt
+            .with_timezone(&chrono::Utc)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string(),
+        Err(_) => value.to_string(),
+    }
+}
+
+fn build_archive(
+    app_handle: &AppHandle,
+    filter: &LogFilter,
+    client_id: String,
+) -> Result<(Vec<u8>, usize, usize), String> {
+    let (app_logs, system_events) = {
+        let state = app_handle.state::<DbState>();
+        let conn = state.0.lock().unwrap();
+        (
+            query_app_logs(&conn, filter)?,
+            query_system_events(&conn, filter)?,
+        )
+    };
+
+    let counts = (app_logs.len(), system_events.len());
+    let bundle = LogBundle {
+        client_id,
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        filter: serde_json::json!({
+            "app_id": filter.app_id,
+            "level": filter.level,
+            "since": filter.since,
+            "until": filter.until,
+        }),
+        app_logs,
+        system_events,
+    };
+
+    let json = serde_json::to_vec(&bundle).map_err(|e| e.to_string())?;
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&json).map_err(|e| e.to_string())?;
+    let archive = encoder.finish().map_err(|e| e.to_string())?;
+
+    Ok((archive, counts.0, counts.1))
+}
+
+fn query_app_logs(conn: &rusqlite::Connection, filter: &LogFilter) -> Result<Vec<LogRow>, String> {
+    let mut clauses = Vec::new();
+    let mut params: Vec<String> = Vec::new();
+
+    if let Some(app_id) = &filter.app_id {
+        params.push(app_id.clone());
+        clauses.push(format!("app_id = ?{}", params.len()));
+    }
+    if let Some(level) = &filter.level {
+        params.push(level.clone());
+        clauses.push(format!("log_type = ?{}", params.len()));
+    }
+    push_time_range(filter, &mut clauses, &mut params);
+
+    let sql = format!(
+        "SELECT id, app_id, log_type, message, details, source, timestamp FROM app_logs {} ORDER BY id ASC LIMIT {}",
+        where_clause(&clauses),
+        MAX_ROWS_PER_TABLE
+    );
+
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(params.iter()), map_log_row)
+        .map_err(|e| e.to_string())?;
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Últimos errores registrados por las apps, del más reciente al más antiguo.
+pub(super) fn recent_errors(
+    conn: &rusqlite::Connection,
+    limit: i64,
+) -> Result<Vec<LogRow>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, app_id, log_type, message, details, source, timestamp FROM app_logs
+             WHERE log_type = 'ERROR' ORDER BY id DESC LIMIT ?1",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([limit], map_log_row)
+        .map_err(|e| e.to_string())?;
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+fn map_log_row(row: &rusqlite::Row) -> rusqlite::Result<LogRow> {
+    Ok(LogRow {
+        id: row.get(0)?,
+        app_id: row.get(1)?,
+        log_type: row.get(2)?,
+        message: row.get(3)?,
+        details: row.get(4).unwrap_or(None),
+        source: row.get(5).unwrap_or(None),
+        timestamp: row.get(6)?,
+    })
+}
+
+fn query_system_events(
+    conn: &rusqlite::Connection,
+    filter: &LogFilter,
+) -> Result<Vec<SystemEventRow>, String> {
+    let mut clauses = Vec::new();
+    let mut params: Vec<String> = Vec::new();
+    push_time_range(filter, &mut clauses, &mut params);
+
+    let sql = format!(
+        "SELECT id, event_type, description, metadata, timestamp FROM system_events {} ORDER BY id ASC LIMIT {}",
+        where_clause(&clauses),
+        MAX_ROWS_PER_TABLE
+    );
+
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(params.iter()), |row| {
+            Ok(SystemEventRow {
+                id: row.get(0)?,
+                event_type: row.get(1)?,
+                description: row.get(2).unwrap_or(None),
+                metadata: row.get(3).unwrap_or(None),
+                timestamp: row.get(4)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+fn push_time_range(filter: &LogFilter, clauses: &mut Vec<String>, params: &mut Vec<String>) {
+    if let Some(since) = &filter.since {
+        params.push(since.clone());
+        clauses.push(format!("timestamp >= ?{}", params.len()));
+    }
+    if let Some(until) = &filter.until {
+        params.push(until.clone());
+        clauses.push(format!("timestamp <= ?{}", params.len()));
+    }
+}
+
+fn where_clause(clauses: &[String]) -> String {
+    if clauses.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", clauses.join(" AND "))
+    }
+}
+
+/// La subida sólo se acepta por HTTPS y hacia el host del servidor conectado:
+/// el servidor no puede desviar los registros a un tercero.
+fn check_upload_url(
+    app_handle: &AppHandle,
+    connection_id: i64,
+    upload_url: &str,
+) -> Result<url::Url, String> {
+    let url = url::Url::parse(upload_url).map_err(|e| e.to_string())?;
+    if url.scheme() != "https" {
+        return Err("La subida de registros sólo se permite por HTTPS.".into());
+    }
+
+    let server_host = app_handle
+        .state::<ConnectionManager>()
+        .ws_url(connection_id)
+        .and_then(|ws_url| url::Url::parse(&ws_url).ok())
+        .and_then(|ws_url| ws_url.host_str().map(str::to_ascii_lowercase))
+        .ok_or("No se conoce el host de la conexión activa.")?;
+    if !url
+        .host_str()
+        .is_some_and(|host| host.eq_ignore_ascii_case(&server_host))
+    {
+        return Err(format!(
+            "La subida de registros sólo se permite al servidor conectado ({}).",
+            server_host
+        ));
+    }
+    Ok(url)
+}
+
+async fn upload(
+    url: url::Url,
+    archive: Vec<u8>,
+    client_id: &str,
+    request_id: &Value,
+) -> Result<(), String> {
+    let client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .timeout(Duration::from_secs(120))
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let resp = client
+        .post(url)
+        .header("Content-Type", "application/gzip")
+        .header("X-Sandra-Client", client_id)
+        .header(
+            "X-Sandra-Request",
+            request_id.as_str().unwrap_or_default().to_string(),
+        )
+        .body(archive)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if resp.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("El servidor rechazó la subida: {}", resp.status()))
+    }
+}
+
+/// Envía `data` en trozos base64 de tipo `kind`. Los trozos no son duraderos: si la
+/// sesión se corta, el servidor debe volver a pedirlos.
+pub(super) fn stream_chunks(
+    kind: &str,
+    encoding: &str,
+    data: &[u8],
+    chunk_size: usize,
+    request_id: &Value,
+    sender: &RemoteSender,
+) -> usize {
+    let total = data.len().div_ceil(chunk_size).max(1);
+    for (index, chunk) in data.chunks(chunk_size).enumerate() {
+        sender.send(
+            kind,
+            serde_json::json!({
+                "request_id": request_id,
+                "index": index,
+                "total": total,
+                "encoding": encoding,
+                "data": general_purpose::STANDARD.encode(chunk),
+            }),
+        );
+    }
+    total
+}
@@ -0,0 +1,593 @@
+pub mod apps;
+pub mod diagnostics;
+pub mod logs;
+pub mod outbox;
+pub mod power;
+pub mod telemetry;
+
+use futures_util::{SinkExt, StreamExt};
+use native_tls::TlsConnector;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{mpsc, Notify};
+
+use tokio_tungstenite::{connect_async_tls_with_config, tungstenite::protocol::Message, Connector};
+
+use crate::connection_manager::{ConnectionManager, ConnectionState};
+use crate::runtime_config::{self, RuntimeConfig, RuntimeConfigStore};
+use crate::storage::DbState;
+use tauri::Manager;
+
+#[derive(Serialize)]
+struct ClientMessage {
+    // Si en Go el struct usa `json:"message"`, usa camelCase.
+    // Si no tiene tags en Go, usa PascalCase para que coincida con "Message".
+    #[serde(rename = "message")]
+    message: String,
+}
+
+/// Sobre común de todo lo que el cliente envía al servidor tras el handshake.
+#[derive(Serialize)]
+pub struct OutboundMessage<T: Serialize> {
+    pub message_id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub client_id: String,
+    pub timestamp: String,
+    pub payload: T,
+}
+
+/// Canal de salida de una sesión WSS. Es clonable para que las tareas en segundo
+/// plano (telemetría, comandos largos) puedan responder cuando terminen.
+/// Si la sesión ya se cerró, los mensajes duraderos se guardan en el outbox.
+#[derive(Clone)]
+pub struct RemoteSender {
+    client_id: String,
+    connection_id: i64,
+    app_handle: AppHandle,
+    outbound: mpsc::UnboundedSender<outbox::Outgoing>,
+}
+
+impl RemoteSender {
+    pub fn send<T: Serialize>(&self, kind: &str, payload: T) {
+        let envelope = OutboundMessage {
+            message_id: uuid::Uuid::new_v4().to_string(),
+            kind: kind.to_string(),
+            client_id: self.client_id.clone(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            payload,
+        };
+
+        match serde_json::to_string(&envelope) {
+            Ok(body) => {
+                let message = outbox::Outgoing {
+                    message_id: envelope.message_id,
+                    kind: envelope.kind,
+                    body,
+                };
+                if let Err(mpsc::error::SendError(message)) = self.outbound.send(message) {
+                    outbox::persist(&self.app_handle, self.connection_id, &message);
+                }
+            }
+            Err(e) => eprintln!("❌ Error serializando mensaje '{}': {}", kind, e),
+        }
+    }
+
+    /// Avance de un comando remoto de larga duración.
+    pub fn progress(&self, request_id: &Value, cmd: &str, stage: &str, detail: Option<String>) {
+        self.send(
+            "command_progress",
+            serde_json::json!({
+                "request_id": request_id,
+                "cmd": cmd,
+                "stage": stage,
+                "detail": detail,
+            }),
+        );
+    }
+
+    /// Resultado final de un comando remoto.
+    pub fn result(&self, request_id: &Value, cmd: &str, outcome: Result<Value, String>) {
+        let (ok, data, error) = match outcome {
+            Ok(data) => (true, data, None),
+            Err(e) => (false, Value::Null, Some(e)),
+        };
+        self.send(
+            "command_result",
+            serde_json::json!({
+                "request_id": request_id,
+                "cmd": cmd,
+                "ok": ok,
+                "data": data,
+                "error": error,
+            }),
+        );
+    }
+}
+
+/// Estado de una sesión WSS abierta, compartido por los manejadores de comandos.
+pub struct RemoteSession {
+    pub app_handle: AppHandle,
+    pub connection_id: i64,
+    pub sender: RemoteSender,
+    /// `None` = telemetría periódica desactivada por el servidor.
+    telemetry_interval: Option<Duration>,
+    telemetry_changed: bool,
+}
+
+impl RemoteSession {
+    /// Lanza la construcción del reporte en un hilo bloqueante y lo envía al terminar.
+    fn push_telemetry(&self) {
+        let app_handle = self.app_handle.clone();
+        let sender = self.sender.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            let report = telemetry::build_report(&app_handle, sender.client_id.clone());
+            sender.send("telemetry", report);
+        });
+    }
+}
+
+/// Pide permiso al usuario local antes de enviar datos sensibles al servidor.
+/// Bloqueante: llamar desde `spawn_blocking`.
+pub fn ask_consent(app_handle: &AppHandle, title: &str, message: &str) -> bool {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+    app_handle
+        .dialog()
+        .message(message)
+        .title(title)
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Permitir".to_string(),
+            "Rechazar".to_string(),
+        ))
+        .blocking_show()
+}
+
+fn load_client_id(app_handle: &AppHandle) -> String {
+    let state = app_handle.state::<DbState>();
+    let conn = state.0.lock().unwrap();
+    crate::storage::get_or_create_client_id(&conn).unwrap_or_else(|e| {
+        eprintln!("❌ No se pudo obtener el client_id: {}", e);
+        String::new()
+    })
+}
+
+fn telemetry_ticker(interval: Option<Duration>) -> tokio::time::Interval {
+    // Sin telemetría dejamos un ticker "dormido" para no complicar el select!
+    let period = interval.unwrap_or(Duration::from_secs(24 * 60 * 60));
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    ticker
+}
+
+/// Política de reconexión por perfil de conexión.
+/// El retardo crece exponencialmente desde `initial_delay_ms` hasta `max_delay_ms`,
+/// con un jitter proporcional para evitar que todos los clientes reintenten a la vez
+/// (el jitter nunca lleva el retardo por encima de `max_delay_ms`).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ReconnectPolicy {
+    pub initial_delay_ms: u64,
+    pub multiplier: f64,
+    pub max_delay_ms: u64,
+    /// Fracción del retardo usada como jitter (0.2 = ±20%).
+    pub jitter: f64,
+    /// `None` reintenta indefinidamente.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay_ms: 1_000,
+            multiplier: 2.0,
+            max_delay_ms: 60_000,
+            jitter: 0.2,
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Retardo antes del reintento número `attempt` (empezando en 1).
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(32) as i32;
+        let base = self.initial_delay_ms as f64 * self.multiplier.max(1.0).powi(exponent);
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = if jitter > 0.0 {
+            1.0 + rand::thread_rng().gen_range(-jitter..=jitter)
+        } else {
+            1.0
+        };
+
+        // El tope se aplica después del jitter: `max_delay_ms` nunca se supera
+        let delay = (base * factor).min(self.max_delay_ms as f64);
+        Duration::from_millis(delay.max(0.0) as u64)
+    }
+
+    pub fn exhausted(&self, attempt: u32) -> bool {
+        matches!(self.max_attempts, Some(max) if attempt >= max)
+    }
+
+    /// Rechaza políticas que reintentarían en bucle (`initial_delay_ms: 0`) o sin sentido.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.initial_delay_ms == 0 {
+            return Err("initial_delay_ms debe ser mayor que 0".into());
+        }
+        if self.max_delay_ms < self.initial_delay_ms {
+            return Err("max_delay_ms no puede ser menor que initial_delay_ms".into());
+        }
+        if !(1.0..=10.0).contains(&self.multiplier) {
+            return Err("multiplier debe estar entre 1 y 10".into());
+        }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            return Err("jitter debe estar entre 0 y 1".into());
+        }
+        if self.max_attempts == Some(0) {
+            return Err("max_attempts debe ser mayor que 0".into());
+        }
+        Ok(())
+    }
+}
+
+// Modified signature to take AppHandle for emitting events
+pub async fn start_remote_listener(
+    ws_url: String,
+    app_handle: AppHandle,
+    connection_id: i64,
+    policy: Option<ReconnectPolicy>,
+    wake: Arc<Notify>,
+) {
+    let mut tls_builder = TlsConnector::builder();
+    tls_builder.danger_accept_invalid_certs(true);
+    tls_builder.min_protocol_version(Some(native_tls::Protocol::Tlsv12));
+
+    let connector = Connector::NativeTls(tls_builder.build().unwrap());
+    let manager = app_handle.state::<ConnectionManager>();
+    // Fallos consecutivos desde la última conexión exitosa
+    let mut attempt_count: u32 = 0;
+
+    loop {
+        println!("🔄 Intentando conectar a: {}", ws_url);
+        manager.transition(
+            &app_handle,
+            connection_id,
+            ConnectionState::Connecting {
+                attempt: attempt_count,
+            },
+            None,
+        );
+
+        let failure_reason = match connect_async_tls_with_config(
+            &ws_url,
+            None,
+            false,
+            Some(connector.clone()),
+        )
+        .await
+        {
+            Ok((mut ws_stream, _)) => {
+                println!("📡 Conectado exitosamente");
+                attempt_count = 0; // Reset on success
+                manager.transition(&app_handle, connection_id, ConnectionState::Connected, None);
+                // Escrituras /v1/ que quedaron encoladas mientras no había conexión
+                crate::proxy_handler::spawn_replay(&app_handle, connection_id);
+
+                let initial_payload = ClientMessage {
+                    message: "Initial Handshake from Sandra OS".to_string(),
+                };
+
+                if let Ok(json_str) = serde_json::to_string(&initial_payload) {
+                    if let Err(e) = ws_stream.send(Message::Text(json_str.into())).await {
+                        eprintln!("❌ Error enviando mensaje inicial: {}", e);
+                    } else {
+                        println!("🚀 Mensaje inicial enviado a Go");
+                    }
+                }
+
+                run_session(ws_stream, &app_handle, connection_id).await
+            }
+            Err(e) => {
+                eprintln!("❌ Error de handshake: {}", e);
+                e.to_string()
+            }
+        };
+
+        attempt_count += 1;
+
+        // Sin política propia se usa la vigente, que el servidor puede cambiar en caliente
+        let policy = policy
+            .clone()
+            .unwrap_or_else(|| runtime_config::current(&app_handle).reconnect);
+
+        if policy.exhausted(attempt_count) {
+            println!(
+                "⚠️ {} intentos fallidos. Se abandona la reconexión automática.",
+                attempt_count
+            );
+            manager.transition(
+                &app_handle,
+                connection_id,
+                ConnectionState::Failed {
+                    reason: failure_reason,
+                },
+                None,
+            );
+            return;
+        }
+
+        let delay = policy.delay_for(attempt_count);
+        println!(
+            "⏳ Reintento #{} en {} ms",
+            attempt_count,
+            delay.as_millis()
+        );
+        manager.transition(
+            &app_handle,
+            connection_id,
+            ConnectionState::reconnecting(attempt_count, delay),
+            Some(failure_reason),
+        );
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = wake.notified() => println!("⏩ Reintento forzado por el usuario"),
+        }
+    }
+}
+
+/// Bucle de una sesión abierta: comandos entrantes, mensajes salientes y telemetría.
+/// Devuelve el motivo por el que terminó.
+async fn run_session<S>(
+    ws_stream: tokio_tungstenite::WebSocketStream<S>,
+    app_handle: &AppHandle,
+    connection_id: i64,
+) -> String
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    let (mut ws_sink, mut ws_source) = ws_stream.split();
+    let (outbound, mut outbound_rx) = mpsc::unbounded_channel::<outbox::Outgoing>();
+
+    // Lo pendiente de sesiones anteriores sale antes que cualquier mensaje nuevo
+    if let Err(e) = flush_outbox(&mut ws_sink, app_handle, connection_id).await {
+        eprintln!("❌ Error vaciando la cola de salida: {}", e);
+        return e;
+    }
+
+    let mut session = RemoteSession {
+        app_handle: app_handle.clone(),
+        connection_id,
+        sender: RemoteSender {
+            client_id: load_client_id(app_handle),
+            connection_id,
+            app_handle: app_handle.clone(),
+            outbound,
+        },
+        telemetry_interval: None,
+        telemetry_changed: false,
+    };
+
+    let mut config_rx = app_handle.state::<RuntimeConfigStore>().subscribe();
+    session.telemetry_interval = config_rx.borrow_and_update().telemetry_interval();
+
+    // Primer reporte nada más conectar; después, según el intervalo vigente
+    session.push_telemetry();
+    let mut telemetry_tick = telemetry_ticker(session.telemetry_interval);
+
+    let reason = loop {
+        tokio::select! {
+            msg = ws_source.next() => match msg {
+                Some(Ok(Message::Text(text))) => process_command(&text, &mut session),
+                Some(Ok(Message::Close(_))) | None => {
+                    println!("🔌 Servidor cerró la conexión.");
+                    break "El servidor cerró la conexión".to_string();
+                }
+                Some(Err(e)) => break e.to_string(),
+                Some(Ok(_)) => {}
+            },
+            Some(out) = outbound_rx.recv() => {
+                if let Err(e) = ws_sink.send(Message::Text(out.body.clone().into())).await {
+                    eprintln!("❌ Error enviando al servidor: {}", e);
+                    outbox::persist(app_handle, connection_id, &out);
+                    break e.to_string();
+                }
+            }
+            _ = telemetry_tick.tick(), if session.telemetry_interval.is_some() => {
+                session.push_telemetry();
+            }
+            Ok(()) = config_rx.changed() => {
+                let interval = config_rx.borrow_and_update().telemetry_interval();
+                if interval != session.telemetry_interval {
+                    session.telemetry_interval = interval;
+                    session.telemetry_changed = true;
+                }
+            }
+        }
+
+        if session.telemetry_changed {
+            session.telemetry_changed = false;
+            telemetry_tick = telemetry_ticker(session.telemetry_interval);
+        }
+    };
+
+    // Lo que quedó sin enviar pasa a la cola; los envíos posteriores de tareas
+    // en segundo plano fallan en el canal cerrado y también acaban allí.
+    outbound_rx.close();
+    while let Ok(out) = outbound_rx.try_recv() {
+        outbox::persist(app_handle, connection_id, &out);
+    }
+
+    reason
+}
+
+/// Envía en orden los mensajes encolados mientras no había conexión.
+/// Cada mensaje se borra sólo tras escribirse en el socket; si se corta a mitad,
+/// el servidor puede recibirlo dos veces y debe deduplicar por `message_id`.
+async fn flush_outbox<S>(
+    ws_sink: &mut S,
+    app_handle: &AppHandle,
+    connection_id: i64,
+) -> Result<(), String>
+where
+    S: futures_util::Sink<Message, Error = tokio_tungstenite::tungstenite::Error> + Unpin,
+{
+    let mut flushed = 0;
+    loop {
+        let batch = outbox::next_batch(app_handle, connection_id)?;
+        if batch.is_empty() {
+            break;
+        }
+        for (id, body) in batch {
+            ws_sink
+                .send(Message::Text(body.into()))
+                .await
+                .map_err(|e| e.to_string())?;
+            outbox::acknowledge(app_handle, id)?;
+            flushed += 1;
+        }
+    }
+
+    if flushed > 0 {
+        println!("📤 [Outbox] {} mensajes pendientes enviados", flushed);
+    }
+    Ok(())
+}
+
+fn process_command(text: &str, session: &mut RemoteSession) {
+    let app_handle = &session.app_handle;
+    if let Ok(json) = serde_json::from_str::<Value>(text) {
+        match json["cmd"].as_str() {
+            Some(
+                cmd @ ("reboot" | "shutdown" | "logoff" | "cancel_power_action" | "power_status"),
+            ) => power::handle(cmd, &json, session),
+            Some("status") => {
+                // Respond or log
+            }
+            Some("welcome") => {
+                let _ = app_handle.emit("server-welcome", json);
+            }
+            // {"cmd": "set_telemetry_interval", "seconds": 120}  (0 = desactivar)
+            Some("set_telemetry_interval") => {
+                let seconds = json["seconds"].as_u64().unwrap_or(0);
+                session.telemetry_interval = telemetry::parse_interval(seconds);
+                session.telemetry_changed = true;
+                println!(
+                    "📊 Intervalo de telemetría: {:?}",
+                    session.telemetry_interval
+                );
+                session.sender.send(
+                    "telemetry_interval",
+                    serde_json::json!({
+                        "seconds": session.telemetry_interval.map(|d| d.as_secs()).unwrap_or(0)
+                    }),
+                );
+            }
+            Some("telemetry") => session.push_telemetry(),
+            // {"cmd": "config", "id": "...", "config": {"version": 3, "telemetry_interval_secs": 120, ...}}
+            Some("config") => {
+                let outcome = serde_json::from_value::<RuntimeConfig>(json["config"].clone())
+                    .map_err(|e| format!("Configuración inválida: {}", e))
+                    .and_then(|config| {
+                        app_handle
+                            .state::<RuntimeConfigStore>()
+                            .apply(app_handle, config)
+                    })
+                    .map(|applied| serde_json::json!({ "version": applied.version }));
+
+                if let Err(e) = &outcome {
+                    println!("❌ [Remote] config rechazada: {}", e);
+                }
+                session.sender.result(&json["id"], "config", outcome);
+            }
+            Some(
+                cmd @ ("install_app" | "update_app" | "remove_app" | "list_apps" | "open_app"),
+            ) => apps::handle(cmd, &json, session),
+            Some("collect_logs") => logs::handle(&json, session),
+            Some("diagnostics") => diagnostics::handle(&json, session),
+            _ => println!("📩 Mensaje recibido: {}", text),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(jitter: f64) -> ReconnectPolicy {
+        ReconnectPolicy {
+            initial_delay_ms: 1_000,
+            multiplier: 2.0,
+            max_delay_ms: 10_000,
+            jitter,
+            max_attempts: Some(5),
+        }
+    }
+
+    #[test]
+    fn delay_grows_exponentially_up_to_the_cap() {
+        let policy = policy(0.0);
+        assert_eq!(policy.delay_for(1), Duration::from_millis(1_000));
+        assert_eq!(policy.delay_for(2), Duration::from_millis(2_000));
+        assert_eq!(policy.delay_for(4), Duration::from_millis(8_000));
+        assert_eq!(policy.delay_for(5), Duration::from_millis(10_000));
+        assert_eq!(policy.delay_for(u32::MAX), Duration::from_millis(10_000));
+    }
+
+    #[test]
+    fn jitter_never_exceeds_max_delay() {
+        let policy = policy(0.5);
+        for _ in 0..500 {
+            let delay = policy.delay_for(10);
+            assert!(delay <= Duration::from_millis(10_000), "{:?}", delay);
+            assert!(delay >= Duration::from_millis(5_000), "{:?}", delay);
+        }
+        for _ in 0..500 {
+            let delay = policy.delay_for(1);
+            assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_millis(1_500));
+        }
+    }
+
+    #[test]
+    fn exhausted_after_max_attempts() {
+        let policy = policy(0.0);
+        assert!(!policy.exhausted(4));
+        assert!(policy.exhausted(5));
+        assert!(!ReconnectPolicy::default().exhausted(u32::MAX));
+    }
+
+    #[test]
+    fn validate_rejects_hot_loops_and_nonsense() {
+        assert!(ReconnectPolicy::default().validate().is_ok());
+        assert!(policy(0.2).validate().is_ok());
+
+        let invalid = [
+            ReconnectPolicy {
+                initial_delay_ms: 0,
+                ..policy(0.0)
+            },
+            ReconnectPolicy {
+                max_delay_ms: 500,
+                ..policy(0.0)
+            },
+            ReconnectPolicy {
+                multiplier: 0.5,
+                ..policy(0.0)
+            },
+            policy(1.5),
+            ReconnectPolicy {
+                max_attempts: Some(0),
+                ..policy(0.0)
+            },
+        ];
+        for policy in invalid {
+            assert!(policy.validate().is_err(), "{:?}", policy);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use crate::storage::DbState;
+use rusqlite::Connection;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+/// Límite de mensajes pendientes por conexión; al superarlo se descartan los más antiguos.
+pub const MAX_QUEUED_MESSAGES: i64 = 1_000;
+/// Límite de bytes pendientes por conexión (de telemetría sólo queda la última).
+pub const MAX_QUEUED_BYTES: i64 = 5 * 1024 * 1024;
+/// Mensajes leídos de la BD por cada vuelta del vaciado.
+pub const FLUSH_BATCH: i64 = 100;
+
+/// Sólo se conservan los mensajes con valor tras la reconexión. El progreso
+/// de comandos o los trozos de logs no tienen sentido fuera de su sesión.
+pub fn is_durable(kind: &str) -> bool {
+    matches!(kind, "telemetry" | "command_result" | "power_status")
+}
+
+/// Tipos de los que sólo interesa el último valor: cada uno nuevo sustituye al que
+/// estuviera en cola, así no desplazan del límite a los `command_result`.
+fn keeps_latest_only(kind: &str) -> bool {
+    kind == "telemetry"
+}
+
+/// Mensaje ya serializado, listo para el socket o para la cola.
+#[derive(Debug)]
+pub struct Outgoing {
+    pub message_id: String,
+    pub kind: String,
+    pub body: String,
+}
+
+/// Resumen de un mensaje en cola (sin el cuerpo) para `get_outbound_queue`.
+#[derive(Serialize)]
+pub struct QueuedMessage {
+    pub id: i64,
+    pub message_id: String,
+    pub connection_id: i64,
+    pub kind: String,
+    pub size_bytes: i64,
+    pub created_at: String,
+}
+
+/// Guarda el mensaje si es duradero. Devuelve `false` si se descartó o ya estaba en cola.
+pub fn persist(app_handle: &AppHandle, connection_id: i64, message: &Outgoing) -> bool {
+    if !is_durable(&message.kind) {
+        return false;
+    }
+
+    let state = app_handle.state::<DbState>();
+    let conn = state.0.lock().unwrap();
+    match enqueue(&conn, connection_id, message) {
+        Ok(queued) => {
+            if queued {
+                println!(
+                    "📥 [Outbox] '{}' en cola para la conexión {}",
+                    message.kind, connection_id
+                );
+            }
+            queued
+        }
+        Err(e) => {
+            eprintln!("❌ [Outbox] No se pudo encolar '{}': {}", message.kind, e);
+            false
+        }
+    }
+}
+
+pub fn enqueue(conn: &Connection, connection_id: i64, message: &Outgoing) -> Result<bool, String> {
+    if keeps_latest_only(&message.kind) {
+        conn.execute(
+            "DELETE FROM outbound_queue WHERE connection_id = ?1 AND kind = ?2 AND message_id != ?3",
+            rusqlite::params![connection_id, message.kind, message.message_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    let inserted = conn
+        .execute(
+            "INSERT OR IGNORE INTO outbound_queue (message_id, connection_id, kind, body) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![message.message_id, connection_id, message.kind, message.body],
+        )
+        .map_err(|e| e.to_string())?;
+
+    trim(conn, connection_id)?;
+    Ok(inserted > 0)
+}
+
+/// Aplica los límites de tamaño descartando primero lo más antiguo.
+fn trim(conn: &Connection, connection_id: i64) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM outbound_queue WHERE connection_id = ?1 AND id NOT IN (
+            SELECT id FROM outbound_queue WHERE connection_id = ?1 ORDER BY id DESC LIMIT ?2
+        )",
+        rusqlite::params![connection_id, MAX_QUEUED_MESSAGES],
+    )
+    .map_err(|e| e.to_string())?;
+
+    loop {
+        let total: i64 = conn
+            .query_row(
+                "SELECT COALESCE(SUM(LENGTH(body)), 0) FROM outbound_queue WHERE connection_id = ?1",
+                [connection_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+
+        if total <= MAX_QUEUED_BYTES {
+            return Ok(());
+        }
+
+        let removed = conn
+            .execute(
+                "DELETE FROM outbound_queue WHERE id = (
+                    SELECT id FROM outbound_queue WHERE connection_id = ?1 ORDER BY id ASC LIMIT 1
+                )",
+                [connection_id],
+            )
+            .map_err(|e| e.to_string())?;
+        if removed == 0 {
+            return Ok(());
+        }
+    }
+}
+
+/// Siguiente lote pendiente en orden de llegada: `(id, cuerpo)`.
+pub fn next_batch(
+    app_handle: &AppHandle,
+    connection_id: i64,
+) -> Result<Vec<(i64, String)>, String> {
+    let state = app_handle.state::<DbState>();
+    let conn = state.0.lock().unwrap();
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, body FROM outbound_queue WHERE connection_id = ?1 ORDER BY id ASC LIMIT ?2",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map(rusqlite::params![connection_id, FLUSH_BATCH], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .map_err(|e| e.to_string())?;
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Borra un mensaje ya entregado al socket.
+pub fn acknowledge(app_handle: &AppHandle, id: i64) -> Result<(), String> {
+    let state = app_handle.state::<DbState>();
+    let conn = state.0.lock().unwrap();
+    conn.execute("DELETE FROM outbound_queue WHERE id = ?1", [id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub fn list(conn: &Connection, connection_id: Option<i64>) -> Result<Vec<QueuedMessage>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, message_id, connection_id, kind, LENGTH(body), created_at FROM outbound_queue
+             WHERE ?1 IS NULL OR connection_id = ?1 ORDER BY id ASC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map([connection_id], |row| {
+            Ok(QueuedMessage {
+                id: row.get(0)?,
+                message_id: row.get(1)?,
+                connection_id: row.get(2)?,
+                kind: row.get(3)?,
+                size_bytes: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+pub fn clear(conn: &Connection, connection_id: Option<i64>) -> Result<usize, String> {
+    conn.execute(
+        "DELETE FROM outbound_queue WHERE ?1 IS NULL OR connection_id = ?1",
+        [connection_id],
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: &str, kind: &str) -> Outgoing {
+        Outgoing {
+            message_id: id.into(),
+            kind: kind.into(),
+            body: format!("{{\"id\":\"{}\"}}", id),
+        }
+    }
+
+    #[test]
+    fn only_the_latest_telemetry_is_kept() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::storage::init_tables(&conn).unwrap();
+
+        assert_eq!(
+            enqueue(&conn, 1, &message("r1", "command_result")),
+            Ok(true)
+        );
+        assert_eq!(enqueue(&conn, 1, &message("t1", "telemetry")), Ok(true));
+        assert_eq!(enqueue(&conn, 2, &message("t2", "telemetry")), Ok(true));
+        assert_eq!(enqueue(&conn, 1, &message("t3", "telemetry")), Ok(true));
+        // Un reintento del mismo mensaje no borra el que ya estaba
+        assert_eq!(enqueue(&conn, 1, &message("t3", "telemetry")), Ok(false));
+
+        let queued: Vec<String> = list(&conn, Some(1))
+            .unwrap()
+            .into_iter()
+            .map(|m| m.message_id)
+            .collect();
+        assert_eq!(queued, vec!["r1", "t3"]);
+        assert_eq!(list(&conn, Some(2)).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn audit_is_not_durable() {
+        assert!(is_durable("command_result"));
+        assert!(!is_durable("audit"));
+        assert!(!is_durable("command_progress"));
+    }
+}
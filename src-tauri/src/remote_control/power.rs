@@ -0,0 +1,66 @@
+use super::RemoteSession;
+use crate::power::{
+    PowerAction, PowerManager, PowerRequest, DEFAULT_POWER_DELAY, MIN_REMOTE_POWER_DELAY,
+};
+use serde_json::Value;
+use std::time::Duration;
+use tauri::Manager;
+
+/// Acciones de energía remotas.
+///
+/// `{"cmd": "reboot", "id": "...", "delay_seconds": 120, "reason": "Actualización", "dry_run": false}`
+/// (`delay_seconds` nunca baja de `MIN_REMOTE_POWER_DELAY`)
+/// `{"cmd": "cancel_power_action", "id": "..."}` / `{"cmd": "power_status", "id": "..."}`
+///
+/// Cada cambio de estado de la acción programada se reporta con `power_status`.
+pub fn handle(cmd: &str, json: &Value, session: &RemoteSession) {
+    let request_id = json["id"].clone();
+    let app_handle = &session.app_handle;
+    let power = app_handle.state::<PowerManager>();
+
+    let outcome = match cmd {
+        "cancel_power_action" => power
+            .cancel()
+            .and_then(|a| serde_json::to_value(a).map_err(|e| e.to_string())),
+        "power_status" => serde_json::to_value(power.status()).map_err(|e| e.to_string()),
+        other => match PowerAction::parse(other) {
+            Some(action) => {
+                // El servidor no puede saltarse la cuenta atrás visible
+                let delay = json["delay_seconds"]
+                    .as_u64()
+                    .map(Duration::from_secs)
+                    .unwrap_or(DEFAULT_POWER_DELAY)
+                    .max(MIN_REMOTE_POWER_DELAY);
+                let reason = json["reason"].as_str().map(String::from);
+                let dry_run = json["dry_run"].as_bool().unwrap_or(false);
+
+                let sender = session.sender.clone();
+                let observer_request = request_id.clone();
+                power
+                    .schedule(
+                        app_handle,
+                        PowerRequest {
+                            action,
+                            delay,
+                            reason,
+                            requested_by: "remote",
+                            dry_run,
+                        },
+                        Some(Box::new(move |status| {
+                            sender.send(
+                                "power_status",
+                                serde_json::json!({
+                                    "request_id": observer_request,
+                                    "status": status,
+                                }),
+                            );
+                        })),
+                    )
+                    .and_then(|a| serde_json::to_value(a).map_err(|e| e.to_string()))
+            }
+            None => Err(format!("Comando desconocido: {}", other)),
+        },
+    };
+
+    session.sender.result(&request_id, cmd, outcome);
+}
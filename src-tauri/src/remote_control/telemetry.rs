@@ -0,0 +1,80 @@
+use crate::commands::apps::{apps_dir, installed_app_version, query_all_apps};
+use crate::commands::monitor::{collect_system_stats, SystemStats};
+use crate::storage::DbState;
+use serde::Serialize;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+/// Evita que un servidor mal configurado sature el enlace.
+pub const MIN_TELEMETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Serialize)]
+pub struct InstalledApp {
+    pub app_id: String,
+    pub name: String,
+    pub installed: bool,
+    pub version: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TelemetryReport {
+    pub client_id: String,
+    pub container_version: String,
+    pub system: SystemStats,
+    pub apps: Vec<InstalledApp>,
+}
+
+/// Construye el reporte completo. Bloqueante (sysinfo + git), ejecutar con `spawn_blocking`.
+pub fn build_report(app_handle: &AppHandle, client_id: String) -> TelemetryReport {
+    let system = collect_system_stats();
+
+    let apps = installed_apps(app_handle);
+
+    TelemetryReport {
+        client_id,
+        container_version: app_handle.package_info().version.to_string(),
+        system,
+        apps,
+    }
+}
+
+/// Apps registradas en la BD con su estado real en disco y versión instalada.
+/// Bloqueante (git).
+pub fn installed_apps(app_handle: &AppHandle) -> Vec<InstalledApp> {
+    let apps = {
+        let state = app_handle.state::<DbState>();
+        let conn = state.0.lock().unwrap();
+        query_all_apps(&conn).unwrap_or_default()
+    };
+
+    let base_dir = apps_dir(app_handle).ok();
+    apps.into_iter()
+        .map(|app| {
+            let app_dir = base_dir.as_ref().map(|d| d.join(&app.app_id));
+            let installed = app_dir
+                .as_ref()
+                .map(|d| d.join("dist").join("index.html").exists())
+                .unwrap_or(false);
+            let version = match (&app_dir, installed) {
+                (Some(dir), true) => installed_app_version(dir),
+                _ => None,
+            };
+
+            InstalledApp {
+                app_id: app.app_id,
+                name: app.name,
+                installed,
+                version,
+            }
+        })
+        .collect()
+}
+
+/// Interpreta el intervalo pedido por el servidor: 0 desactiva el envío periódico.
+pub fn parse_interval(seconds: u64) -> Option<Duration> {
+    if seconds == 0 {
+        None
+    } else {
+        Some(Duration::from_secs(seconds).max(MIN_TELEMETRY_INTERVAL))
+    }
+}
@@ -0,0 +1,216 @@
+use crate::proxy_handler::ExternalPolicy;
+use crate::remote_control::telemetry::MIN_TELEMETRY_INTERVAL;
+use crate::remote_control::ReconnectPolicy;
+use crate::storage::DbState;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::watch;
+
+/// Clave de la tabla `config` donde se guarda el documento vigente.
+const CONFIG_KEY: &str = "runtime_config";
+const MAX_PROXY_TIMEOUT_SECS: u64 = 600;
+const MIN_PROXY_BODY_BYTES: u64 = 1024 * 1024;
+const MAX_PROXY_BODY_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Tiempos de espera del protocolo `sandra-app://`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProxyConfig {
+    /// Peticiones `/v1/` al servidor de la conexión.
+    pub remote_timeout_secs: u64,
+    /// Peticiones del proxy externo (`/external-proxy/`).
+    pub external_timeout_secs: u64,
+    /// Tamaño máximo de una respuesta remota o externa; si se supera se contesta 502.
+    pub max_body_bytes: u64,
+    /// Cabeceras que se reenvían al backend en `/v1/` (`x-*` = prefijo).
+    pub forward_headers: Vec<String>,
+    /// Destinos permitidos y bloqueados del proxy externo.
+    pub external_policy: ExternalPolicy,
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        Self {
+            remote_timeout_secs: 15,
+            external_timeout_secs: 20,
+            max_body_bytes: 64 * 1024 * 1024,
+            forward_headers: crate::proxy_handler::default_forward_headers(),
+            external_policy: ExternalPolicy::default(),
+        }
+    }
+}
+
+impl ProxyConfig {
+    pub fn remote_timeout(&self) -> Duration {
+        Duration::from_secs(self.remote_timeout_secs)
+    }
+
+    pub fn external_timeout(&self) -> Duration {
+        Duration::from_secs(self.external_timeout_secs)
+    }
+
+    pub fn max_body_bytes(&self) -> usize {
+        self.max_body_bytes as usize
+    }
+}
+
+/// Configuración que el servidor puede empujar con el comando `config`.
+/// Los campos ausentes toman su valor por defecto; los desconocidos se rechazan.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuntimeConfig {
+    /// Sólo se aceptan versiones mayores que la aplicada (0 = valores de fábrica).
+    pub version: u64,
+    /// Intervalo de telemetría periódica; 0 la desactiva.
+    pub telemetry_interval_secs: u64,
+    /// Política para las conexiones que no tienen una propia.
+    pub reconnect: ReconnectPolicy,
+    pub proxy: ProxyConfig,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            version: 0,
+            telemetry_interval_secs: 60,
+            reconnect: ReconnectPolicy::default(),
+            proxy: ProxyConfig::default(),
+        }
+    }
+}
+
+impl RuntimeConfig {
+    pub fn telemetry_interval(&self) -> Option<Duration> {
+        crate::remote_control::telemetry::parse_interval(self.telemetry_interval_secs)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.telemetry_interval_secs != 0
+            && self.telemetry_interval_secs < MIN_TELEMETRY_INTERVAL.as_secs()
+        {
+            return Err(format!(
+                "telemetry_interval_secs debe ser 0 o al menos {}",
+                MIN_TELEMETRY_INTERVAL.as_secs()
+            ));
+        }
+
+        self.reconnect
+            .validate()
+            .map_err(|e| format!("reconnect.{}", e))?;
+
+        for (name, value) in [
+            ("proxy.remote_timeout_secs", self.proxy.remote_timeout_secs),
+            (
+                "proxy.external_timeout_secs",
+                self.proxy.external_timeout_secs,
+            ),
+        ] {
+            if !(1..=MAX_PROXY_TIMEOUT_SECS).contains(&value) {
+                return Err(format!(
+                    "{} debe estar entre 1 y {}",
+                    name, MAX_PROXY_TIMEOUT_SECS
+                ));
+            }
+        }
+
+        if !(MIN_PROXY_BODY_BYTES..=MAX_PROXY_BODY_BYTES).contains(&self.proxy.max_body_bytes) {
+            return Err(format!(
+                "proxy.max_body_bytes debe estar entre {} y {}",
+                MIN_PROXY_BODY_BYTES, MAX_PROXY_BODY_BYTES
+            ));
+        }
+
+        for name in &self.proxy.forward_headers {
+            let name = name.strip_suffix('*').unwrap_or(name);
+            if !name.is_empty() && tauri::http::HeaderName::from_bytes(name.as_bytes()).is_err() {
+                return Err(format!(
+                    "proxy.forward_headers: cabecera inválida '{}'",
+                    name
+                ));
+            }
+        }
+
+        self.proxy.external_policy.validate()
+    }
+}
+
+/// Configuración vigente compartida. Los consumidores de larga duración
+/// (sesiones WSS) se suscriben para aplicar los cambios sin reiniciar.
+pub struct RuntimeConfigStore {
+    current: watch::Sender<RuntimeConfig>,
+}
+
+impl RuntimeConfigStore {
+    /// Carga el último documento guardado o los valores de fábrica.
+    pub fn load(conn: &rusqlite::Connection) -> Self {
+        let config = conn
+            .query_row(
+                "SELECT value FROM config WHERE key = ?1",
+                [CONFIG_KEY],
+                |row| row.get::<_, String>(0),
+            )
+            .ok()
+            .and_then(|json| serde_json::from_str::<RuntimeConfig>(&json).ok())
+            .filter(|config| config.validate().is_ok())
+            .unwrap_or_default();
+
+        println!(
+            "⚙️ Configuración en tiempo de ejecución v{}",
+            config.version
+        );
+        Self {
+            current: watch::channel(config).0,
+        }
+    }
+
+    pub fn current(&self) -> RuntimeConfig {
+        self.current.borrow().clone()
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<RuntimeConfig> {
+        self.current.subscribe()
+    }
+
+    /// Valida, guarda y publica un documento nuevo. Reenviar la versión ya
+    /// aplicada con el mismo contenido no es un error (el servidor puede reintentar).
+    pub fn apply(
+        &self,
+        app_handle: &AppHandle,
+        config: RuntimeConfig,
+    ) -> Result<RuntimeConfig, String> {
+        let current = self.current();
+        if config == current {
+            return Ok(current);
+        }
+        if config.version <= current.version {
+            return Err(format!(
+                "La versión {} no es posterior a la aplicada ({})",
+                config.version, current.version
+            ));
+        }
+        config.validate()?;
+
+        {
+            let state = app_handle.state::<DbState>();
+            let conn = state.0.lock().unwrap();
+            let json = serde_json::to_string(&config).map_err(|e| e.to_string())?;
+            conn.execute(
+                "INSERT INTO config (key, value) VALUES (?1, ?2)
+                 ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+                [CONFIG_KEY, &json],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+
+        println!("⚙️ Configuración v{} aplicada", config.version);
+        self.current.send_replace(config.clone());
+        let _ = app_handle.emit("runtime-config-changed", &config);
+        Ok(config)
+    }
+}
+
+/// Atajo para quien sólo tiene el `AppHandle`.
+pub fn current(app_handle: &AppHandle) -> RuntimeConfig {
+    app_handle.state::<RuntimeConfigStore>().current()
+}
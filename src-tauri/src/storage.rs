@@ -1,4 +1,4 @@
-use rusqlite::{Connection, Result};
+use rusqlite::{Connection, OptionalExtension, Result};
 use std::fs;
 use tauri::AppHandle;
 use tauri::Manager;
@@ -31,6 +31,11 @@ pub fn initialize_db(app: &AppHandle) -> Result<Connection, String> {
     // 4. Seed Data
     seed_db(&conn)?;
 
+    // 5. El estado de conexión no sobrevive a un reinicio (o a un crash):
+    //    arrancamos siempre desconectados y el ConnectionManager lo actualiza.
+    conn.execute("UPDATE connections SET is_connected = 0", [])
+        .map_err(|e| e.to_string())?;
+
     Ok(conn)
 }
 
@@ -83,7 +88,13 @@ pub fn init_tables(conn: &Connection) -> Result<(), String> {
             wss_host TEXT,
             wss_port INTEGER,
             is_connected BOOLEAN DEFAULT 0,
-            last_connected DATETIME
+            last_connected DATETIME,
+            reconnect_policy TEXT,
+            api_base_url TEXT,
+            route_rules TEXT,
+            offline_cache BOOLEAN DEFAULT 0,
+            offline_writes BOOLEAN DEFAULT 0,
+            mock_config TEXT
         )",
         [],
     )
@@ -104,6 +115,29 @@ pub fn init_tables(conn: &Connection) -> Result<(), String> {
         [],
     );
 
+    // Migración silenciosa: Política de reconexión (JSON) por conexión
+    let _ = conn.execute(
+        "ALTER TABLE connections ADD COLUMN reconnect_policy TEXT",
+        [],
+    );
+
+    // Migración silenciosa: URL base de la API y reglas de enrutado (JSON) del proxy /v1/
+    let _ = conn.execute("ALTER TABLE connections ADD COLUMN api_base_url TEXT", []);
+    let _ = conn.execute("ALTER TABLE connections ADD COLUMN route_rules TEXT", []);
+
+    // Migración silenciosa: Caché offline del proxy /v1/ (opt-in por conexión)
+    let _ = conn.execute(
+        "ALTER TABLE connections ADD COLUMN offline_cache BOOLEAN DEFAULT 0",
+        [],
+    );
+    // Migración silenciosa: Cola de escrituras /v1/ sin conexión (opt-in por conexión)
+    let _ = conn.execute(
+        "ALTER TABLE connections ADD COLUMN offline_writes BOOLEAN DEFAULT 0",
+        [],
+    );
+    // Migración silenciosa: Modo simulado del proxy /v1/ (JSON) por conexión
+    let _ = conn.execute("ALTER TABLE connections ADD COLUMN mock_config TEXT", []);
+
     conn.execute(
         "CREATE TABLE IF NOT EXISTS desktop_apps (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -117,7 +151,8 @@ pub fn init_tables(conn: &Connection) -> Result<(), String> {
             description TEXT,
             username TEXT,
             password TEXT,
-            token TEXT
+            token TEXT,
+            connection_id INTEGER
         )",
         [],
     )
@@ -129,6 +164,115 @@ pub fn init_tables(conn: &Connection) -> Result<(), String> {
     let _ = conn.execute("ALTER TABLE desktop_apps ADD COLUMN password TEXT", []);
     let _ = conn.execute("ALTER TABLE desktop_apps ADD COLUMN token TEXT", []);
 
+    // Migración silenciosa: Conexión (servidor) por la que se enruta el tráfico /v1/ de la app
+    let _ = conn.execute(
+        "ALTER TABLE desktop_apps ADD COLUMN connection_id INTEGER",
+        [],
+    );
+
+    // Cola persistente de mensajes hacia el servidor mientras el WebSocket está caído.
+    // message_id es único: un mismo mensaje nunca se encola dos veces.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS outbound_queue (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            message_id TEXT NOT NULL UNIQUE,
+            connection_id INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            body TEXT NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    // Migración: la clave única de proxy_cookies no incluía host_only. SQLite no permite
+    // cambiar un UNIQUE, así que se renombra la tabla antigua y se copian sus filas.
+    let cookies_schema: Option<String> = conn
+        .query_row(
+            "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'proxy_cookies'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let migrate_cookies =
+        cookies_schema.is_some_and(|sql| sql.contains("UNIQUE(app_id, domain, path, name)"));
+    if migrate_cookies {
+        conn.execute("ALTER TABLE proxy_cookies RENAME TO proxy_cookies_old", [])
+            .map_err(|e| e.to_string())?;
+    }
+
+    // Cookies de los sitios externos proxificados, separadas por app (app_id '' = contenedor).
+    // Una cookie de host y otra de dominio con el mismo nombre y path conviven (RFC 6265 §5.3).
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS proxy_cookies (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            app_id TEXT NOT NULL DEFAULT '',
+            domain TEXT NOT NULL,
+            host_only BOOLEAN NOT NULL DEFAULT 1,
+            path TEXT NOT NULL DEFAULT '/',
+            name TEXT NOT NULL,
+            value TEXT NOT NULL,
+            expires_at INTEGER,
+            secure BOOLEAN NOT NULL DEFAULT 0,
+            http_only BOOLEAN NOT NULL DEFAULT 0,
+            same_site TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(app_id, domain, host_only, path, name)
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    if migrate_cookies {
+        conn.execute_batch(
+            "INSERT OR IGNORE INTO proxy_cookies
+                (app_id, domain, host_only, path, name, value, expires_at, secure, http_only, same_site, created_at)
+             SELECT app_id, domain, host_only, path, name, value, expires_at, secure, http_only, same_site, created_at
+             FROM proxy_cookies_old;
+             DROP TABLE proxy_cookies_old;",
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    // Respuestas GET /v1/ guardadas para servirlas cuando el servidor no responde
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS proxy_cache (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            connection_id INTEGER NOT NULL,
+            app_id TEXT NOT NULL DEFAULT '',
+            url TEXT NOT NULL,
+            status INTEGER NOT NULL,
+            headers TEXT NOT NULL,
+            body BLOB NOT NULL,
+            size_bytes INTEGER NOT NULL,
+            stored_at INTEGER NOT NULL,
+            last_used_at INTEGER NOT NULL,
+            UNIQUE(connection_id, app_id, url)
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    // Escrituras /v1/ pendientes de reenviar, en orden de llegada por conexión
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS proxy_write_queue (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            queue_id TEXT NOT NULL UNIQUE,
+            connection_id INTEGER NOT NULL,
+            app_id TEXT NOT NULL DEFAULT '',
+            method TEXT NOT NULL,
+            url TEXT NOT NULL,
+            headers TEXT NOT NULL,
+            body BLOB NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            attempts INTEGER NOT NULL DEFAULT 0,
+            last_error TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
     Ok(())
 }
 
@@ -147,6 +291,31 @@ pub fn seed_db(conn: &Connection) -> Result<(), String> {
     Ok(())
 }
 
+/// Identificador estable de este cliente (se genera la primera vez).
+pub fn get_or_create_client_id(conn: &Connection) -> Result<String, String> {
+    let existing: Option<String> = conn
+        .query_row(
+            "SELECT value FROM config WHERE key = 'client_id'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    if let Some(uuid) = existing {
+        return Ok(uuid);
+    }
+
+    let new_uuid = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO config (key, value) VALUES ('client_id', ?1)",
+        [&new_uuid],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(new_uuid)
+}
+
 pub fn recreate_app_logs_table(conn: &rusqlite::Connection) -> Result<(), String> {
     // Elimina la tabla por completo (DROP)
     conn.execute("DROP TABLE IF EXISTS app_logs", [])
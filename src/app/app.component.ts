@@ -83,6 +83,8 @@ export class AppComponent implements OnInit {
 
   wsStatus: ConnectionStatus = "Desconectado";
   attemptNumber: number = 0;
+  // Último estado de cada conexión (connection_id -> status) para el indicador global
+  private connectionStatuses = new Map<number, string>();
 
   installModal = {
     show: false,
@@ -195,23 +197,27 @@ export class AppComponent implements OnInit {
       if (this.showControlPanel) this.loadConnections();
     });
 
-    // Global Connection Status Listener
+    // Global Connection Status Listener: el indicador resume todas las conexiones,
+    // no sólo la última que emitió
     await listen("connection-status", (event: any) => {
       console.log("Global connection status updated:", event.payload);
-      const s = event.payload as string;
-      if (s === "connected") {
-        this.wsStatus = "Conectado";
-      } else if (s === "disconnected") {
-        this.wsStatus = "Desconectado";
-      } else if (s === "connecting") {
-        this.wsStatus = "Reintentando";
-      } else if (s === "error") {
-        this.wsStatus = "Desconectado";
-      }
-      this.zone.run(() => {
-        // Trigger UI update
-      });
+      const id = event.payload?.connection_id;
+      if (id === undefined || id === null) return;
+      this.connectionStatuses.set(id, event.payload.status);
+      this.zone.run(() => this.updateGlobalStatus());
     });
+    // Estado de partida de las conexiones que ya estaban activas
+    try {
+      const snapshots = await this.sdcService.getConnectionState();
+      for (const snapshot of snapshots) {
+        if (!this.connectionStatuses.has(snapshot.connection_id)) {
+          this.connectionStatuses.set(snapshot.connection_id, snapshot.status);
+        }
+      }
+      this.zone.run(() => this.updateGlobalStatus());
+    } catch (e) {
+      console.error("Error loading connection state", e);
+    }
 
     // Initialize Client ID and Connections
     this.clientId = await this.sdcService.getClientId();
@@ -221,6 +227,23 @@ export class AppComponent implements OnInit {
     this.desktopAppsService.appsUpdated$.subscribe(() => {
       this.loadApps();
     });
+
+    // Apps instaladas/eliminadas desde el servidor remoto
+    await listen("remote-apps-changed", () => {
+      this.zone.run(() => this.loadApps());
+    });
+  }
+
+  // idle, connecting, connected, reconnecting, failed, disconnecting
+  updateGlobalStatus() {
+    const statuses = [...this.connectionStatuses.values()];
+    if (statuses.includes("connected")) {
+      this.wsStatus = "Conectado";
+    } else if (statuses.some((s) => s === "connecting" || s === "reconnecting")) {
+      this.wsStatus = "Reintentando";
+    } else {
+      this.wsStatus = "Desconectado";
+    }
   }
 
   async loadApps() {
@@ -308,7 +331,9 @@ export class AppComponent implements OnInit {
     try {
       await this.sdcService.disconnectFromServer(conn, this.clientId);
       this.activeConnection = null;
-      this.wsStatus = "Desconectado"; // Optimistic update
+      // Optimistic update (las demás conexiones siguen contando)
+      this.connectionStatuses.set(conn.id ?? 0, "idle");
+      this.updateGlobalStatus();
 
       // Update the connection in the list to reflect disconnected state
       // (assuming getConnections reads from DB where flag is updated)
@@ -5,6 +5,7 @@ import { Observable } from 'rxjs';
 import { AppStateService } from '../../core/services/app-state.service';
 import { LoggerService } from '../../core/services/logger.service';
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 
 interface AppLog {
   id?: number;
@@ -73,22 +74,38 @@ export class InspectorComponent implements OnInit {
         details: log.details
       };
 
-      // Add to session memory
-      if (!this.sessionLogs.has(targetAppId)) {
-        this.sessionLogs.set(targetAppId, []);
-      }
-      this.sessionLogs.get(targetAppId)?.unshift(appLog);
-
-      // If viewing this app, update UI immediately
-      if (this.currentTabId === targetAppId || (['dashboard', 'connections', 'security', 'monitor', 'system'].includes(this.currentTabId) && targetAppId === 'App.SDC')) {
-        this.loadLogsForActiveTab();
-      }
+      // Add to session memory (and update UI immediately if viewing this app)
+      this.addSessionLog(appLog);
     });
 
     this.activeTabId$.subscribe(id => {
       this.currentTabId = id;
       this.loadLogsForActiveTab();
     });
+
+    // Tráfico real que atraviesa el proxy (sandra-app://), capturado en el backend
+    listen<any>('proxy-traffic', event => {
+      const entry = event.payload;
+      this.addSessionLog({
+        app_id: entry.app_id || 'App.SDC',
+        log_type: 'FETCH',
+        message: `${entry.method} ${entry.url} -> ${entry.status || entry.error} (${entry.duration_ms} ms)`,
+        source: `proxy:${entry.kind}`,
+        timestamp: entry.started_at,
+        details: entry
+      });
+    });
+  }
+
+  private addSessionLog(appLog: AppLog) {
+    if (!this.sessionLogs.has(appLog.app_id)) {
+      this.sessionLogs.set(appLog.app_id, []);
+    }
+    this.sessionLogs.get(appLog.app_id)?.unshift(appLog);
+
+    if (this.currentTabId === appLog.app_id || (['dashboard', 'connections', 'security', 'monitor', 'system'].includes(this.currentTabId) && appLog.app_id === 'App.SDC')) {
+      this.loadLogsForActiveTab();
+    }
   }
 
   async loadLogsForActiveTab() {
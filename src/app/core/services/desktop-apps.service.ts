@@ -15,6 +15,7 @@ export interface DesktopApp {
   username?: string;
   password?: string;
   token?: string;
+  connection_id?: number | null; // Conexión para el tráfico /v1/ (null = la primera activa)
   action?: string; // Optional for mapped actions like 'toggleCP'
 }
 
@@ -42,6 +43,11 @@ export class DesktopAppsService {
     this.appsUpdatedSubject.next(); // Notify
   }
 
+  async setAppConnection(appId: string, connectionId: number | null): Promise<void> {
+    await invoke("set_app_connection", { appId, connectionId });
+    this.appsUpdatedSubject.next(); // Notify
+  }
+
   async deleteApp(appId: string): Promise<void> {
     await invoke("delete_app", { appId: appId });
     this.appsUpdatedSubject.next(); // Notify
@@ -1,5 +1,5 @@
 import { Injectable } from '@angular/core';
-import { invoke } from '@tauri-apps/api/core';
+import { Channel, invoke } from '@tauri-apps/api/core';
 import { SystemStats } from '../models/telemetry.model';
 
 @Injectable({
@@ -63,6 +63,104 @@ export class SdcService {
     return await invoke('disconnect_from_server', { connData: connection, clientId });
   }
 
+  async retryNow(connectionId?: number): Promise<void> {
+    return await invoke('retry_now', { connectionId });
+  }
+
+  async getConnectionState(connectionId?: number): Promise<any[]> {
+    return await invoke('get_connection_state', { connectionId });
+  }
+
+  async getOutboundQueue(connectionId?: number): Promise<any[]> {
+    return await invoke('get_outbound_queue', { connectionId });
+  }
+
+  async clearOutboundQueue(connectionId?: number): Promise<number> {
+    return await invoke('clear_outbound_queue', { connectionId });
+  }
+
+  async getRuntimeConfig(): Promise<any> {
+    return await invoke('get_runtime_config');
+  }
+
+  async getExternalSessions(): Promise<any[]> {
+    return await invoke('get_external_sessions');
+  }
+
+  async clearExternalSessions(webview?: string): Promise<number> {
+    return await invoke('clear_external_sessions', { webview });
+  }
+
+  async getProxyCookies(appId?: string, site?: string): Promise<any[]> {
+    return await invoke('get_proxy_cookies', { appId, site });
+  }
+
+  async clearProxyCookies(appId?: string, site?: string): Promise<number> {
+    return await invoke('clear_proxy_cookies', { appId, site });
+  }
+
+  async getExternalProxyDenials(limit?: number): Promise<any[]> {
+    return await invoke('get_external_proxy_denials', { limit });
+  }
+
+  async getProxyTraffic(appId?: string, limit?: number): Promise<any[]> {
+    return await invoke('get_proxy_traffic', { appId, limit });
+  }
+
+  async clearProxyTraffic(appId?: string): Promise<number> {
+    return await invoke('clear_proxy_traffic', { appId });
+  }
+
+  async getProxyCaptureOptions(): Promise<any> {
+    return await invoke('get_proxy_capture_options');
+  }
+
+  async setProxyCaptureOptions(options: { enabled: boolean; bodies: boolean; max_body_bytes: number }): Promise<any> {
+    return await invoke('set_proxy_capture_options', { options });
+  }
+
+  async exportProxyHar(targetPath: string, appId?: string): Promise<number> {
+    return await invoke('export_proxy_har', { appId, targetPath });
+  }
+
+  async getProxyCacheStats(): Promise<any> {
+    return await invoke('get_proxy_cache_stats');
+  }
+
+  async purgeProxyCache(connectionId?: number, appId?: string): Promise<number> {
+    return await invoke('purge_proxy_cache', { connectionId, appId });
+  }
+
+  async getWriteQueue(connectionId?: number, appId?: string): Promise<any[]> {
+    return await invoke('get_write_queue', { connectionId, appId });
+  }
+
+  async replayWriteQueue(connectionId: number): Promise<void> {
+    return await invoke('replay_write_queue', { connectionId });
+  }
+
+  async discardQueuedWrite(queueId: string): Promise<number> {
+    return await invoke('discard_queued_write', { queueId });
+  }
+
+  async openAppSocket(path: string, onEvent: (event: any) => void): Promise<string> {
+    const channel = new Channel<any>();
+    channel.onmessage = onEvent;
+    return await invoke('open_app_socket', { path, onEvent: channel });
+  }
+
+  async sendAppSocket(socketId: string, data: string, binary = false): Promise<void> {
+    return await invoke('send_app_socket', { socketId, data, binary });
+  }
+
+  async closeAppSocket(socketId: string): Promise<boolean> {
+    return await invoke('close_app_socket', { socketId });
+  }
+
+  async downloadAppFile(path: string, fileName?: string): Promise<{ path: string; bytes: number }> {
+    return await invoke('download_app_file', { path, fileName });
+  }
+
 
 
   async getClientId(): Promise<string> {
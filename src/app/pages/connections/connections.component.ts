@@ -22,6 +22,12 @@ interface Connection {
   wss_host?: string;
   wss_port?: number;
   is_connected?: boolean;
+  reconnect_policy?: any;
+  api_base_url?: string;
+  route_rules?: { path: string; upstream: string }[];
+  offline_cache?: boolean;
+  offline_writes?: boolean;
+  mock?: { enabled: boolean; fixtures_dir?: string; har_file?: string; latency_ms?: number; jitter_ms?: number; error_rate?: number; error_status?: number };
 }
 
 @Component({
@@ -120,15 +126,36 @@ export class ConnectionsComponente implements OnInit, OnDestroy {
 
     // Listen to global connection status
     this.unlistenFn = await listen("connection-status", (event: any) => {
-      const status = event.payload as string; // connecting, connected, disconnected, error
-      // console.log('Connection Status Event:', status);
+      // Sólo la conexión del formulario (sin perfil guardado es la ad hoc, id 0)
+      if (event.payload?.connection_id !== (this.form.id ?? 0)) return;
+
+      // idle, connecting, connected, reconnecting, failed, disconnecting
+      const state = event.payload?.status as string;
+      // console.log('Connection Status Event:', event.payload);
+
+      // Los reintentos del backoff no vuelven a abrir los modales:
+      // sólo el primer intento (attempt 0) y el primer fallo (attempt 1)
+      const attempt = event.payload?.state?.attempt ?? 0;
+      const retrying =
+        (state === "connecting" && attempt > 0) ||
+        (state === "reconnecting" && attempt > 1);
+
+      const status =
+        state === "idle"
+          ? "disconnected"
+          : state === "reconnecting" || state === "failed"
+            ? "error"
+            : state;
+      if (state === "disconnecting") return;
 
       this.connectionState = status as any;
 
       if (status === "connecting") {
-        this.showModal = true;
-        this.connStatusMsg = "Estableciendo enlace seguro...";
-        this.connProgress = 30;
+        if (!retrying) {
+          this.showModal = true;
+          this.connStatusMsg = "Estableciendo enlace seguro...";
+          this.connProgress = 30;
+        }
       } else if (status === "connected") {
         this.connProgress = 100;
         this.connStatusMsg = "¡Conectado!";
@@ -138,11 +165,13 @@ export class ConnectionsComponente implements OnInit, OnDestroy {
           this.loadSavedConnections().then(() => this.syncFormStatus()); // Sync UI
         }, 800);
       } else if (status === "error") {
-        this.connStatusMsg = "Error en la conexión.";
-        this.verifyStatus = "error";
-        setTimeout(() => {
-          this.showModal = false;
-        }, 2000);
+        if (!retrying) {
+          this.connStatusMsg = "Error en la conexión.";
+          this.verifyStatus = "error";
+          setTimeout(() => {
+            this.showModal = false;
+          }, 2000);
+        }
         this.loadSavedConnections().then(() => this.syncFormStatus());
       } else if (status === "disconnected") {
         this.loadSavedConnections().then(() => this.syncFormStatus());